@@ -0,0 +1,7 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    // the pbr shader library exported by wgx_pbr_shaders, included as "wgx_pbr::pbr.wgsl"
+    #[cfg(feature = "pbr")]
+    wgsl_modules_loader::build::import_packages().unwrap();
+}
@@ -106,7 +106,7 @@ async fn init_app(app_ctx: &mut AppCtx) -> impl FnMut(&mut AppCtx, &AppEvent) {
         output.clipped_primitives.extend_from_slice(&add_primitives);
 
         // draw
-        target.with_frame(None, |frame| gx.with_encoder(|encoder| {
+        target.with_frame(&gx, None, |frame| gx.with_encoder(|encoder| {
 
           output.prepare(&mut egs_renderer, &gx, encoder);
 
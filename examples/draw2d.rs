@@ -0,0 +1,103 @@
+
+use platform::winit::{
+  window::WindowAttributes, event::{WindowEvent, KeyEvent, ElementState}, keyboard::{PhysicalKey, KeyCode},
+  dpi::PhysicalSize,
+};
+use platform::{*, time::*};
+use wgx::{*, math::*, draw2d::*};
+
+
+main_app_closure! {
+    LogLevel::Warn,
+    WindowAttributes::default().with_inner_size(PhysicalSize::new(1000, 1000)),
+    init_app,
+}
+
+async fn init_app(ctx: &mut AppCtx) -> impl FnMut(&mut AppCtx, &AppEvent) {
+
+    let window = ctx.window_clone();
+
+    let srgb = true;
+    let msaa = 4;
+    let depth_testing = None;
+
+    let (gx, mut target) = Wgx::new_with_target(window.clone(), features!(), limits!{}, window.inner_size(), srgb, msaa, depth_testing).await.unwrap();
+
+    let mut draw = Draw2d::new(&gx, &target).unwrap();
+    let mut batch = Batch::for_target(&target);
+
+    // picture
+    let img = image::load_from_memory(include_bytes!("common/img/logo_red.png"))
+        .expect("failed loading image")
+        .into_rgba8()
+    ;
+
+    let (w, h) = (img.width(), img.height());
+    let image_texture = TextureLot::new_2d_with_data(&gx, [w, h, 1], 1, DEFAULT_SRGB, None, TexUse::TEXTURE_BINDING, &img.as_raw()[..]);
+    let image = draw.add_texture(&gx, &image_texture.view, wgpu::FilterMode::Linear).unwrap();
+
+    let then = Instant::now();
+
+    // event loop
+
+    move |_ctx: &mut AppCtx, event: &AppEvent| match event {
+
+        AppEvent::WindowEvent(WindowEvent::Resized(size)) => {
+            target.update(&gx, *size);
+            batch.resize(target.region().size());
+        },
+
+        AppEvent::WindowEvent(WindowEvent::KeyboardInput { event: KeyEvent {
+            state: ElementState::Pressed, physical_key: PhysicalKey::Code(KeyCode::KeyR), ..
+        }, ..}) => {
+            window.request_redraw();
+        },
+
+        AppEvent::WindowEvent(WindowEvent::RedrawRequested) => {
+
+            let t = then.elapsed().as_secs_f32();
+
+            batch.rect([50.0, 50.0], [200.0, 120.0], Color::RED);
+            batch.rounded_rect([300.0, 50.0], [200.0, 120.0], 24.0, Color::BLUE);
+            batch.stroke_rounded_rect([300.0, 50.0], [200.0, 120.0], 24.0, Stroke::new(4.0), Color::BLACK);
+            batch.circle([700.0, 110.0], 60.0, Color::GREEN);
+            batch.stroke_circle([700.0, 110.0], 60.0, Stroke::new(6.0), Color::from_u8([0, 0, 0, 128]));
+
+            let zigzag: Vec<Vec2> = (0..8).map(|i| Vec2::new(60.0 + i as f32 * 60.0, if i % 2 == 0 { 300.0 } else { 360.0 })).collect();
+
+            for (k, join) in [LineJoin::Miter, LineJoin::Bevel, LineJoin::Round].into_iter().enumerate() {
+                batch.with_transform(Mat4::from_translation(Vec3::new(0.0, k as f32 * 100.0, 0.0)), |batch| {
+                    batch.polyline(&zigzag, false, Stroke::new(16.0).join(join).cap(LineCap::Round), Color::from_u8([0, 0, 255, 160]));
+                });
+            }
+
+            batch.polygon(&[
+                Vec2::new(600.0, 300.0), Vec2::new(900.0, 300.0), Vec2::new(900.0, 550.0),
+                Vec2::new(750.0, 400.0), Vec2::new(600.0, 550.0),
+            ], Color::ORANGE);
+
+            // rotating picture
+            batch.push();
+            batch.translate([750.0, 800.0]);
+            batch.rotate(t);
+            batch.image(image, [-100.0, -100.0], [200.0, 200.0], None, Color::WHITE);
+            batch.pop();
+
+            batch.set_blend(BlendMode::Additive);
+            batch.circle([200.0, 800.0], 100.0, Color::new(0.5, 0.0, 0.0, 1.0));
+            batch.circle([280.0, 800.0], 100.0, Color::new(0.0, 0.5, 0.0, 1.0));
+
+            target.with_frame(&gx, None, |frame| gx.with_encoder(|encoder| {
+                encoder.with_render_pass(frame.attachments(Some(Color::WHITE), None, None), |rpass| {
+                    draw.flush(&gx, rpass, &mut batch);
+                });
+            })).expect("frame error");
+
+            draw.reset();
+
+            window.request_redraw();
+        },
+
+        _ => {}
+    }
+}
@@ -0,0 +1,124 @@
+use platform::winit::{
+  window::WindowAttributes, event::{WindowEvent, KeyEvent, ElementState}, keyboard::{PhysicalKey, KeyCode},
+  dpi::PhysicalSize,
+};
+use platform::*;
+use wgx::{*, math::*, mesh::*, pbr::*, camera::*};
+
+
+main_app_closure! {
+  LogLevel::Warn,
+  WindowAttributes::default().with_inner_size(PhysicalSize::new(1000, 1000)),
+  init_app,
+}
+
+async fn init_app(ctx: &mut AppCtx) -> impl FnMut(&mut AppCtx, &AppEvent) {
+
+  let window = ctx.window_clone();
+
+  let srgb = true;
+  let msaa = 4;
+  let depth_testing = Some(DEFAULT_DEPTH);
+
+  let (gx, mut target) = Wgx::new_with_target(window.clone(), features!(), limits!{}, window.inner_size(), srgb, msaa, depth_testing).await.unwrap();
+
+  // renderer with one shadow layer
+  let environment = Environment::uniform(&gx, Color::new_rgb(0.15, 0.18, 0.22)).unwrap();
+  let mut renderer = PbrRenderer::new(&gx, &target, environment, 2048, 1).unwrap();
+
+  renderer.lights = vec![
+    Light::directional([-0.5, -1.0, 0.8], Color::WHITE, 3.0).shadow(true),
+    Light::point([2.5, 1.5, -2.5], Color::ORANGE.linear(), 15.0).range(8.0),
+  ];
+
+  renderer.shadow_bounds = BoundingSphere { center: Vec3::ZERO, radius: 6.0 };
+
+  // meshes
+  let schema = vertex_schema();
+  let sphere = MeshData::uv_sphere(0.4, 48, 24).upload(&gx, &schema).unwrap();
+  let ground = MeshData::plane([10.0, 10.0], [1, 1]).upload(&gx, &schema).unwrap();
+
+  // metallic increasing along z, roughness along x
+  let mut materials = Vec::new();
+  let mut instances = Vec::new();
+
+  for row in 0..5 {
+    for column in 0..5 {
+      let material = PbrMaterial::new(Color::new_rgb(0.9, 0.3, 0.1).linear(), row as f32 / 4.0, (column as f32 / 4.0).max(0.05));
+      materials.push(renderer.add_material(&gx, &material).unwrap());
+      instances.push(PbrInstance::from(Mat4::from_translation(Vec3::new(column as f32 - 2.0, 0.5, row as f32 - 2.0))));
+    }
+  }
+
+  let ground_material = renderer.add_material(&gx, &PbrMaterial::new(Color::new_rgb(0.5, 0.5, 0.5), 0.0, 0.8)).unwrap();
+  instances.push(PbrInstance::from(Mat4::IDENTITY));
+
+  let spheres = 0..materials.len() as u32;
+  let ground_instance = spheres.end..spheres.end + 1;
+
+  let instance_buffer = gx.buffer_from_data(BufUse::VERTEX, instances.as_slice());
+
+  // camera orbiting the center, A/D and W/S rotate, Z/X zoom
+  let mut projection = Projection::perspective(f32::to_radians(45.0), 1.0, 0.1, 100.0);
+  projection.resize(target.size());
+
+  let mut camera = Camera::looking_at([0.0, 4.5, -7.0], [0.0, 0.0, 0.0], projection);
+  renderer.set_projection(&gx, &camera.projection);
+
+  let mut controller = OrbitController::from_camera(&camera, Vec3::ZERO);
+  controller.sensitivity = f32::to_radians(5.0);
+  controller.min_distance = 2.0;
+
+
+  // event loop
+
+  move |_ctx: &mut AppCtx, event: &AppEvent| match event {
+
+    AppEvent::WindowEvent(WindowEvent::Resized(size)) => {
+      target.update(&gx, *size);
+      camera.projection.resize(target.size());
+    },
+
+    AppEvent::WindowEvent(WindowEvent::KeyboardInput { event: KeyEvent {
+      physical_key: PhysicalKey::Code(keycode), state: ElementState::Pressed, ..
+    }, ..}) => {
+      let mut input = CameraInput::default();
+
+      match keycode {
+        KeyCode::KeyA => input.rotation.x = 1.0, KeyCode::KeyD => input.rotation.x = -1.0,
+        KeyCode::KeyW => input.rotation.y = -1.0, KeyCode::KeyS => input.rotation.y = 1.0,
+        KeyCode::KeyX => input.zoom = 1.0, KeyCode::KeyZ => input.zoom = -1.0,
+        _ => return,
+      }
+
+      controller.update(&mut camera, &input, 1.0);
+      window.request_redraw();
+    },
+
+    AppEvent::WindowEvent(WindowEvent::RedrawRequested) => {
+
+      renderer.prepare(&gx, camera.view_projection(), camera.position).unwrap();
+
+      target.with_frame(&gx, None, |frame| gx.with_encoder(|encoder| {
+
+        renderer.render_shadows(encoder, |rpass| {
+          renderer.draw_shadow_caster(rpass, &sphere, &instance_buffer, spheres.clone());
+          renderer.draw_shadow_caster(rpass, &ground, &instance_buffer, ground_instance.clone());
+        });
+
+        encoder.with_render_pass(frame.attachments(Some(Color::BLACK), Some(1.0), None), |rpass| {
+          renderer.bind_frame(rpass);
+
+          for (i, material) in materials.iter().enumerate() {
+            renderer.draw(rpass, &sphere, *material, &instance_buffer, i as u32..i as u32 + 1).unwrap();
+          }
+
+          renderer.draw(rpass, &ground, ground_material, &instance_buffer, ground_instance.clone()).unwrap();
+        });
+
+      })).expect("frame error");
+    },
+
+    _ => {}
+  }
+}
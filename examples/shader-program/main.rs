@@ -38,12 +38,12 @@ async fn init_app(ctx: &mut AppCtx) -> impl FnMut(&mut AppCtx, &AppEvent) {
   // pipeline
   let shader = gx.load_wgsl(shader_src);
 
-  let layout = gx.layout(&[
+  let layout = gx.layout_dsc(&[
     binding!(0, Stage::VERTEX_FRAGMENT, UniformBuffer, 16),
   ]);
 
   let pipeline = target.render_pipeline(&gx,
-    Some((push_constants![0..4 => Stage::FRAGMENT], &[&layout])),
+    Some((push_constants![0..4 => Stage::FRAGMENT], &[&layout.layout])),
     &[vertex_dsc!(Vertex, 0 => Float32x2)],
     (&shader, "vs_main", Primitive::default()),
     (&shader, "fs_main", blending),
@@ -71,9 +71,7 @@ async fn init_app(ctx: &mut AppCtx) -> impl FnMut(&mut AppCtx, &AppEvent) {
   let view_buffer = gx.buffer_from_data(BufUse::UNIFORM | BufUse::COPY_DST, [width, height, width/height, scale]);
 
   // binding
-  let binding = gx.bind(&layout, &[
-    bind!(0, Buffer, &view_buffer),
-  ]);
+  let binding = layout.bind(&gx).buffer(0, &view_buffer).finish().unwrap();
 
 
   // event loop
@@ -113,7 +111,7 @@ async fn init_app(ctx: &mut AppCtx) -> impl FnMut(&mut AppCtx, &AppEvent) {
     AppEvent::WindowEvent(WindowEvent::RedrawRequested) => {
 
       // draw
-      target.with_frame(None, |frame| gx.with_encoder(|encoder| {
+      target.with_frame(&gx, None, |frame| gx.with_encoder(|encoder| {
         encoder.with_render_pass(frame.attachments(Some(Color::BLACK), None, None), |rpass| {
           rpass.set_pipeline(&pipeline);
           rpass.set_bind_group(0, &binding, &[]);
@@ -1,18 +1,13 @@
 
 
 use platform::winit::{
-  window::WindowAttributes, event::{WindowEvent, KeyEvent, ElementState}, keyboard::PhysicalKey,
+  window::WindowAttributes, event::{WindowEvent, KeyEvent, ElementState}, keyboard::{PhysicalKey, KeyCode},
   dpi::PhysicalSize,
 };
 use platform::{*, time::*};
-use wgx::{*, math::*};
+use wgx::{*, math::*, camera::*};
 use std::ops::Neg;
 
-// common
-#[path="common/world_view.rs"] #[allow(dead_code)]
-mod world_view;
-use world_view::*;
-
 
 main_app_closure! {
   LogLevel::Warn,
@@ -165,25 +160,25 @@ async fn init_app(ctx: &mut AppCtx) -> impl FnMut(&mut AppCtx, &AppEvent) {
   let instance_buffer = gx.buffer_from_data(BufUse::VERTEX, instance_data);
 
 
-  // world
-  let [width, height] = window.inner_size().into();
-  let mut world = WorldView::new(&gx, 10.0, 5.0, 0.1, FovProjection::window(45.0, width, height));
-
-  world.objects = Mat4::from_uniform_scale(0.25 * height);
-  world.calc_clip_matrix();
+  // camera orbiting the unit sphere, A/D and W/S rotate, Z/X zoom, R resets
+  let mut projection = Projection::perspective(f32::to_radians(45.0), 1.0, 0.01, 100.0);
+  projection.resize(target.size());
 
-  let light_matrix = Mat4::from_rotation_x(f32::to_radians(-30.0));
+  let mut camera = Camera::looking_at([0.0, 0.0, -5.0], [0.0, 0.0, 0.0], projection);
+  let mut controller = OrbitController::from_camera(&camera, Vec3::ZERO);
+  controller.sensitivity = f32::to_radians(5.0);
+  let start = controller.clone();
 
-  world.light_matrix = light_matrix * world.rotation; // keep light
+  let clip_buffer = gx.buffer_from_data(BufUse::UNIFORM | BufUse::COPY_DST, camera.view_projection());
 
-  world.write_clip_buffer(&gx);
-  world.write_light_buffer(&gx);
+  // light fixed in world space
+  let light_buffer = gx.buffer_from_data(BufUse::UNIFORM, Mat4::from_rotation_x(f32::to_radians(-30.0)));
 
 
   // bind
   let binding = gx.bind(&pipeline.get_bind_group_layout(0), &[
-    bind!(0, Buffer, world.clip_buffer),
-    bind!(1, Buffer, world.light_buffer),
+    bind!(0, Buffer, clip_buffer),
+    bind!(1, Buffer, light_buffer),
     bind!(2, TextureView, &color_texture.view),
     bind!(3, Sampler, &sampler),
   ]);
@@ -203,31 +198,33 @@ async fn init_app(ctx: &mut AppCtx) -> impl FnMut(&mut AppCtx, &AppEvent) {
 
     AppEvent::WindowEvent(WindowEvent::Resized(size)) => {
       target.update(&gx, *size);
-      world.fov.resize_window(size.width as f32, size.height as f32, true);
-      world.calc_clip_matrix();
-      // world.light_matrix = light_matrix * world.rotation; // keep light
-      world.write_clip_buffer(&gx);
-      world.write_light_buffer(&gx);
+      camera.projection.resize(target.size());
+      gx.write_buffer(&clip_buffer, 0, camera.view_projection());
     },
 
     AppEvent::WindowEvent(WindowEvent::KeyboardInput { event: KeyEvent {
       physical_key: PhysicalKey::Code(keycode), state: ElementState::Pressed, ..
     }, ..}) => {
-      if let Some(key) = InputKey::match_keycode(*keycode) {
-        world.input(key);
-        world.calc_clip_matrix();
-        // world.light_matrix = light_matrix * world.rotation; // keep light
-        world.write_clip_buffer(&gx);
-        world.write_light_buffer(&gx);
-        window.request_redraw();
+      let mut input = CameraInput::default();
+
+      match keycode {
+        KeyCode::KeyA => input.rotation.x = 1.0, KeyCode::KeyD => input.rotation.x = -1.0,
+        KeyCode::KeyW => input.rotation.y = -1.0, KeyCode::KeyS => input.rotation.y = 1.0,
+        KeyCode::KeyX => input.zoom = 1.0, KeyCode::KeyZ => input.zoom = -1.0,
+        KeyCode::KeyR => controller = start.clone(),
+        _ => return,
       }
+
+      controller.update(&mut camera, &input, 1.0);
+      gx.write_buffer(&clip_buffer, 0, camera.view_projection());
+      window.request_redraw();
     },
 
     AppEvent::WindowEvent(WindowEvent::RedrawRequested) => {
 
       let then = Instant::now();
 
-      target.with_frame(None, |frame| gx.with_encoder(|encoder| {
+      target.with_frame(&gx, None, |frame| gx.with_encoder(|encoder| {
         encoder.pass_bundles(frame.attachments(Some(Color::BLACK), Some(1.0), None), &bundles);
       })).expect("frame error");
 
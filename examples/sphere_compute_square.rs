@@ -1,15 +1,10 @@
 
 use platform::winit::{
-  window::WindowAttributes, event::{WindowEvent, KeyEvent, ElementState}, keyboard::PhysicalKey,
+  window::WindowAttributes, event::{WindowEvent, KeyEvent, ElementState}, keyboard::{PhysicalKey, KeyCode},
   dpi::PhysicalSize,
 };
 use platform::{*, time::*};
-use wgx::{*, math::*};
-
-// common
-#[path="common/world_view.rs"] #[allow(dead_code)]
-mod world_view;
-use world_view::*;
+use wgx::{*, math::*, camera::*};
 
 
 main_app_closure! {
@@ -69,7 +64,7 @@ async fn init_app(ctx: &mut AppCtx) -> impl FnMut(&mut AppCtx, &AppEvent) {
 
   println!("mesh_len: {mesh_len:#}");
 
-  let vertex_buffer = gx.buffer(BufUse::STORAGE | BufUse::VERTEX /*| BufUse::MAP_READ*/, mesh_size, false);
+  let vertex_buffer = gx.buffer(BufUse::STORAGE | BufUse::VERTEX | BufUse::COPY_SRC, mesh_size, false);
 
   let layout = gx.layout(&[binding!(0, Stage::COMPUTE, StorageBuffer, mesh_size, false)]);
 
@@ -88,18 +83,33 @@ async fn init_app(ctx: &mut AppCtx) -> impl FnMut(&mut AppCtx, &AppEvent) {
   });
 
 
-  // read out the first triangles
-  /*vertex_buffer.with_map_sync(&gx, 0..(3*vertex_size), MapMode::Read, |buffer_slice| {
+  // export the generated geometry for inspection, with EXPORT_MESH=path.obj or path.ply
+  if let Some(path) = std::env::var_os("EXPORT_MESH").map(std::path::PathBuf::from) {
 
-    let mapped = buffer_slice.get_mapped_range();
-    let vertices: &[Vertex] = unsafe { mapped.align_to().1 };
-    // let vertices: Vec<_> = vertices.iter().map(|v| v[0]).collect();
-    // let vertices: &[Vertex] = unsafe { vertices.align_to().1 };
-    let vertices: Vec<_> = vertices.iter().map(|v| format!("{:?}", v)).collect();
+    let readback = gx.buffer(BufUse::MAP_READ | BufUse::COPY_DST, mesh_size, false);
 
-    eprintln!("{:#?}", vertices);
+    gx.with_encoder(|encoder| {
+      encoder.copy_buffer_to_buffer(&vertex_buffer, 0, &readback, 0, mesh_size);
+    });
 
-  }).unwrap();*/
+    let mesh = readback.with_map_sync(&gx, .., MapMode::Read, |buffer_slice| {
+      let mapped = buffer_slice.get_mapped_range();
+      let vertices: &[Vertex] = unsafe { mapped.align_to().1 };
+      wgx::mesh::MeshData {
+        positions: vertices.iter().map(|v| Vec3::from(v[0])).collect(),
+        normals: vertices.iter().map(|v| Vec3::from(v[2])).collect(),
+        ..Default::default()
+      }
+    }).unwrap();
+
+    let saved = match path.extension().and_then(|ext| ext.to_str()) {
+      Some("ply") => wgx::ply::save(&path, &mesh, wgx::ply::PlyFormat::BinaryLittleEndian),
+      _ => wgx::obj::save(&path, &mesh),
+    };
+    saved.unwrap();
+
+    println!("exported {} vertices to {}", mesh.vertex_count(), path.display());
+  }
 
 
   // instance data
@@ -123,26 +133,28 @@ async fn init_app(ctx: &mut AppCtx) -> impl FnMut(&mut AppCtx, &AppEvent) {
   let instance_buffer = gx.buffer_from_data(BufUse::VERTEX, instance_data);
 
 
-  // world
-  let PhysicalSize { width, height } = window.inner_size();
-  let (width, height) = (width as f32, height as f32);
+  // camera orbiting the unit sphere, A/D and W/S rotate, Z/X zoom, R resets
+  let mut projection = Projection::perspective(f32::to_radians(45.0), 1.0, 0.01, 100.0);
+  projection.resize(target.size());
 
-  let mut world = WorldView::new(&gx, 10.0, 5.0, 0.1, FovProjection::window(45.0, width, height));
+  let mut camera = Camera::looking_at([0.0, 0.0, -5.0], [0.0, 0.0, 0.0], projection);
+  let mut controller = OrbitController::from_camera(&camera, Vec3::ZERO);
+  controller.sensitivity = f32::to_radians(5.0);
+  let start = controller.clone();
 
-  world.objects = Mat4::from_uniform_scale(0.25 * height);
-  world.calc_clip_matrix();
+  let clip_buffer = gx.buffer(BufUse::UNIFORM | BufUse::COPY_DST, 64, false);
+  let light_buffer = gx.buffer(BufUse::UNIFORM | BufUse::COPY_DST, 64, false);
 
+  // light relative to the camera
   let light_matrix = Mat4::from_rotation_x(f32::to_radians(-30.0));
 
-  world.light_matrix = light_matrix * world.rotation; // keep light
-
 
   // staging belt
-  let mut staging_belt = StagingBelt::new(4 * world.clip_buffer.size());
+  let mut staging_belt = StagingBelt::new(4 * clip_buffer.size());
 
   gx.with_encoder(|mut encoder| {
-    staging_belt.write_data(&gx, &mut encoder, &world.clip_buffer, 0, world.clip_matrix);
-    staging_belt.write_data(&gx, &mut encoder, &world.light_buffer, 0, world.light_matrix);
+    staging_belt.write_data(&gx, &mut encoder, &clip_buffer, 0, camera.view_projection());
+    staging_belt.write_data(&gx, &mut encoder, &light_buffer, 0, light_matrix * Mat4::from_quat(camera.rotation.inverse()));
     staging_belt.finish();
   });
   staging_belt.recall();
@@ -150,8 +162,8 @@ async fn init_app(ctx: &mut AppCtx) -> impl FnMut(&mut AppCtx, &AppEvent) {
 
   // bind
   let binding = gx.bind(&pipeline.get_bind_group_layout(0), &[
-    bind!(0, Buffer, world.clip_buffer),
-    bind!(1, Buffer, world.light_buffer),
+    bind!(0, Buffer, clip_buffer),
+    bind!(1, Buffer, light_buffer),
     bind!(2, TextureView, &color_texture.view),
     bind!(3, Sampler, &sampler),
   ]);
@@ -172,13 +184,11 @@ async fn init_app(ctx: &mut AppCtx) -> impl FnMut(&mut AppCtx, &AppEvent) {
 
     AppEvent::WindowEvent(WindowEvent::Resized(size)) => {
       target.update(&gx, *size);
-      world.fov.resize_window(size.width as f32, size.height as f32, true);
-      world.calc_clip_matrix();
-      world.light_matrix = light_matrix * world.rotation; // keep light
+      camera.projection.resize(target.size());
 
       gx.with_encoder(|mut encoder| {
-        staging_belt.write_data(&gx, &mut encoder, &world.clip_buffer, 0, world.clip_matrix);
-        staging_belt.write_data(&gx, &mut encoder, &world.light_buffer, 0, world.light_matrix);
+        staging_belt.write_data(&gx, &mut encoder, &clip_buffer, 0, camera.view_projection());
+        staging_belt.write_data(&gx, &mut encoder, &light_buffer, 0, light_matrix * Mat4::from_quat(camera.rotation.inverse()));
         staging_belt.finish();
       });
       staging_belt.recall();
@@ -187,27 +197,33 @@ async fn init_app(ctx: &mut AppCtx) -> impl FnMut(&mut AppCtx, &AppEvent) {
     AppEvent::WindowEvent(WindowEvent::KeyboardInput { event: KeyEvent {
       physical_key: PhysicalKey::Code(keycode), state: ElementState::Pressed, ..
     }, ..}) => {
-      if let Some(key) = InputKey::match_keycode(*keycode) {
-        world.input(key);
-        world.calc_clip_matrix();
-        world.light_matrix = light_matrix * world.rotation; // keep light
-
-        gx.with_encoder(|mut encoder| {
-          staging_belt.write_data(&gx, &mut encoder, &world.clip_buffer, 0, world.clip_matrix);
-          staging_belt.write_data(&gx, &mut encoder, &world.light_buffer, 0, world.light_matrix);
-          staging_belt.finish();
-        });
-        staging_belt.recall();
-
-        window.request_redraw();
+      let mut input = CameraInput::default();
+
+      match keycode {
+        KeyCode::KeyA => input.rotation.x = 1.0, KeyCode::KeyD => input.rotation.x = -1.0,
+        KeyCode::KeyW => input.rotation.y = -1.0, KeyCode::KeyS => input.rotation.y = 1.0,
+        KeyCode::KeyX => input.zoom = 1.0, KeyCode::KeyZ => input.zoom = -1.0,
+        KeyCode::KeyR => controller = start.clone(),
+        _ => return,
       }
+
+      controller.update(&mut camera, &input, 1.0);
+
+      gx.with_encoder(|mut encoder| {
+        staging_belt.write_data(&gx, &mut encoder, &clip_buffer, 0, camera.view_projection());
+        staging_belt.write_data(&gx, &mut encoder, &light_buffer, 0, light_matrix * Mat4::from_quat(camera.rotation.inverse()));
+        staging_belt.finish();
+      });
+      staging_belt.recall();
+
+      window.request_redraw();
     },
 
     AppEvent::WindowEvent(WindowEvent::RedrawRequested) => {
 
       let then = Instant::now();
 
-      target.with_frame(None, |frame| gx.with_encoder(|encoder| {
+      target.with_frame(&gx, None, |frame| gx.with_encoder(|encoder| {
         encoder.pass_bundles(frame.attachments(Some(bg_color), Some(1.0), None), &bundles);
       })).expect("frame error");
 
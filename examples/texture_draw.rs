@@ -83,7 +83,7 @@ async fn init_app(ctx: &mut AppCtx) -> impl FnMut(&mut AppCtx, &AppEvent) {
         bind!(1, Sampler, &sampler),
     ]);
 
-    target.with_frame(None, |frame| gx.with_encoder(|encoder| {
+    target.with_frame(&gx, None, |frame| gx.with_encoder(|encoder| {
 
         encoder.with_render_pass(
             (
@@ -131,7 +131,7 @@ async fn init_app(ctx: &mut AppCtx) -> impl FnMut(&mut AppCtx, &AppEvent) {
 
             let then = Instant::now();
 
-            target.with_frame(None, |frame| gx.with_encoder(|encoder| {
+            target.with_frame(&gx, None, |frame| gx.with_encoder(|encoder| {
                 encoder.with_render_pass(
                     frame.attachments(Some(bg_color_target), None, None),
                     |rpass| {
@@ -96,7 +96,7 @@ async fn init_app(ctx: &mut AppCtx) -> impl FnMut(&mut AppCtx, &AppEvent) {
 
             let then = Instant::now();
 
-            target.with_frame(None, |frame| gx.with_encoder(|encoder| {
+            target.with_frame(&gx, None, |frame| gx.with_encoder(|encoder| {
                 encoder.pass_bundles(frame.attachments(Some(Color::GREEN), Some(1.0), None), &bundles);
             })).expect("frame error");
 
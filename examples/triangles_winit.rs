@@ -111,7 +111,7 @@ fn main() {
 
                 // let then = Instant::now();
 
-                target.with_frame(None, |frame| gx.with_encoder(|encoder| {
+                target.with_frame(&gx, None, |frame| gx.with_encoder(|encoder| {
                     encoder.pass_bundles(frame.attachments(Some(Color::GREEN), Some(1.0), None), &bundles);
                 })).expect("frame error");
 
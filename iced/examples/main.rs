@@ -52,7 +52,7 @@ async fn init_app(app_ctx: &mut AppCtx) -> impl FnMut(&mut AppCtx, &AppEvent) {
         gui.update(app_ctx);
 
         // draw
-        target.with_frame(None, |frame| engine.with_encoder(&gx, |engine, encoder| {
+        target.with_frame(&gx, None, |frame| engine.with_encoder(&gx, |engine, encoder| {
 
           let bg_color = gui.program().bg_color;
           gui.draw(&gx, engine, encoder, frame, Some(bg_color));
@@ -0,0 +1,6 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    // included by dependents as "wgx_pbr::pbr.wgsl" after wgsl_modules_loader::build::import_packages
+    wgsl_modules_loader::build::export_package("wgx_pbr", "shaders").unwrap();
+}
@@ -0,0 +1,55 @@
+
+// frame bindings, group 0
+
+struct Frame {
+    view_projection: mat4x4f,
+    camera_position: vec3f,
+    light_count: u32,
+    environment_intensity: f32,
+    prefiltered_mips: f32, // mip levels of the prefiltered environment
+    shadow_texel: f32, // 1 / shadow map size
+    _pad: f32,
+}
+
+struct Light {
+    position: vec3f,
+    range: f32, // 0 for no cutoff
+    direction: vec3f,
+    kind: u32, // LIGHT_DIRECTIONAL, LIGHT_POINT or LIGHT_SPOT
+    color: vec3f, // linear
+    intensity: f32,
+    cone: vec2f, // cosines of the inner and outer spot angle
+    shadow: i32, // shadow map layer, -1 without shadow
+    shadow_bias: f32, // world space offset along the normal
+}
+
+@group(0) @binding(0) var<uniform> frame: Frame;
+@group(0) @binding(1) var<storage, read> lights: array<Light>;
+@group(0) @binding(2) var<storage, read> shadow_matrices: array<mat4x4f>; // per shadow map layer
+@group(0) @binding(3) var shadow_maps: texture_depth_2d_array;
+@group(0) @binding(4) var shadow_sampler: sampler_comparison;
+@group(0) @binding(5) var irradiance_map: texture_cube<f32>;
+@group(0) @binding(6) var prefiltered_map: texture_cube<f32>;
+@group(0) @binding(7) var brdf_lut: texture_2d<f32>;
+@group(0) @binding(8) var environment_sampler: sampler;
+
+
+// material bindings, group 1
+
+struct Material {
+    base_color: vec4f, // linear
+    emissive: vec3f, // linear
+    metallic: f32,
+    roughness: f32,
+    normal_scale: f32,
+    occlusion_strength: f32,
+    alpha_cutoff: f32, // 0 without alpha testing
+}
+
+@group(1) @binding(0) var<uniform> material: Material;
+@group(1) @binding(1) var base_color_texture: texture_2d<f32>; // srgb
+@group(1) @binding(2) var metallic_roughness_texture: texture_2d<f32>; // roughness in g, metallic in b
+@group(1) @binding(3) var normal_texture: texture_2d<f32>; // tangent space
+@group(1) @binding(4) var occlusion_texture: texture_2d<f32>; // in r
+@group(1) @binding(5) var emissive_texture: texture_2d<f32>; // srgb
+@group(1) @binding(6) var material_sampler: sampler;
@@ -0,0 +1,63 @@
+
+// cook torrance brdf with ggx distribution, smith visibility and schlick fresnel
+
+const PI = 3.14159265358979;
+
+struct Surface {
+    position: vec3f, // world space
+    normal: vec3f,
+    view: vec3f, // towards the camera
+    base_color: vec3f,
+    alpha: f32,
+    metallic: f32,
+    roughness: f32, // perceptual
+    occlusion: f32,
+    emissive: vec3f,
+}
+
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+    let a = roughness * roughness;
+    let a2 = a * a;
+    let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+// height correlated smith, includes the 1 / (4 n.l n.v) term
+fn visibility_smith(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let a = roughness * roughness;
+    let a2 = a * a;
+    let v = n_dot_l * sqrt(n_dot_v * n_dot_v * (1.0 - a2) + a2);
+    let l = n_dot_v * sqrt(n_dot_l * n_dot_l * (1.0 - a2) + a2);
+    return 0.5 / max(v + l, 1e-5);
+}
+
+fn fresnel_schlick(cos_theta: f32, f0: vec3f) -> vec3f {
+    return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// for ambient light, rough surfaces reflect less at grazing angles
+fn fresnel_schlick_roughness(cos_theta: f32, f0: vec3f, roughness: f32) -> vec3f {
+    return f0 + (max(vec3f(1.0 - roughness), f0) - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// reflectance at normal incidence, 4% for dielectrics
+fn surface_f0(surface: Surface) -> vec3f {
+    return mix(vec3f(0.04), surface.base_color, surface.metallic);
+}
+
+// light reflected towards the view from radiance arriving from direction l
+fn brdf(surface: Surface, l: vec3f, radiance: vec3f) -> vec3f {
+    let n = surface.normal;
+    let v = surface.view;
+    let h = normalize(l + v);
+
+    let n_dot_l = max(dot(n, l), 0.0);
+    let n_dot_v = max(dot(n, v), 1e-4);
+    let n_dot_h = max(dot(n, h), 0.0);
+
+    let f = fresnel_schlick(max(dot(h, v), 0.0), surface_f0(surface));
+    let specular = f * distribution_ggx(n_dot_h, surface.roughness) * visibility_smith(n_dot_v, n_dot_l, surface.roughness);
+    let diffuse = (1.0 - f) * (1.0 - surface.metallic) * surface.base_color / PI;
+
+    return (diffuse + specular) * radiance * n_dot_l;
+}
@@ -0,0 +1,14 @@
+
+// the renderers default shader, custom shaders follow the same pattern
+
+&include "pbr.wgsl"
+&include "vertex.wgsl"
+
+@fragment
+fn fs_main(in: VertexOut, @builtin(front_facing) front_facing: bool) -> @location(0) vec4f {
+    let surface = material_surface(in.position, in.normal, in.tangent, in.uv, front_facing);
+
+    if surface.alpha < material.alpha_cutoff { discard; }
+
+    return vec4f(shade(surface), surface.alpha);
+}
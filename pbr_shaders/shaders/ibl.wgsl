@@ -0,0 +1,21 @@
+
+// image based ambient light with the split sum approximation, expects bindings.wgsl and brdf.wgsl
+
+fn ambient_light(surface: Surface) -> vec3f {
+    let n = surface.normal;
+    let v = surface.view;
+    let n_dot_v = max(dot(n, v), 1e-4);
+
+    let f0 = surface_f0(surface);
+    let f = fresnel_schlick_roughness(n_dot_v, f0, surface.roughness);
+
+    let irradiance = textureSampleLevel(irradiance_map, environment_sampler, n, 0.0).rgb;
+    let diffuse = (1.0 - f) * (1.0 - surface.metallic) * surface.base_color * irradiance;
+
+    let lod = surface.roughness * (frame.prefiltered_mips - 1.0);
+    let prefiltered = textureSampleLevel(prefiltered_map, environment_sampler, reflect(-v, n), lod).rgb;
+    let scale_bias = textureSampleLevel(brdf_lut, environment_sampler, vec2f(n_dot_v, surface.roughness), 0.0).rg;
+    let specular = prefiltered * (f0 * scale_bias.x + scale_bias.y);
+
+    return (diffuse + specular) * surface.occlusion * frame.environment_intensity;
+}
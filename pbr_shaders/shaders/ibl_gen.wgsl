@@ -0,0 +1,156 @@
+
+// environment preprocessing, full screen passes rendering all six cube faces as instances
+
+&include "brdf.wgsl"
+
+struct Params {
+    roughness: f32, // of the prefiltered mip
+    lod: f32, // source mip to sample
+    samples: u32,
+    _pad: f32,
+}
+
+@group(0) @binding(0) var source_cube: texture_cube<f32>;
+@group(0) @binding(1) var source_sampler: sampler;
+@group(0) @binding(2) var<uniform> params: Params;
+@group(0) @binding(3) var source_equirect: texture_2d<f32>;
+
+struct VertexOut {
+    @builtin(position) position: vec4f,
+    @location(0) uv: vec2f,
+    @location(1) @interpolate(flat) face: u32,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, @builtin(instance_index) face: u32) -> VertexOut {
+    let uv = vec2f(f32((index << 1u) & 2u), f32(index & 2u));
+    return VertexOut(vec4f(uv * vec2f(2.0, -2.0) + vec2f(-1.0, 1.0), 0.0, 1.0), uv, face);
+}
+
+// direction through the uv of a cube face, faces ordered +x, -x, +y, -y, +z, -z
+fn cube_direction(face: u32, uv: vec2f) -> vec3f {
+    let p = uv * 2.0 - 1.0;
+    switch face {
+        case 0u: { return normalize(vec3f(1.0, -p.y, -p.x)); }
+        case 1u: { return normalize(vec3f(-1.0, -p.y, p.x)); }
+        case 2u: { return normalize(vec3f(p.x, 1.0, p.y)); }
+        case 3u: { return normalize(vec3f(p.x, -1.0, -p.y)); }
+        case 4u: { return normalize(vec3f(p.x, -p.y, 1.0)); }
+        default: { return normalize(vec3f(-p.x, -p.y, -1.0)); }
+    }
+}
+
+// orthonormal basis around n
+fn tangent_frame(n: vec3f, v: vec3f) -> vec3f {
+    let up = select(vec3f(0.0, 1.0, 0.0), vec3f(1.0, 0.0, 0.0), abs(n.y) > 0.999);
+    let tangent = normalize(cross(up, n));
+    let bitangent = cross(n, tangent);
+    return tangent * v.x + bitangent * v.y + n * v.z;
+}
+
+fn hammersley(i: u32, count: u32) -> vec2f {
+    return vec2f(f32(i) / f32(count), f32(reverseBits(i)) * 2.3283064365386963e-10);
+}
+
+// half vector around n, distributed like the ggx lobe
+fn importance_sample_ggx(xi: vec2f, n: vec3f, roughness: f32) -> vec3f {
+    let a = roughness * roughness;
+    let phi = 2.0 * PI * xi.x;
+    let cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+    let sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+    return normalize(tangent_frame(n, vec3f(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta)));
+}
+
+
+// equirectangular panorama to cube, +y up, the center of the panorama towards +x
+@fragment
+fn fs_equirect(in: VertexOut) -> @location(0) vec4f {
+    let d = cube_direction(in.face, in.uv);
+    let uv = vec2f(atan2(d.z, d.x) / (2.0 * PI) + 0.5, acos(clamp(d.y, -1.0, 1.0)) / PI);
+    return vec4f(textureSampleLevel(source_equirect, source_sampler, uv, 0.0).rgb, 1.0);
+}
+
+// copies a cube or downsamples the previous mip
+@fragment
+fn fs_copy(in: VertexOut) -> @location(0) vec4f {
+    return vec4f(textureSampleLevel(source_cube, source_sampler, cube_direction(in.face, in.uv), params.lod).rgb, 1.0);
+}
+
+// cosine weighted hemisphere integral, divided by pi
+@fragment
+fn fs_irradiance(in: VertexOut) -> @location(0) vec4f {
+    let n = cube_direction(in.face, in.uv);
+    let steps = max(params.samples, 4u);
+
+    var sum = vec3f(0.0);
+
+    for (var i = 0u; i < steps; i++) {
+        let phi = 2.0 * PI * (f32(i) + 0.5) / f32(steps);
+
+        for (var j = 0u; j < steps / 4u; j++) {
+            let theta = 0.5 * PI * (f32(j) + 0.5) / f32(steps / 4u);
+            let direction = tangent_frame(n, vec3f(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta)));
+            sum += textureSampleLevel(source_cube, source_sampler, direction, params.lod).rgb * cos(theta) * sin(theta);
+        }
+    }
+
+    return vec4f(PI * sum / f32(steps * (steps / 4u)), 1.0);
+}
+
+// ggx lobe integral with view = normal, sampling coarser mips for sparse samples
+@fragment
+fn fs_prefilter(in: VertexOut) -> @location(0) vec4f {
+    let n = cube_direction(in.face, in.uv);
+    let size = f32(textureDimensions(source_cube).x);
+    let texel_angle = 4.0 * PI / (6.0 * size * size);
+
+    var sum = vec3f(0.0);
+    var weight = 0.0;
+
+    for (var i = 0u; i < params.samples; i++) {
+        let h = importance_sample_ggx(hammersley(i, params.samples), n, params.roughness);
+        let l = normalize(2.0 * dot(n, h) * h - n);
+        let n_dot_l = dot(n, l);
+
+        if n_dot_l > 0.0 {
+            let n_dot_h = max(dot(n, h), 0.0);
+            let pdf = distribution_ggx(n_dot_h, params.roughness) * 0.25 + 1e-4;
+            let sample_angle = 1.0 / (f32(params.samples) * pdf);
+            let lod = max(0.5 * log2(sample_angle / texel_angle) + 1.0, 0.0);
+
+            sum += textureSampleLevel(source_cube, source_sampler, l, lod).rgb * n_dot_l;
+            weight += n_dot_l;
+        }
+    }
+
+    return vec4f(sum / max(weight, 1e-4), 1.0);
+}
+
+// scale and bias of f0 over n.v in x and roughness in y
+@fragment
+fn fs_brdf_lut(in: VertexOut) -> @location(0) vec4f {
+    let n_dot_v = max(in.uv.x, 1e-3);
+    let roughness = in.uv.y;
+    let v = vec3f(sqrt(1.0 - n_dot_v * n_dot_v), 0.0, n_dot_v);
+    let n = vec3f(0.0, 0.0, 1.0);
+
+    var scale_bias = vec2f(0.0);
+
+    for (var i = 0u; i < params.samples; i++) {
+        let h = importance_sample_ggx(hammersley(i, params.samples), n, roughness);
+        let l = normalize(2.0 * dot(v, h) * h - v);
+
+        let n_dot_l = max(l.z, 0.0);
+        let n_dot_h = max(h.z, 0.0);
+        let v_dot_h = max(dot(v, h), 0.0);
+
+        if n_dot_l > 0.0 {
+            // visibility including 4 n.l n.v, divided by the pdf d n.h / (4 v.h)
+            let g = visibility_smith(n_dot_v, n_dot_l, roughness) * 4.0 * n_dot_l * v_dot_h / max(n_dot_h, 1e-5);
+            let fc = pow(1.0 - v_dot_h, 5.0);
+            scale_bias += vec2f(1.0 - fc, fc) * g;
+        }
+    }
+
+    return vec4f(scale_bias / f32(params.samples), 0.0, 1.0);
+}
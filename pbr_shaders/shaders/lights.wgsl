@@ -0,0 +1,41 @@
+
+// punctual lights, expects bindings.wgsl
+
+const LIGHT_DIRECTIONAL = 0u;
+const LIGHT_POINT = 1u;
+const LIGHT_SPOT = 2u;
+
+struct LightSample {
+    direction: vec3f, // towards the light
+    radiance: vec3f,
+}
+
+// inverse square falloff, smoothly windowed to zero at the range
+fn light_attenuation(distance: f32, range: f32) -> f32 {
+    let inverse_square = 1.0 / max(distance * distance, 1e-4);
+    if range <= 0.0 { return inverse_square; }
+    let ratio = distance / range;
+    let window = clamp(1.0 - ratio * ratio * ratio * ratio, 0.0, 1.0);
+    return inverse_square * window * window;
+}
+
+// light arriving at the position, without shadowing
+fn light_sample(light: Light, position: vec3f) -> LightSample {
+    let radiance = light.color * light.intensity;
+
+    if light.kind == LIGHT_DIRECTIONAL {
+        return LightSample(-light.direction, radiance);
+    }
+
+    let to_light = light.position - position;
+    let distance = length(to_light);
+    let direction = to_light / max(distance, 1e-5);
+
+    var attenuation = light_attenuation(distance, light.range);
+
+    if light.kind == LIGHT_SPOT {
+        attenuation *= smoothstep(light.cone.y, light.cone.x, dot(-direction, light.direction));
+    }
+
+    return LightSample(direction, radiance * attenuation);
+}
@@ -0,0 +1,23 @@
+
+// the pbr shader library, include as "wgx_pbr::pbr.wgsl" to write custom shaders on the renderers bindings
+
+&include "bindings.wgsl"
+&include "brdf.wgsl"
+&include "lights.wgsl"
+&include "shadows.wgsl"
+&include "ibl.wgsl"
+&include "surface.wgsl"
+
+// ambient, emissive and all lights with their shadows
+fn shade(surface: Surface) -> vec3f {
+    var color = ambient_light(surface) + surface.emissive;
+
+    for (var i = 0u; i < frame.light_count; i++) {
+        let light = lights[i];
+        let incoming = light_sample(light, surface.position);
+        let visibility = shadow_visibility(light, surface.position, surface.normal);
+        color += brdf(surface, incoming.direction, incoming.radiance * visibility);
+    }
+
+    return color;
+}
@@ -0,0 +1,16 @@
+
+// depth only pass into one shadow map layer
+
+@group(0) @binding(0) var<uniform> light_view_projection: mat4x4f;
+
+@vertex
+fn vs_main(
+    @location(0) position: vec3f,
+    @location(4) model_0: vec4f,
+    @location(5) model_1: vec4f,
+    @location(6) model_2: vec4f,
+    @location(7) model_3: vec4f,
+) -> @builtin(position) vec4f {
+    let model = mat4x4f(model_0, model_1, model_2, model_3);
+    return light_view_projection * model * vec4f(position, 1.0);
+}
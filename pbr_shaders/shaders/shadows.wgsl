@@ -0,0 +1,25 @@
+
+// shadow map lookup, expects bindings.wgsl
+
+// 3x3 pcf filtered visibility of the light, 1 outside of the shadow map
+fn shadow_visibility(light: Light, position: vec3f, normal: vec3f) -> f32 {
+    if light.shadow < 0 { return 1.0; }
+
+    // offset along the normal against shadow acne
+    let clip = shadow_matrices[light.shadow] * vec4f(position + normal * light.shadow_bias, 1.0);
+    let ndc = clip.xyz / clip.w;
+    let uv = ndc.xy * vec2f(0.5, -0.5) + 0.5;
+
+    if any(uv < vec2f(0.0)) || any(uv > vec2f(1.0)) || ndc.z > 1.0 { return 1.0; }
+
+    var visibility = 0.0;
+
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let offset = vec2f(f32(x), f32(y)) * frame.shadow_texel;
+            visibility += textureSampleCompareLevel(shadow_maps, shadow_sampler, uv + offset, light.shadow, ndc.z);
+        }
+    }
+
+    return visibility / 9.0;
+}
@@ -0,0 +1,35 @@
+
+// surface from the material, expects bindings.wgsl and brdf.wgsl
+
+// tangent w is the handedness of the bitangent, front_facing flips the normal of back faces
+fn material_surface(position: vec3f, normal: vec3f, tangent: vec4f, uv: vec2f, front_facing: bool) -> Surface {
+    let base_color = textureSample(base_color_texture, material_sampler, uv) * material.base_color;
+    let metallic_roughness = textureSample(metallic_roughness_texture, material_sampler, uv);
+    let normal_sample = textureSample(normal_texture, material_sampler, uv).xyz * 2.0 - 1.0;
+    let occlusion = textureSample(occlusion_texture, material_sampler, uv).r;
+    let emissive = textureSample(emissive_texture, material_sampler, uv).rgb;
+
+    var n = normalize(normal);
+    if !front_facing { n = -n; }
+
+    // normal mapping, skipped with degenerate tangents
+    let t = tangent.xyz - n * dot(n, tangent.xyz);
+    if dot(t, t) > 1e-8 {
+        let t = normalize(t);
+        let b = cross(n, t) * tangent.w;
+        let mapped = normal_sample * vec3f(material.normal_scale, material.normal_scale, 1.0);
+        n = normalize(t * mapped.x + b * mapped.y + n * mapped.z);
+    }
+
+    var surface: Surface;
+    surface.position = position;
+    surface.normal = n;
+    surface.view = normalize(frame.camera_position - position);
+    surface.base_color = base_color.rgb;
+    surface.alpha = base_color.a;
+    surface.metallic = clamp(material.metallic * metallic_roughness.b, 0.0, 1.0);
+    surface.roughness = clamp(material.roughness * metallic_roughness.g, 0.03, 1.0);
+    surface.occlusion = mix(1.0, occlusion, material.occlusion_strength);
+    surface.emissive = material.emissive * emissive;
+    return surface;
+}
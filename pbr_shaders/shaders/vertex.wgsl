@@ -0,0 +1,37 @@
+
+// vertex stage of the renderer, mesh attributes at locations 0 to 3 and the model matrix per instance at 4 to 7
+
+struct VertexIn {
+    @location(0) position: vec3f,
+    @location(1) normal: vec3f,
+    @location(2) uv: vec2f,
+    @location(3) tangent: vec4f,
+    @location(4) model_0: vec4f,
+    @location(5) model_1: vec4f,
+    @location(6) model_2: vec4f,
+    @location(7) model_3: vec4f,
+}
+
+struct VertexOut {
+    @builtin(position) clip_position: vec4f,
+    @location(0) position: vec3f, // world space
+    @location(1) normal: vec3f,
+    @location(2) uv: vec2f,
+    @location(3) tangent: vec4f,
+}
+
+// normals are transformed with the model matrix, assuming uniform scale
+@vertex
+fn vs_main(in: VertexIn) -> VertexOut {
+    let model = mat4x4f(in.model_0, in.model_1, in.model_2, in.model_3);
+    let normal_matrix = mat3x3f(model[0].xyz, model[1].xyz, model[2].xyz);
+    let position = model * vec4f(in.position, 1.0);
+
+    var out: VertexOut;
+    out.clip_position = frame.view_projection * position;
+    out.position = position.xyz;
+    out.normal = normal_matrix * in.normal;
+    out.uv = in.uv;
+    out.tangent = vec4f(normal_matrix * in.tangent.xyz, in.tangent.w);
+    return out;
+}
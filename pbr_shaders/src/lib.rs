@@ -0,0 +1,6 @@
+
+// the wgsl shader library of wgx::pbr, exported as wgsl package `wgx_pbr`
+// kept in its own crate as exporting a package requires the `links` key for the whole crate
+
+// directory of the shaders, e.g. for loading them at runtime
+pub const ROOT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/shaders");
@@ -0,0 +1,371 @@
+use std::{collections::HashMap, hash::Hash};
+use wgpu::{TextureFormat, TextureViewDimension};
+use anyhow::{Result as Res, bail, ensure};
+use crate::*;
+
+
+// packs many rgba8 images into the layers of one texture array
+// images are extruded into a gutter around them, positions and gutters are aligned to
+// 2^(mip_levels-1) so that neighbours never bleed into each other down to the smallest mip
+#[derive(Debug, Clone)]
+pub struct AtlasBuilder<K> {
+    pub size: [u32; 2], // of each layer
+    pub padding: u32, // transparent space between the gutters
+    pub mip_levels: u32,
+    pub max_layers: u32,
+    images: Vec<(K, [u32; 2], Vec<u8>)>,
+}
+
+// placement of an image in the atlas
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AtlasEntry {
+    pub layer: u32,
+    pub rect: [u32; 4], // x, y, width, height in pixels, without gutter
+    pub uv: [f32; 4], // x, y, width, height in texture coordinates
+}
+
+impl AtlasEntry {
+    pub fn size(&self) -> [u32; 2] { [self.rect[2], self.rect[3]] }
+}
+
+
+// bottom left skyline packer for one layer
+#[derive(Debug, Clone)]
+pub(crate) struct Skyline {
+    pub(crate) width: u32,
+    pub(crate) height: u32, // may grow
+    segments: Vec<[u32; 3]>, // x, y, width
+}
+
+impl Skyline {
+
+    pub(crate) fn new([width, height]: [u32; 2]) -> Self {
+        Self { width, height, segments: vec![[0, 0, width]] }
+    }
+
+    fn fit(&self, index: usize, width: u32) -> Option<u32> {
+        let x = self.segments[index][0];
+        if x + width > self.width { return None }
+
+        let mut y = 0;
+        let mut remaining = width as i64;
+
+        for &[_, seg_y, seg_width] in &self.segments[index..] {
+            if remaining <= 0 { break }
+            y = y.max(seg_y);
+            remaining -= seg_width as i64;
+        }
+        Some(y)
+    }
+
+    pub(crate) fn insert(&mut self, [width, height]: [u32; 2]) -> Option<[u32; 2]> {
+
+        // lowest top edge, then leftmost
+        let (index, y) = (0..self.segments.len())
+            .filter_map(|i| self.fit(i, width).filter(|y| y + height <= self.height).map(|y| (i, y)))
+            .min_by_key(|&(i, y)| (y + height, self.segments[i][0]))?;
+
+        let x = self.segments[index][0];
+        self.segments.insert(index, [x, y + height, width]);
+
+        // cut the covered segments
+        let right = x + width;
+        let i = index + 1;
+        while i < self.segments.len() {
+            let [seg_x, seg_y, seg_width] = self.segments[i];
+            if seg_x >= right { break }
+            if seg_x + seg_width <= right { self.segments.remove(i); }
+            else { self.segments[i] = [right, seg_y, seg_x + seg_width - right]; break }
+        }
+
+        // merge equal heights
+        self.segments.dedup_by(|b, a| {
+            if a[1] == b[1] { a[2] += b[2]; true } else { false }
+        });
+
+        Some([x, y])
+    }
+}
+
+
+fn align(value: u32, alignment: u32) -> u32 { value.div_ceil(alignment) * alignment }
+
+
+// appends the box filtered mips 1..mip_levels of a rgba8 image to data
+pub(crate) fn box_mips(image: &[u8], size: [u32; 2], mip_levels: u32, data: &mut Vec<u8>) {
+    let mut level = image.to_vec();
+    let [mut w, mut h] = size;
+
+    for _ in 1..mip_levels {
+        let [nw, nh] = [(w / 2).max(1), (h / 2).max(1)];
+        let mut next = vec![0u8; (nw * nh * 4) as usize];
+
+        for ny in 0..nh { for nx in 0..nw { for c in 0..4 {
+            let sum: u32 = [(0, 0), (1, 0), (0, 1), (1, 1)].iter().map(|&(ox, oy)| {
+                let (sx, sy) = ((nx * 2 + ox).min(w - 1), (ny * 2 + oy).min(h - 1));
+                level[((sy * w + sx) * 4 + c) as usize] as u32
+            }).sum();
+            next[((ny * nw + nx) * 4 + c) as usize] = ((sum + 2) / 4) as u8;
+        }}}
+
+        data.extend_from_slice(&next);
+        (level, w, h) = (next, nw, nh);
+    }
+}
+
+
+impl<K: Eq + Hash + Clone> AtlasBuilder<K> {
+
+    pub fn new(size: [u32; 2]) -> Self {
+        Self { size, padding: 1, mip_levels: 1, max_layers: 1, images: Vec::new() }
+    }
+
+    pub fn padding(self, padding: u32) -> Self { Self { padding, ..self } }
+    pub fn mip_levels(self, mip_levels: u32) -> Self { Self { mip_levels: mip_levels.max(1), ..self } }
+    pub fn max_layers(self, max_layers: u32) -> Self { Self { max_layers, ..self } }
+
+    pub fn len(&self) -> usize { self.images.len() }
+    pub fn is_empty(&self) -> bool { self.images.is_empty() }
+
+    // tightly packed rgba8 rows, each key once
+    pub fn add(&mut self, key: K, size: [u32; 2], data: impl Into<Vec<u8>>) -> Res<()> {
+        let data = data.into();
+        ensure!(!self.images.iter().any(|(other, ..)| *other == key), "image key added twice to the atlas");
+        ensure!(data.len() == (size[0] * size[1] * 4) as usize, "image data of {} bytes doesn't match the size {size:?}", data.len());
+        self.images.push((key, size, data));
+        Ok(())
+    }
+
+    fn alignment(&self) -> u32 { 1 << (self.mip_levels - 1) }
+
+    // extruded edge pixels around each image
+    pub fn gutter(&self) -> u32 { if self.mip_levels > 1 { self.alignment() } else { 0 } }
+
+    // places all images, largest first, returns the entries and the number of layers used
+    pub fn pack(&self) -> Res<(HashMap<K, AtlasEntry>, u32)> {
+
+        let (alignment, gutter) = (self.alignment(), self.gutter());
+
+        let mut order: Vec<usize> = (0..self.images.len()).collect();
+        order.sort_by_key(|&i| { let [w, h] = self.images[i].1; std::cmp::Reverse((h, w)) });
+
+        let mut layers: Vec<Skyline> = Vec::new();
+        let mut entries = HashMap::with_capacity(self.images.len());
+
+        for i in order {
+            let (key, [w, h], _) = &self.images[i];
+
+            let slot = [
+                align(w + 2 * gutter + self.padding, alignment),
+                align(h + 2 * gutter + self.padding, alignment),
+            ];
+
+            if slot[0] > self.size[0] || slot[1] > self.size[1] {
+                bail!("image of size {:?} doesn't fit into an atlas layer of size {:?}", [w, h], self.size);
+            }
+
+            let mut placed = layers.iter_mut().enumerate().find_map(|(l, layer)| Some((l, layer.insert(slot)?)));
+
+            if placed.is_none() {
+                if layers.len() as u32 >= self.max_layers {
+                    bail!("atlas is full after {} of {} images with {} layers", entries.len(), self.images.len(), self.max_layers);
+                }
+                let mut layer = Skyline::new(self.size);
+                placed = layer.insert(slot).map(|pos| (layers.len(), pos));
+                layers.push(layer);
+            }
+
+            let (layer, [x, y]) = placed.unwrap();
+            let [x, y] = [x + gutter, y + gutter];
+
+            entries.insert(key.clone(), AtlasEntry {
+                layer: layer as u32,
+                rect: [x, y, *w, *h],
+                uv: [
+                    x as f32 / self.size[0] as f32, y as f32 / self.size[1] as f32,
+                    *w as f32 / self.size[0] as f32, *h as f32 / self.size[1] as f32,
+                ],
+            });
+        }
+
+        Ok((entries, layers.len().max(1) as u32))
+    }
+
+    // pixels of all layers including their mips, layer major
+    pub fn compose(&self, entries: &HashMap<K, AtlasEntry>, layers: u32) -> Vec<u8> {
+
+        let [width, height] = self.size;
+        let layer_bytes = (width * height * 4) as usize;
+        let mut pixels = vec![0u8; layer_bytes * layers as usize];
+        let gutter = self.gutter() as i64;
+
+        for (key, [w, h], data) in &self.images {
+            let entry = &entries[key];
+            let [x, y, ..] = entry.rect;
+            let layer = &mut pixels[layer_bytes * entry.layer as usize..][..layer_bytes];
+
+            // each pixel including the gutter takes the nearest pixel of the image
+            for dy in -gutter..*h as i64 + gutter {
+                let sy = dy.clamp(0, *h as i64 - 1) as u32;
+                let ty = (y as i64 + dy) as u32;
+
+                for dx in -gutter..*w as i64 + gutter {
+                    let sx = dx.clamp(0, *w as i64 - 1) as u32;
+                    let tx = (x as i64 + dx) as u32;
+
+                    let src = ((sy * w + sx) * 4) as usize;
+                    let dst = ((ty * width + tx) * 4) as usize;
+                    layer[dst..dst + 4].copy_from_slice(&data[src..src + 4]);
+                }
+            }
+        }
+
+        if self.mip_levels == 1 { return pixels }
+
+        // box filtered mips following each layer
+        let mut data = Vec::with_capacity(pixels.len() * 4 / 3 + 4 * layers as usize * self.mip_levels as usize);
+
+        for layer in pixels.chunks_exact(layer_bytes) {
+            data.extend_from_slice(layer);
+
+            box_mips(layer, self.size, self.mip_levels, &mut data);
+        }
+
+        data
+    }
+
+    // packs, composes and uploads the atlas, format has to be a rgba8 format
+    pub fn build(&self, gx: &impl WgxDeviceQueue, format: TextureFormat) -> Res<Atlas<K>> {
+
+        ensure!(format.block_copy_size(None) == Some(4) && format.components() == 4, "atlas format {format:?} is no rgba8 format");
+        ensure!(self.size[0] >> (self.mip_levels - 1) > 0 && self.size[1] >> (self.mip_levels - 1) > 0, "too many mip levels for the atlas size");
+
+        let (entries, layers) = self.pack()?;
+
+        let mut descriptor = TexDsc::new_2d([self.size[0], self.size[1], layers], 1, format, None, TexUse::TEXTURE_BINDING | TexUse::COPY_DST);
+        descriptor.mip_level_count = self.mip_levels;
+        descriptor.view_dimension = TextureViewDimension::D2Array;
+
+        let lot = TextureLot::new_with_data(gx, descriptor, self.compose(&entries, layers).as_slice());
+
+        Ok(Atlas { lot, entries })
+    }
+}
+
+
+#[derive(Debug)]
+pub struct Atlas<K> {
+    pub lot: TextureLot, // D2Array view
+    pub entries: HashMap<K, AtlasEntry>,
+}
+
+impl<K: Eq + Hash> Atlas<K> {
+    pub fn get(&self, key: &K) -> Option<&AtlasEntry> { self.entries.get(key) }
+    pub fn layers(&self) -> u32 { self.lot.descriptor.size[2] }
+    pub fn view(&self) -> &wgpu::TextureView { &self.lot.view }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builder(size: [u32; 2]) -> AtlasBuilder<u32> { AtlasBuilder::new(size).padding(0) }
+
+    fn image(size: [u32; 2], rgba: [u8; 4]) -> Vec<u8> { rgba.repeat((size[0] * size[1]) as usize) }
+
+    #[test]
+    fn skyline_fills_rows() {
+        let mut skyline = Skyline::new([8, 8]);
+        assert_eq!(skyline.insert([4, 4]), Some([0, 0]));
+        assert_eq!(skyline.insert([4, 4]), Some([4, 0]));
+        assert_eq!(skyline.insert([4, 4]), Some([0, 4]));
+        assert_eq!(skyline.insert([4, 4]), Some([4, 4]));
+        assert_eq!(skyline.insert([1, 1]), None);
+    }
+
+    #[test]
+    fn skyline_prefers_lowest_top_edge() {
+        let mut skyline = Skyline::new([8, 8]);
+        assert_eq!(skyline.insert([2, 6]), Some([0, 0]));
+        assert_eq!(skyline.insert([6, 2]), Some([2, 0]));
+        assert_eq!(skyline.insert([6, 2]), Some([2, 2]));
+        assert_eq!(skyline.insert([8, 2]), Some([0, 6]));
+    }
+
+    #[test]
+    fn skyline_rejects_oversized() {
+        let mut skyline = Skyline::new([8, 8]);
+        assert_eq!(skyline.insert([9, 1]), None);
+        assert_eq!(skyline.insert([1, 9]), None);
+    }
+
+    #[test]
+    fn pack_aligns_gutters() {
+        let mut atlas = builder([64, 64]).mip_levels(3);
+        atlas.add(0, [4, 4], image([4, 4], [255; 4])).unwrap();
+        atlas.add(1, [3, 5], image([3, 5], [255; 4])).unwrap();
+
+        assert_eq!(atlas.gutter(), 4);
+
+        let (entries, layers) = atlas.pack().unwrap();
+        assert_eq!(layers, 1);
+
+        // the taller image is placed first, slots are aligned to 4 including the gutters
+        assert_eq!(entries[&1].rect, [4, 4, 3, 5]);
+        assert_eq!(entries[&0].rect, [16, 4, 4, 4]);
+        assert_eq!(entries[&0].uv, [0.25, 0.0625, 0.0625, 0.0625]);
+    }
+
+    #[test]
+    fn compose_extrudes_into_gutter() {
+        let mut atlas = builder([8, 8]).mip_levels(2);
+        atlas.add(0, [1, 1], image([1, 1], [10, 20, 30, 40])).unwrap();
+
+        let (entries, layers) = atlas.pack().unwrap();
+        assert_eq!(entries[&0].rect, [2, 2, 1, 1]);
+
+        let pixels = atlas.compose(&entries, layers);
+        let pixel = |x: usize, y: usize| &pixels[(y * 8 + x) * 4..][..4];
+
+        for [x, y] in [[0, 0], [2, 2], [4, 1], [1, 4]] {
+            assert_eq!(pixel(x, y), [10, 20, 30, 40]);
+        }
+        assert_eq!(pixel(5, 5), [0; 4]);
+
+        // the mip of the layer follows it
+        assert_eq!(pixels.len(), (8 * 8 + 4 * 4) * 4);
+    }
+
+    #[test]
+    fn pack_overflows_into_layers() {
+        let mut atlas = builder([16, 16]);
+        for key in 0..3 { atlas.add(key, [8, 16], image([8, 16], [0; 4])).unwrap() }
+
+        assert!(atlas.pack().is_err());
+
+        let (entries, layers) = atlas.clone().max_layers(2).pack().unwrap();
+        assert_eq!(layers, 2);
+        assert_eq!(entries.values().filter(|entry| entry.layer == 1).count(), 1);
+    }
+
+    #[test]
+    fn pack_rejects_oversized() {
+        let mut atlas = builder([16, 16]).padding(1);
+        atlas.add(0, [16, 4], image([16, 4], [0; 4])).unwrap();
+        assert!(atlas.pack().is_err());
+    }
+
+    #[test]
+    fn add_checks_data_size() {
+        assert!(builder([16, 16]).add(0, [2, 2], vec![0; 15]).is_err());
+    }
+
+    #[test]
+    fn add_rejects_duplicate_keys() {
+        let mut atlas = builder([16, 16]);
+        atlas.add(0, [2, 2], image([2, 2], [0; 4])).unwrap();
+        assert!(atlas.add(0, [4, 4], image([4, 4], [0; 4])).is_err());
+        assert_eq!(atlas.len(), 1);
+    }
+}
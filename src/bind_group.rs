@@ -0,0 +1,412 @@
+use std::{ops::Deref, num::NonZeroU64, fmt::Write, sync::Arc};
+use wgpu::{BindingType, BufferBindingType, TextureSampleType, SamplerBindingType, BindGroupLayoutEntry, BindingResource, BufferBinding};
+use crate::{*};
+use anyhow::{Result as Res, bail};
+
+
+// bind group layout which retains its entries, created with WgxDevice::layout_dsc
+#[derive(Debug)]
+pub struct BindGroupLayoutDsc {
+    pub layout: Arc<wgpu::BindGroupLayout>,
+    pub entries: Vec<BindGroupLayoutEntry>,
+}
+
+impl Deref for BindGroupLayoutDsc {
+    type Target = wgpu::BindGroupLayout;
+    fn deref(&self) -> &Self::Target { &self.layout }
+}
+
+impl BindGroupLayoutDsc {
+
+    pub fn entry(&self, binding: u32) -> Option<&BindGroupLayoutEntry> {
+        self.entries.iter().find(|entry| entry.binding == binding)
+    }
+
+    pub fn bind<'a, G: WgxDevice>(&'a self, gx: &'a G) -> BindGroupBuilder<'a, G> {
+        BindGroupBuilder { gx, layout: self, resources: Vec::with_capacity(self.entries.len()) }
+    }
+}
+
+
+// resources
+
+#[derive(Debug, Clone)]
+enum Resource<'a> {
+    Buffer(BufferBinding<'a>),
+    BufferArray(&'a [BufferBinding<'a>]),
+    Texture(&'a wgpu::TextureView, Option<&'a TexDsc>),
+    TextureArray(&'a [&'a wgpu::TextureView]),
+    Sampler(&'a wgpu::Sampler, Option<&'a SamplerDsc>),
+    SamplerArray(&'a [&'a wgpu::Sampler]),
+}
+
+impl<'a> Resource<'a> {
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Buffer(_) => "buffer",
+            Self::BufferArray(_) => "buffer array",
+            Self::Texture(..) => "texture",
+            Self::TextureArray(_) => "texture array",
+            Self::Sampler(..) => "sampler",
+            Self::SamplerArray(_) => "sampler array",
+        }
+    }
+
+    fn binding_resource(&self) -> BindingResource<'a> {
+        match self {
+            Self::Buffer(buffer) => BindingResource::Buffer(buffer.clone()),
+            Self::BufferArray(buffers) => BindingResource::BufferArray(buffers),
+            Self::Texture(view, _) => BindingResource::TextureView(view),
+            Self::TextureArray(views) => BindingResource::TextureViewArray(views),
+            Self::Sampler(sampler, _) => BindingResource::Sampler(sampler),
+            Self::SamplerArray(samplers) => BindingResource::SamplerArray(samplers),
+        }
+    }
+}
+
+
+fn binding_kind(ty: &BindingType) -> &'static str {
+    match ty {
+        BindingType::Buffer { ty: BufferBindingType::Uniform, .. } => "uniform buffer",
+        BindingType::Buffer { ty: BufferBindingType::Storage {..}, .. } => "storage buffer",
+        BindingType::Texture {..} => "texture",
+        BindingType::StorageTexture {..} => "storage texture",
+        BindingType::Sampler(_) => "sampler",
+        BindingType::AccelerationStructure => "acceleration structure",
+    }
+}
+
+fn sample_type_compatible(layout_type: TextureSampleType, texture_type: TextureSampleType) -> bool {
+    use TextureSampleType::*;
+    matches!((layout_type, texture_type),
+        (Float { filterable: true }, Float { filterable: true }) |
+        (Float { filterable: false }, Float {..} | Depth) |
+        (Depth, Depth) | (Sint, Sint) | (Uint, Uint)
+    )
+}
+
+fn check_array_len(entry: &BindGroupLayoutEntry, len: usize) -> Result<(), String> {
+    match entry.count {
+        None => Err("is not an array binding".to_string()),
+        Some(count) if len == 0 || len > count.get() as usize => Err(format!(
+            "array of length {len} doesn't fit layout count {count}"
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn check_buffer(entry: &BindGroupLayoutEntry, binding: &BufferBinding) -> Result<(), String> {
+
+    let BindingType::Buffer { ty, min_binding_size, .. } = entry.ty else { unreachable!() };
+
+    let usage = match ty {
+        BufferBindingType::Uniform => BufUse::UNIFORM,
+        BufferBindingType::Storage {..} => BufUse::STORAGE,
+    };
+
+    if !binding.buffer.usage().contains(usage) {
+        return Err(format!("buffer is missing usage {usage:?}"));
+    }
+
+    let buffer_size = binding.buffer.size();
+
+    if binding.offset > buffer_size {
+        return Err(format!("offset {} exceeds buffer size {buffer_size}", binding.offset));
+    }
+
+    let size = binding.size.map(NonZeroU64::get).unwrap_or(buffer_size - binding.offset);
+
+    match binding.offset.checked_add(size) {
+        Some(end) if end <= buffer_size => {},
+        Some(end) => return Err(format!("range {}..{end} exceeds buffer size {buffer_size}", binding.offset)),
+        None => return Err(format!("offset {} plus size {size} overflows", binding.offset)),
+    }
+
+    if let Some(min_size) = min_binding_size {
+        if size < min_size.get() {
+            return Err(format!("bound size {size} is smaller than min_binding_size {min_size}"));
+        }
+    }
+
+    Ok(())
+}
+
+fn check_texture(entry: &BindGroupLayoutEntry, dsc: &TexDsc) -> Result<(), String> {
+    match entry.ty {
+        BindingType::Texture { sample_type, view_dimension, multisampled } => {
+
+            if !dsc.usage.contains(TexUse::TEXTURE_BINDING) {
+                return Err("texture is missing usage TEXTURE_BINDING".to_string());
+            }
+            if dsc.view_dimension != view_dimension {
+                return Err(format!("view dimension {:?} doesn't match layout {view_dimension:?}", dsc.view_dimension));
+            }
+            if (dsc.sample_count > 1) != multisampled {
+                return Err(format!("sample count {} doesn't match layout multisampled: {multisampled}", dsc.sample_count));
+            }
+
+            match dsc.view_format.sample_type(Some(dsc.view_aspect), None) {
+                Some(texture_type) if sample_type_compatible(sample_type, texture_type) => Ok(()),
+                texture_type => Err(format!(
+                    "format {:?} with sample type {texture_type:?} doesn't match layout {sample_type:?}", dsc.view_format,
+                )),
+            }
+        },
+        BindingType::StorageTexture { format, view_dimension, .. } => {
+
+            if !dsc.usage.contains(TexUse::STORAGE_BINDING) {
+                return Err("texture is missing usage STORAGE_BINDING".to_string());
+            }
+            if dsc.view_dimension != view_dimension {
+                return Err(format!("view dimension {:?} doesn't match layout {view_dimension:?}", dsc.view_dimension));
+            }
+            if dsc.view_format != format {
+                return Err(format!("format {:?} doesn't match layout {format:?}", dsc.view_format));
+            }
+
+            Ok(())
+        },
+        _ => unreachable!(),
+    }
+}
+
+fn check_sampler(ty: SamplerBindingType, dsc: &SamplerDsc) -> Result<(), String> {
+    match ty {
+        SamplerBindingType::Comparison if !dsc.is_comparison() => Err("sampler has no compare function".to_string()),
+        SamplerBindingType::Filtering | SamplerBindingType::NonFiltering if dsc.is_comparison() => {
+            Err("comparison sampler in non-comparison binding".to_string())
+        },
+        SamplerBindingType::NonFiltering if dsc.is_filtering() => Err("filtering sampler in non-filtering binding".to_string()),
+        _ => Ok(()),
+    }
+}
+
+fn check_resource(entry: &BindGroupLayoutEntry, resource: &Resource) -> Result<(), String> {
+    match (&entry.ty, resource) {
+
+        (BindingType::Buffer {..}, Resource::Buffer(binding)) => {
+            if entry.count.is_some() { return Err("array binding requires a buffer array".to_string()) }
+            check_buffer(entry, binding)
+        },
+        (BindingType::Buffer {..}, Resource::BufferArray(bindings)) => {
+            check_array_len(entry, bindings.len())?;
+            bindings.iter().enumerate().try_for_each(|(i, binding)|
+                check_buffer(entry, binding).map_err(|err| format!("[{i}] {err}"))
+            )
+        },
+
+        (BindingType::Texture {..} | BindingType::StorageTexture {..}, Resource::Texture(_, dsc)) => {
+            if entry.count.is_some() { return Err("array binding requires a texture array".to_string()) }
+            dsc.map_or(Ok(()), |dsc| check_texture(entry, dsc))
+        },
+        (BindingType::Texture {..} | BindingType::StorageTexture {..}, Resource::TextureArray(views)) => {
+            check_array_len(entry, views.len())
+        },
+
+        (BindingType::Sampler(ty), Resource::Sampler(_, dsc)) => {
+            if entry.count.is_some() { return Err("array binding requires a sampler array".to_string()) }
+            dsc.map_or(Ok(()), |dsc| check_sampler(*ty, dsc))
+        },
+        (BindingType::Sampler(_), Resource::SamplerArray(samplers)) => {
+            check_array_len(entry, samplers.len())
+        },
+
+        (ty, resource) => Err(format!("expected {}, got {}", binding_kind(ty), resource.kind())),
+    }
+}
+
+
+// checks each resource against its layout entry, collecting all errors
+fn validate(entries: &[BindGroupLayoutEntry], resources: &[(u32, Resource)]) -> Res<()> {
+
+    let mut errors = String::new();
+
+    for (i, (binding, resource)) in resources.iter().enumerate() {
+
+        if resources[..i].iter().any(|(b, _)| b == binding) {
+            let _ = write!(errors, "\n  binding {binding}: bound more than once");
+            continue;
+        }
+
+        match entries.iter().find(|entry| entry.binding == *binding) {
+            Some(entry) => if let Err(err) = check_resource(entry, resource) {
+                let _ = write!(errors, "\n  binding {binding} ({}): {err}", binding_kind(&entry.ty));
+            },
+            None => {
+                let _ = write!(errors, "\n  binding {binding}: not in layout");
+            },
+        }
+    }
+
+    for entry in entries {
+        if !resources.iter().any(|(binding, _)| *binding == entry.binding) {
+            let _ = write!(errors, "\n  binding {} ({}): missing", entry.binding, binding_kind(&entry.ty));
+        }
+    }
+
+    if !errors.is_empty() { bail!("invalid bind group:{errors}") }
+
+    Ok(())
+}
+
+
+// builder
+
+#[derive(Debug)]
+pub struct BindGroupBuilder<'a, G: WgxDevice> {
+    gx: &'a G,
+    layout: &'a BindGroupLayoutDsc,
+    resources: Vec<(u32, Resource<'a>)>,
+}
+
+impl<'a, G: WgxDevice> BindGroupBuilder<'a, G> {
+
+    fn with(mut self, binding: u32, resource: Resource<'a>) -> Self {
+        self.resources.push((binding, resource));
+        self
+    }
+
+    pub fn buffer(self, binding: u32, buffer: &'a wgpu::Buffer) -> Self {
+        self.with(binding, Resource::Buffer(BufferBinding { buffer, offset: 0, size: None }))
+    }
+
+    pub fn buffer_range(self, binding: u32, buffer: &'a wgpu::Buffer, offset: u64, size: Option<NonZeroU64>) -> Self {
+        self.with(binding, Resource::Buffer(BufferBinding { buffer, offset, size }))
+    }
+
+    pub fn buffer_array(self, binding: u32, buffers: &'a [BufferBinding<'a>]) -> Self {
+        self.with(binding, Resource::BufferArray(buffers))
+    }
+
+    pub fn texture(self, binding: u32, view: &'a wgpu::TextureView) -> Self {
+        self.with(binding, Resource::Texture(view, None))
+    }
+
+    // texture with descriptor, which is checked against the layout
+    pub fn texture_dsc(self, binding: u32, view: &'a wgpu::TextureView, descriptor: &'a TexDsc) -> Self {
+        self.with(binding, Resource::Texture(view, Some(descriptor)))
+    }
+
+    pub fn texture_lot(self, binding: u32, lot: &'a TextureLot) -> Self {
+        self.texture_dsc(binding, &lot.view, &lot.descriptor)
+    }
+
+    pub fn texture_array(self, binding: u32, views: &'a [&'a wgpu::TextureView]) -> Self {
+        self.with(binding, Resource::TextureArray(views))
+    }
+
+    pub fn sampler(self, binding: u32, sampler: &'a wgpu::Sampler) -> Self {
+        self.with(binding, Resource::Sampler(sampler, None))
+    }
+
+    // sampler with descriptor, which is checked against the layout
+    pub fn sampler_dsc(self, binding: u32, sampler: &'a wgpu::Sampler, descriptor: &'a SamplerDsc) -> Self {
+        self.with(binding, Resource::Sampler(sampler, Some(descriptor)))
+    }
+
+    pub fn sampler_array(self, binding: u32, samplers: &'a [&'a wgpu::Sampler]) -> Self {
+        self.with(binding, Resource::SamplerArray(samplers))
+    }
+
+
+    pub fn validate(&self) -> Res<()> {
+        validate(&self.layout.entries, &self.resources)
+    }
+
+
+    pub fn finish(self) -> Res<wgpu::BindGroup> {
+
+        self.validate()?;
+
+        let entries: Vec<_> = self.resources.iter().map(|(binding, resource)| wgpu::BindGroupEntry {
+            binding: *binding, resource: resource.binding_resource(),
+        }).collect();
+
+        Ok(self.gx.bind(&self.layout.layout, &entries))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wgpu::{TextureFormat, TextureViewDimension, CompareFunction};
+
+    // arrays are the only resources available without a device
+    const SAMPLERS: Resource = Resource::SamplerArray(&[]);
+
+    fn errors(entries: &[BindGroupLayoutEntry], resources: &[(u32, Resource)]) -> String {
+        format!("{:#}", validate(entries, resources).unwrap_err())
+    }
+
+    #[test]
+    fn empty_layout() {
+        assert!(validate(&[], &[]).is_ok());
+    }
+
+    #[test]
+    fn missing_entries() {
+        let entries = [binding!(0, Stage::FRAGMENT, Texture, D2), binding!(1, Stage::FRAGMENT, Sampler)];
+        let errors = errors(&entries, &[]);
+
+        assert!(errors.contains("binding 0 (texture): missing"), "{errors}");
+        assert!(errors.contains("binding 1 (sampler): missing"), "{errors}");
+    }
+
+    #[test]
+    fn duplicate_and_unknown_bindings() {
+        let entries = [binding!(0, Stage::FRAGMENT, Sampler, [2])];
+        let errors = errors(&entries, &[(0, SAMPLERS), (0, SAMPLERS), (5, SAMPLERS)]);
+
+        assert!(errors.contains("binding 0: bound more than once"), "{errors}");
+        assert!(errors.contains("binding 5: not in layout"), "{errors}");
+        assert!(!errors.contains("missing"), "{errors}");
+    }
+
+    #[test]
+    fn mismatched_entries() {
+        let entries = [
+            binding!(0, Stage::FRAGMENT, Texture, D2), binding!(1, Stage::FRAGMENT, Sampler), binding!(2, Stage::FRAGMENT, Sampler, [2]),
+        ];
+        let errors = errors(&entries, &[(0, SAMPLERS), (1, SAMPLERS), (2, SAMPLERS)]);
+
+        assert!(errors.contains("binding 0 (texture): expected texture, got sampler array"), "{errors}");
+        assert!(errors.contains("binding 1 (sampler): is not an array binding"), "{errors}");
+        assert!(errors.contains("binding 2 (sampler): array of length 0 doesn't fit layout count 2"), "{errors}");
+    }
+
+    #[test]
+    fn texture_checks() {
+        let entry = binding!(0, Stage::FRAGMENT, Texture, D2);
+        let dsc = TexDsc::new_2d([4, 4, 1], 1, TextureFormat::Rgba8Unorm, None, TexUse::TEXTURE_BINDING);
+
+        assert!(check_texture(&entry, &dsc).is_ok());
+        assert!(check_texture(&entry, &TexDsc { usage: TexUse::COPY_DST, ..dsc }).unwrap_err().contains("TEXTURE_BINDING"));
+        assert!(check_texture(&entry, &TexDsc { view_dimension: TextureViewDimension::D2Array, ..dsc }).unwrap_err().contains("view dimension"));
+        assert!(check_texture(&entry, &TexDsc { sample_count: 4, ..dsc }).unwrap_err().contains("sample count"));
+        assert!(check_texture(&entry, &TexDsc { view_format: TextureFormat::R32Uint, ..dsc }).unwrap_err().contains("sample type"));
+    }
+
+    #[test]
+    fn sample_types() {
+        use TextureSampleType::*;
+
+        assert!(sample_type_compatible(Float { filterable: false }, Float { filterable: true }));
+        assert!(sample_type_compatible(Float { filterable: false }, Depth));
+        assert!(!sample_type_compatible(Float { filterable: true }, Float { filterable: false }));
+        assert!(!sample_type_compatible(Float { filterable: true }, Depth));
+        assert!(!sample_type_compatible(Uint, Sint));
+    }
+
+    #[test]
+    fn sampler_checks() {
+        assert!(check_sampler(SamplerBindingType::Filtering, &SamplerDsc::LINEAR).is_ok());
+        assert!(check_sampler(SamplerBindingType::NonFiltering, &SamplerDsc::NEAREST).is_ok());
+        assert!(check_sampler(SamplerBindingType::Comparison, &SamplerDsc::LINEAR.compare(CompareFunction::Less)).is_ok());
+
+        assert!(check_sampler(SamplerBindingType::Comparison, &SamplerDsc::LINEAR).is_err());
+        assert!(check_sampler(SamplerBindingType::Filtering, &SamplerDsc::LINEAR.compare(CompareFunction::Less)).is_err());
+        assert!(check_sampler(SamplerBindingType::NonFiltering, &SamplerDsc::LINEAR).is_err());
+    }
+}
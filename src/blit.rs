@@ -0,0 +1,160 @@
+use std::{sync::{Arc, Mutex}, collections::{HashMap, hash_map::Entry}};
+use wgpu::{TextureFormat, FilterMode};
+use anyhow::{Result as Res};
+use crate::*;
+
+
+// bind group of a source view and filter with its params buffer, used since the last end_frame
+#[derive(Debug)]
+struct Binding {
+    bind_group: wgpu::BindGroup,
+    params: wgpu::Buffer,
+    used: bool,
+}
+
+// pipelines for copying between textures of different sizes and formats, one per destination format and msaa
+// srgb conversion happens through the view formats of source and destination
+// bind groups are kept per source view and filter, they keep the view alive until end_frame or clear_bindings
+#[derive(Debug)]
+pub struct Blitter {
+    pub layout: BindGroupLayoutDsc,
+    module: Arc<wgpu::ShaderModule>,
+    pipeline_layout: Arc<wgpu::PipelineLayout>,
+    samplers: [Arc<wgpu::Sampler>; 2],
+    pipelines: Mutex<HashMap<(TextureFormat, u32), Arc<wgpu::RenderPipeline>>>,
+    bindings: Mutex<HashMap<(wgpu::Id<wgpu::TextureView>, FilterMode), Binding>>,
+}
+
+impl Blitter {
+
+    pub fn new(gx: &impl WgxDevice) -> Self {
+        let layout = gx.layout_dsc(&[
+            binding!(0, Stage::VERTEX_FRAGMENT, Texture, D2),
+            binding!(1, Stage::FRAGMENT, Sampler),
+            binding!(2, Stage::VERTEX, UniformBuffer, 16),
+        ]);
+        let pipeline_layout = gx.shared_pipeline_layout(&[], &[&layout.layout]);
+
+        Self {
+            module: gx.shared_wgsl(include_str!("shaders/blit.wgsl")),
+            samplers: [gx.shared_sampler(&SamplerDsc::NEAREST), gx.shared_sampler(&SamplerDsc::LINEAR)],
+            pipelines: Mutex::default(),
+            bindings: Mutex::default(),
+            layout, pipeline_layout,
+        }
+    }
+
+    pub fn pipeline(&self, gx: &impl WgxDevice, format: TextureFormat, msaa: u32) -> Arc<wgpu::RenderPipeline> {
+        let mut pipelines = self.pipelines.lock().unwrap_or_else(|err| err.into_inner());
+
+        Arc::clone(pipelines.entry((format, msaa)).or_insert_with(|| gx.shared_render_pipeline(
+            msaa, None, Some(&self.pipeline_layout), &[],
+            (&self.module, "vs_main", Primitive::default()),
+            Some((&self.module, "fs_main", &[(format, None)])),
+        )))
+    }
+
+    fn sampler(&self, filter: FilterMode) -> (&wgpu::Sampler, &'static SamplerDsc) {
+        match filter {
+            FilterMode::Nearest => (&self.samplers[0], &SamplerDsc::NEAREST),
+            FilterMode::Linear => (&self.samplers[1], &SamplerDsc::LINEAR),
+        }
+    }
+
+    // copies the source rect into the params of the binding, then sets it
+    fn set_binding(
+        &self, gx: &impl WgxDevice, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView, rect: Region, filter: FilterMode,
+        handler: impl FnOnce(&mut wgpu::CommandEncoder, &wgpu::BindGroup),
+    ) -> Res<()> {
+        let mut bindings = self.bindings.lock().unwrap_or_else(|err| err.into_inner());
+
+        let binding = match bindings.entry((view.global_id(), filter)) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let params = gx.buffer(BufUse::UNIFORM | BufUse::COPY_DST, 16, false);
+                let (sampler, sampler_dsc) = self.sampler(filter);
+
+                let bind_group = self.layout.bind(gx)
+                    .texture(0, view)
+                    .sampler_dsc(1, sampler, sampler_dsc)
+                    .buffer(2, &params)
+                    .finish()?;
+
+                entry.insert(Binding { bind_group, params, used: false })
+            },
+        };
+
+        // encoded before the pass, so each blit of a submit sees its own rect
+        let rect = gx.buffer_from_data(BufUse::COPY_SRC, [rect.x, rect.y, rect.width, rect.height]);
+        encoder.copy_buffer_to_buffer(&rect, 0, &binding.params, 0, 16);
+
+        binding.used = true;
+        handler(encoder, &binding.bind_group);
+        Ok(())
+    }
+
+    // drops bind groups not used since the last call, e.g. of source textures recreated on resize
+    // call once per frame when blitting from changing textures
+    pub fn end_frame(&self) {
+        self.bindings.lock().unwrap_or_else(|err| err.into_inner()).retain(|_, binding| {
+            std::mem::take(&mut binding.used)
+        });
+    }
+
+    // drops all cached bind groups
+    pub fn clear_bindings(&self) {
+        self.bindings.lock().unwrap_or_else(|err| err.into_inner()).clear();
+    }
+}
+
+
+pub trait BlitExtension {
+
+    // draws the source rect into the rect of the target, keeping the rest of the target
+    // rects are in pixels, negative source sizes flip
+    // dst_rect is clamped to the target, cropping the source rect in the same proportion
+    fn blit(
+        &mut self, gx: &impl WgxDevice, blitter: &Blitter,
+        src: (&wgpu::TextureView, Region), dst: (&impl RenderAttachable, Region), filter: FilterMode,
+    ) -> Res<()>;
+
+    // whole texture scaled to the whole target, call blitter.end_frame after frames if the texture is recreated on resize
+    fn present_texture(&mut self, gx: &impl WgxDevice, blitter: &Blitter, frame: &impl RenderAttachable, lot: &TextureLot) -> Res<()> {
+        self.blit(
+            gx, blitter, (&lot.view, Region::full(lot.descriptor.size_2d())), (frame, frame.region()), FilterMode::Linear,
+        )
+    }
+}
+
+impl BlitExtension for wgpu::CommandEncoder {
+
+    fn blit(
+        &mut self, gx: &impl WgxDevice, blitter: &Blitter,
+        (src_view, src_rect): (&wgpu::TextureView, Region),
+        (dst_target, dst_rect): (&impl RenderAttachable, Region),
+        filter: FilterMode,
+    ) -> Res<()> {
+
+        let clamped = dst_rect.clamp(dst_target.size());
+        if clamped.is_empty() { return Ok(()) }
+
+        // fractions of dst_rect still covered
+        let [x0, y0] = [(clamped.x - dst_rect.x) / dst_rect.width, (clamped.y - dst_rect.y) / dst_rect.height];
+        let [x1, y1] = [x0 + clamped.width / dst_rect.width, y0 + clamped.height / dst_rect.height];
+
+        let src_rect = Region::new(
+            src_rect.x + x0 * src_rect.width, src_rect.y + y0 * src_rect.height,
+            (x1 - x0) * src_rect.width, (y1 - y0) * src_rect.height,
+        );
+
+        let pipeline = blitter.pipeline(gx, dst_target.view_format(), dst_target.msaa());
+
+        blitter.set_binding(gx, self, src_view, src_rect, filter, |encoder, binding| {
+            let mut rpass = encoder.render_pass(([Some(dst_target.color_attachment(None))], None));
+            rpass.set_region(&clamped, dst_target.size());
+            rpass.set_pipeline(&pipeline);
+            rpass.set_bind_group(0, binding, &[]);
+            rpass.draw(0..3, 0..1);
+        })
+    }
+}
@@ -0,0 +1,109 @@
+use arrayvec::ArrayVec;
+use wgpu::TextureFormat;
+use crate::*;
+
+
+// everything a render bundle has to match to be executed in a pass of a target
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BundleKey {
+    pub formats: ArrayVec<Option<TextureFormat>, 8>,
+    pub depth_testing: Option<TextureFormat>,
+    pub msaa: u32,
+    pub generation: u64,
+}
+
+impl BundleKey {
+
+    pub fn of_target(target: &impl RenderTarget) -> Self {
+        Self {
+            formats: target.color_formats().into_iter().map(Some).collect(),
+            depth_testing: target.depth_testing(), msaa: target.msaa(), generation: target.generation(),
+        }
+    }
+
+    // bundle matching the key
+    pub fn render_bundle<'a>(&self, gx: &'a impl WgxDevice, handler: impl FnOnce(&mut wgpu::RenderBundleEncoder<'a>)) -> wgpu::RenderBundle {
+        gx.render_bundle(&self.formats, self.depth_testing, self.msaa, handler)
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BundleStats {
+    pub builds: u64,
+    pub mismatches: u64, // rebuilds caused by a changed key
+    pub invalidations: u64, // rebuilds requested with invalidate
+    pub hits: u64,
+}
+
+
+// render bundle rebuilt with the stored closure whenever the target changes
+// the closure should use the key for its bundle and pipelines, e.g. key.render_bundle(gx, ..)
+pub struct CachedBundle<'r> {
+    record: Box<dyn FnMut(&BundleKey) -> wgpu::RenderBundle + 'r>,
+    cached: Option<(BundleKey, wgpu::RenderBundle)>,
+    invalid: bool,
+    stats: BundleStats,
+}
+
+impl std::fmt::Debug for CachedBundle<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("CachedBundle")
+            .field("key", &self.key())
+            .field("invalid", &self.invalid)
+            .field("stats", &self.stats)
+            .finish()
+    }
+}
+
+impl<'r> CachedBundle<'r> {
+
+    pub fn new(record: impl FnMut(&BundleKey) -> wgpu::RenderBundle + 'r) -> Self {
+        Self { record: Box::new(record), cached: None, invalid: false, stats: BundleStats::default() }
+    }
+
+    pub fn key(&self) -> Option<&BundleKey> { self.cached.as_ref().map(|(key, _)| key) }
+    pub fn stats(&self) -> BundleStats { self.stats }
+
+    // current bundle without checking, None if never built
+    pub fn get(&self) -> Option<&wgpu::RenderBundle> { self.cached.as_ref().map(|(_, bundle)| bundle) }
+
+    pub fn is_compatible(&self, key: &BundleKey) -> bool {
+        !self.invalid && self.key() == Some(key)
+    }
+
+    // rebuild on next use, e.g. after the recorded resources changed
+    pub fn invalidate(&mut self) { self.invalid = true }
+
+    // rebuilds if needed
+    pub fn update(&mut self, key: &BundleKey) -> &wgpu::RenderBundle {
+        if self.is_compatible(key) {
+            self.stats.hits += 1;
+        }
+        else {
+            if self.invalid { self.stats.invalidations += 1 }
+            else if self.cached.is_some() { self.stats.mismatches += 1 }
+
+            self.stats.builds += 1;
+            self.invalid = false;
+            self.cached = Some((key.clone(), (self.record)(key)));
+        }
+        self.get().unwrap()
+    }
+
+    pub fn bundle(&mut self, target: &impl RenderTarget) -> &wgpu::RenderBundle {
+        self.update(&BundleKey::of_target(target))
+    }
+}
+
+
+// updates the bundles for the key and executes them in one pass
+pub fn pass_cached_bundles<'a, const S: usize>(
+    encoder: &'a mut wgpu::CommandEncoder, attachments: RenderAttachments<'a, S>,
+    key: &BundleKey, bundles: &'a mut [CachedBundle],
+) {
+    for bundle in bundles.iter_mut() { bundle.update(key); }
+
+    let bundles: &'a [CachedBundle] = bundles;
+    encoder.pass_bundles(attachments, bundles.iter().filter_map(|bundle| bundle.get()));
+}
@@ -0,0 +1,562 @@
+
+// cameras with projections, controllers driven by abstract input, frustum culling and a uniform buffer helper
+// left handed like the examples: the camera looks along +z with +y up, depth goes from 0 to 1
+
+use std::{mem::size_of, f32::consts::FRAC_PI_2};
+use wgpu::CompareFunction;
+use crate::{*, math::*, mesh::{Aabb, BoundingSphere}};
+
+
+// projections
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProjectionKind {
+    Perspective { fov_y: f32 }, // radians
+    Orthographic { height: f32 }, // of the view volume
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Projection {
+    pub kind: ProjectionKind,
+    pub aspect: f32, // width / height
+    pub near: f32,
+    pub far: f32, // infinite for no far plane, perspective only
+    pub reversed_z: bool, // depth 1 at near and 0 at far, more precise with float depth formats
+}
+
+impl Projection {
+
+    pub fn perspective(fov_y: f32, aspect: f32, near: f32, far: f32) -> Self {
+        Self { kind: ProjectionKind::Perspective { fov_y }, aspect, near, far, reversed_z: false }
+    }
+
+    pub fn orthographic(height: f32, aspect: f32, near: f32, far: f32) -> Self {
+        Self { kind: ProjectionKind::Orthographic { height }, aspect, near, far, reversed_z: false }
+    }
+
+    pub fn infinite(self) -> Self { Self { far: f32::INFINITY, ..self } }
+    pub fn reversed_z(self, reversed_z: bool) -> Self { Self { reversed_z, ..self } }
+
+    pub fn is_infinite(&self) -> bool { self.far.is_infinite() }
+
+    // aspect of a target size
+    pub fn resize(&mut self, [width, height]: [u32; 2]) {
+        self.aspect = width as f32 / height.max(1) as f32;
+    }
+
+    pub fn matrix(&self) -> Mat4 {
+        let (near, far) = (self.near, self.far);
+
+        match self.kind {
+            ProjectionKind::Perspective { fov_y } => {
+                // depth = a + b / z
+                let (a, b) = match (self.reversed_z, self.is_infinite()) {
+                    (false, false) => (far / (far - near), -near * far / (far - near)),
+                    (false, true) => (1.0, -near),
+                    (true, false) => (-near / (far - near), near * far / (far - near)),
+                    (true, true) => (0.0, near),
+                };
+                let h = 1.0 / (fov_y / 2.0).tan();
+                Mat4::from_cols(
+                    Vec4::new(h / self.aspect, 0.0, 0.0, 0.0), Vec4::new(0.0, h, 0.0, 0.0),
+                    Vec4::new(0.0, 0.0, a, 1.0), Vec4::new(0.0, 0.0, b, 0.0),
+                )
+            },
+            ProjectionKind::Orthographic { height } => {
+                debug_assert!(far.is_finite(), "orthographic projections need a finite far plane");
+
+                // depth = a * z + b
+                let (a, b) = if self.reversed_z {
+                    (-1.0 / (far - near), far / (far - near))
+                } else {
+                    (1.0 / (far - near), -near / (far - near))
+                };
+                Mat4::from_cols(
+                    Vec4::new(2.0 / (height * self.aspect), 0.0, 0.0, 0.0), Vec4::new(0.0, 2.0 / height, 0.0, 0.0),
+                    Vec4::new(0.0, 0.0, a, 0.0), Vec4::new(0.0, 0.0, b, 1.0),
+                )
+            },
+        }
+    }
+
+    // depth test and clear value matching the depth direction
+    pub fn depth_compare(&self) -> CompareFunction {
+        if self.reversed_z { CompareFunction::GreaterEqual } else { CompareFunction::LessEqual }
+    }
+
+    pub fn clear_depth(&self) -> f32 { if self.reversed_z { 0.0 } else { 1.0 } }
+
+    pub fn depth_state(&self, format: TextureFormat) -> wgpu::DepthStencilState {
+        wgpu::DepthStencilState { depth_compare: self.depth_compare(), ..depth_state(format) }
+    }
+}
+
+
+// camera
+
+// rotation looking along direction, up only needs to point roughly upwards
+pub fn look_rotation(direction: Vec3, up: Vec3) -> Quat {
+    let forward = direction.normalize_or(Vec3::Z);
+    let right = up.cross(forward).try_normalize().unwrap_or_else(|| forward.any_orthonormal_vector());
+    Quat::from_mat3(&Mat3::from_cols(right, forward.cross(right), forward))
+}
+
+// yaw to the right around +y, then pitch upwards
+fn yaw_pitch_rotation(yaw: f32, pitch: f32) -> Quat {
+    Quat::from_rotation_y(yaw) * Quat::from_rotation_x(-pitch)
+}
+
+fn yaw_pitch(direction: Vec3) -> (f32, f32) {
+    let direction = direction.normalize_or(Vec3::Z);
+    (direction.x.atan2(direction.z), direction.y.clamp(-1.0, 1.0).asin())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    pub position: Vec3,
+    pub rotation: Quat, // from camera to world space
+    pub projection: Projection,
+}
+
+impl Camera {
+
+    pub fn new(position: impl Into<Vec3>, rotation: Quat, projection: Projection) -> Self {
+        Self { position: position.into(), rotation, projection }
+    }
+
+    pub fn looking_at(position: impl Into<Vec3>, target: impl Into<Vec3>, projection: Projection) -> Self {
+        let position = position.into();
+        Self::new(position, look_rotation(target.into() - position, Vec3::Y), projection)
+    }
+
+    pub fn look_at(&mut self, target: impl Into<Vec3>, up: impl Into<Vec3>) {
+        self.rotation = look_rotation(target.into() - self.position, up.into());
+    }
+
+    pub fn forward(&self) -> Vec3 { self.rotation * Vec3::Z }
+    pub fn right(&self) -> Vec3 { self.rotation * Vec3::X }
+    pub fn up(&self) -> Vec3 { self.rotation * Vec3::Y }
+
+    pub fn view(&self) -> Mat4 {
+        Mat4::from_quat(self.rotation.inverse()) * Mat4::from_translation(-self.position)
+    }
+
+    pub fn view_projection(&self) -> Mat4 { self.projection.matrix() * self.view() }
+
+    pub fn frustum(&self) -> Frustum { Frustum::from_matrix(&self.view_projection()) }
+
+    pub fn uniform(&self) -> CameraUniform {
+        let view = self.view();
+        let projection = self.projection.matrix();
+        let view_projection = projection * view;
+
+        CameraUniform {
+            view, projection, view_projection,
+            inverse_view: view.inverse(), inverse_projection: projection.inverse(),
+            inverse_view_projection: view_projection.inverse(),
+            position: self.position, near: self.projection.near,
+            far: self.projection.far.min(f32::MAX),
+            _padding: [0.0; 3],
+        }
+    }
+}
+
+
+// controllers
+
+// input of one update, e.g. mapped from keys, mouse motion and the scroll wheel
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct CameraInput {
+    pub movement: Vec3, // x right, y up, z forward, usually -1 to 1 per axis, scaled by speed and time
+    pub rotation: Vec2, // yaw to the right and pitch upwards, scaled by the sensitivity, e.g. mouse motion
+    pub roll: f32, // to the left, scaled like movement
+    pub zoom: f32, // zooms in if positive, e.g. scroll wheel lines
+    pub pan: Vec2, // moves an orbit target in the view plane, relative to the distance
+}
+
+impl CameraInput {
+    pub fn is_zero(&self) -> bool { *self == Self::default() }
+}
+
+pub trait CameraController {
+    // applies the input and advances the smoothing by dt seconds
+    fn update(&mut self, camera: &mut Camera, input: &CameraInput, dt: f32);
+}
+
+// part of the remaining way covered in dt, smoothing is the time constant in seconds, 0 for none
+pub fn smoothing_factor(smoothing: f32, dt: f32) -> f32 {
+    if smoothing <= 0.0 { 1.0 } else { 1.0 - (-dt / smoothing).exp() }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Orbit { target: Vec3, distance: f32, yaw: f32, pitch: f32 }
+
+impl Orbit {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        Self {
+            target: self.target.lerp(other.target, t), distance: self.distance + (other.distance - self.distance) * t,
+            yaw: self.yaw + (other.yaw - self.yaw) * t, pitch: self.pitch + (other.pitch - self.pitch) * t,
+        }
+    }
+}
+
+// rotates around and zooms towards a target, the public state is where the camera is heading
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrbitController {
+    pub target: Vec3,
+    pub distance: f32,
+    pub yaw: f32,
+    pub pitch: f32, // of the view direction, positive looks up
+    pub min_distance: f32,
+    pub max_distance: f32,
+    pub pitch_limit: f32,
+    pub sensitivity: f32,
+    pub zoom_speed: f32,
+    pub pan_speed: f32,
+    pub move_speed: f32, // of the target, units per second
+    pub smoothing: f32,
+    current: Orbit,
+}
+
+impl OrbitController {
+
+    pub fn new(target: impl Into<Vec3>, distance: f32) -> Self {
+        let orbit = Orbit { target: target.into(), distance, yaw: 0.0, pitch: 0.0 };
+        Self {
+            target: orbit.target, distance, yaw: 0.0, pitch: 0.0,
+            min_distance: 1e-3, max_distance: f32::INFINITY, pitch_limit: FRAC_PI_2 - 1e-3,
+            sensitivity: 1.0, zoom_speed: 0.1, pan_speed: 1.0, move_speed: 1.0, smoothing: 0.0,
+            current: orbit,
+        }
+    }
+
+    // keeps the current view of the camera
+    pub fn from_camera(camera: &Camera, target: impl Into<Vec3>) -> Self {
+        let target = target.into();
+        let (yaw, pitch) = yaw_pitch(target - camera.position);
+        let mut controller = Self::new(target, camera.position.distance(target));
+        (controller.yaw, controller.pitch) = (yaw, pitch);
+        controller.snap();
+        controller
+    }
+
+    fn goal(&self) -> Orbit {
+        Orbit { target: self.target, distance: self.distance, yaw: self.yaw, pitch: self.pitch }
+    }
+
+    // skips the smoothing
+    pub fn snap(&mut self) { self.current = self.goal() }
+}
+
+impl CameraController for OrbitController {
+    fn update(&mut self, camera: &mut Camera, input: &CameraInput, dt: f32) {
+        self.yaw += input.rotation.x * self.sensitivity;
+        self.pitch = (self.pitch + input.rotation.y * self.sensitivity).clamp(-self.pitch_limit, self.pitch_limit);
+        self.distance = (self.distance * (-input.zoom * self.zoom_speed).exp()).clamp(self.min_distance, self.max_distance);
+
+        let rotation = yaw_pitch_rotation(self.yaw, self.pitch);
+        let pan = input.pan.extend(0.0) * self.pan_speed * self.distance;
+        self.target += rotation * (pan + input.movement * self.move_speed * dt);
+
+        self.current = self.current.lerp(self.goal(), smoothing_factor(self.smoothing, dt));
+
+        camera.rotation = yaw_pitch_rotation(self.current.yaw, self.current.pitch);
+        camera.position = self.current.target - camera.forward() * self.current.distance;
+    }
+}
+
+
+// free flight with roll, movement and rotation relative to the camera
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlyController {
+    pub speed: f32, // units per second
+    pub sensitivity: f32,
+    pub roll_speed: f32, // radians per second
+    pub smoothing: f32,
+    velocity: Vec3,
+    pending: Vec3, // yaw, pitch and roll not applied yet
+}
+
+impl FlyController {
+    pub fn new(speed: f32) -> Self {
+        Self { speed, sensitivity: 1.0, roll_speed: 1.0, smoothing: 0.0, velocity: Vec3::ZERO, pending: Vec3::ZERO }
+    }
+
+    pub fn stop(&mut self) {
+        self.velocity = Vec3::ZERO;
+        self.pending = Vec3::ZERO;
+    }
+}
+
+impl CameraController for FlyController {
+    fn update(&mut self, camera: &mut Camera, input: &CameraInput, dt: f32) {
+        let t = smoothing_factor(self.smoothing, dt);
+
+        self.pending += Vec3::new(
+            input.rotation.x * self.sensitivity, input.rotation.y * self.sensitivity, input.roll * self.roll_speed * dt,
+        );
+        let applied = self.pending * t;
+        self.pending -= applied;
+
+        camera.rotation = (
+            camera.rotation * yaw_pitch_rotation(applied.x, applied.y) * Quat::from_rotation_z(applied.z)
+        ).normalize();
+
+        self.velocity = self.velocity.lerp(camera.rotation * input.movement * self.speed, t);
+        camera.position += self.velocity * dt;
+    }
+}
+
+
+// yaw and pitch without roll, moving on the horizontal plane and along +y
+#[derive(Debug, Clone, PartialEq)]
+pub struct FirstPersonController {
+    pub yaw: f32,
+    pub pitch: f32, // positive looks up
+    pub pitch_limit: f32,
+    pub speed: f32, // units per second
+    pub sensitivity: f32,
+    pub smoothing: f32,
+    velocity: Vec3,
+    current: Vec2, // yaw and pitch
+}
+
+impl FirstPersonController {
+
+    // keeps the view direction of the camera
+    pub fn new(camera: &Camera, speed: f32) -> Self {
+        let (yaw, pitch) = yaw_pitch(camera.forward());
+        Self {
+            yaw, pitch, pitch_limit: FRAC_PI_2 - 1e-3, speed, sensitivity: 1.0, smoothing: 0.0,
+            velocity: Vec3::ZERO, current: Vec2::new(yaw, pitch),
+        }
+    }
+
+    pub fn snap(&mut self) {
+        self.current = Vec2::new(self.yaw, self.pitch);
+    }
+}
+
+impl CameraController for FirstPersonController {
+    fn update(&mut self, camera: &mut Camera, input: &CameraInput, dt: f32) {
+        let t = smoothing_factor(self.smoothing, dt);
+
+        self.yaw += input.rotation.x * self.sensitivity;
+        self.pitch = (self.pitch + input.rotation.y * self.sensitivity).clamp(-self.pitch_limit, self.pitch_limit);
+        self.current = self.current.lerp(Vec2::new(self.yaw, self.pitch), t);
+
+        camera.rotation = yaw_pitch_rotation(self.current.x, self.current.y);
+
+        let (sin, cos) = self.current.x.sin_cos();
+        let (forward, right) = (Vec3::new(sin, 0.0, cos), Vec3::new(cos, 0.0, -sin));
+        let movement = right * input.movement.x + Vec3::Y * input.movement.y + forward * input.movement.z;
+
+        self.velocity = self.velocity.lerp(movement * self.speed, t);
+        camera.position += self.velocity * dt;
+    }
+}
+
+
+// frustum culling
+
+// planes as normal and distance with the normals pointing inwards
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frustum {
+    pub planes: [Vec4; 6], // left, right, bottom, top, near, far
+}
+
+impl Frustum {
+
+    // from a view projection with depth from 0 to 1, reversed or not
+    pub fn from_matrix(view_projection: &Mat4) -> Self {
+        let [x, y, z, w] = [0, 1, 2, 3].map(|i| view_projection.row(i));
+
+        let planes = [w + x, w - x, w + y, w - y, z, w - z].map(|plane| {
+            let length = plane.truncate().length();
+            // the far plane of infinite projections contains everything
+            if length > 1e-6 { plane / length } else { Vec4::W }
+        });
+
+        Self { planes }
+    }
+
+    fn distance(plane: Vec4, point: Vec3) -> f32 { plane.truncate().dot(point) + plane.w }
+
+    pub fn contains_point(&self, point: Vec3) -> bool {
+        self.planes.iter().all(|&plane| Self::distance(plane, point) >= 0.0)
+    }
+
+    pub fn intersects_sphere(&self, sphere: &BoundingSphere) -> bool {
+        self.planes.iter().all(|&plane| Self::distance(plane, sphere.center) >= -sphere.radius)
+    }
+
+    // conservative, boxes near the edges may pass although outside
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|&plane| {
+            let normal = plane.truncate();
+            let corner = Vec3::select(normal.cmpge(Vec3::ZERO), aabb.max, aabb.min);
+            Self::distance(plane, corner) >= 0.0
+        })
+    }
+}
+
+
+// uniform buffer
+
+// view and projection with their inverses, matches struct Camera in CameraUniform::WGSL
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct CameraUniform {
+    pub view: Mat4,
+    pub projection: Mat4,
+    pub view_projection: Mat4,
+    pub inverse_view: Mat4,
+    pub inverse_projection: Mat4,
+    pub inverse_view_projection: Mat4,
+    pub position: Vec3,
+    pub near: f32,
+    pub far: f32, // f32::MAX for infinite projections
+    _padding: [f32; 3],
+}
+
+unsafe impl ReadBytes for CameraUniform {}
+
+impl CameraUniform {
+    pub const SIZE: u64 = size_of::<Self>() as u64;
+    pub const WGSL: &'static str = include_str!("shaders/camera.wgsl");
+}
+
+#[derive(Debug)]
+pub struct CameraBuffer {
+    pub buffer: wgpu::Buffer,
+}
+
+impl CameraBuffer {
+
+    pub fn new(gx: &impl WgxDevice) -> Self {
+        Self { buffer: gx.buffer(BufUse::UNIFORM | BufUse::COPY_DST, CameraUniform::SIZE, false) }
+    }
+
+    pub fn write(&self, gx: &impl WgxQueue, camera: &Camera) {
+        gx.write_buffer(&self.buffer, 0, camera.uniform());
+    }
+
+    pub fn layout_entry(binding: u32, stage: Stage) -> wgpu::BindGroupLayoutEntry {
+        binding!(binding, stage, UniformBuffer, CameraUniform::SIZE)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem::offset_of;
+
+    fn depth(projection: &Projection, z: f32) -> f32 {
+        projection.matrix().project_point3(Vec3::new(0.0, 0.0, z)).z
+    }
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-4, "{a} != {b}");
+    }
+
+    #[test]
+    fn perspective_depth_range() {
+        let projection = Projection::perspective(FRAC_PI_2, 1.5, 0.5, 50.0);
+
+        for (reversed_z, infinite) in [(false, false), (false, true), (true, false), (true, true)] {
+            let projection = projection.reversed_z(reversed_z);
+            let projection = if infinite { projection.infinite() } else { projection };
+            let (near, far) = if reversed_z { (1.0, 0.0) } else { (0.0, 1.0) };
+
+            assert_near(depth(&projection, 0.5), near);
+
+            if infinite {
+                assert!((depth(&projection, 1e6) - far).abs() < 1e-3);
+            } else {
+                assert_near(depth(&projection, 50.0), far);
+            }
+            assert_eq!(projection.clear_depth(), far);
+        }
+    }
+
+    #[test]
+    fn orthographic_depth_range() {
+        let projection = Projection::orthographic(4.0, 2.0, 1.0, 11.0);
+
+        assert_near(depth(&projection, 1.0), 0.0);
+        assert_near(depth(&projection, 11.0), 1.0);
+        assert_near(projection.matrix().project_point3(Vec3::new(4.0, 2.0, 5.0)).x, 1.0);
+        assert_near(projection.matrix().project_point3(Vec3::new(4.0, 2.0, 5.0)).y, 1.0);
+
+        let reversed = projection.reversed_z(true);
+        assert_near(depth(&reversed, 1.0), 1.0);
+        assert_near(depth(&reversed, 11.0), 0.0);
+        assert_eq!(reversed.depth_compare(), CompareFunction::GreaterEqual);
+    }
+
+    fn frustum(projection: Projection) -> Frustum {
+        Camera::new(Vec3::ZERO, Quat::IDENTITY, projection).frustum()
+    }
+
+    #[test]
+    fn frustum_contains() {
+        let projection = Projection::perspective(FRAC_PI_2, 1.0, 1.0, 10.0);
+
+        for frustum in [frustum(projection), frustum(projection.reversed_z(true))] {
+            assert!(frustum.contains_point(Vec3::new(0.0, 0.0, 5.0)));
+            assert!(frustum.contains_point(Vec3::new(4.0, -4.0, 5.0)));
+            assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, 0.5)));
+            assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, 11.0)));
+            assert!(!frustum.contains_point(Vec3::new(6.0, 0.0, 5.0)));
+            assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, -5.0)));
+        }
+    }
+
+    #[test]
+    fn infinite_frustum() {
+        let frustum = frustum(Projection::perspective(FRAC_PI_2, 1.0, 1.0, 10.0).infinite());
+
+        assert_eq!(frustum.planes[5], Vec4::W);
+        assert!(frustum.contains_point(Vec3::new(0.0, 0.0, 1e6)));
+        assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, 0.5)));
+    }
+
+    #[test]
+    fn frustum_intersects() {
+        let frustum = frustum(Projection::perspective(FRAC_PI_2, 1.0, 1.0, 10.0));
+
+        assert!(frustum.intersects_sphere(&BoundingSphere { center: Vec3::new(0.0, 0.0, 11.0), radius: 2.0 }));
+        assert!(frustum.intersects_sphere(&BoundingSphere { center: Vec3::new(6.0, 0.0, 5.0), radius: 1.0 }));
+        assert!(!frustum.intersects_sphere(&BoundingSphere { center: Vec3::new(0.0, 0.0, 11.0), radius: 0.5 }));
+        assert!(!frustum.intersects_sphere(&BoundingSphere { center: Vec3::new(0.0, 0.0, -3.0), radius: 1.0 }));
+
+        assert!(frustum.intersects_aabb(&Aabb { min: Vec3::new(-1.0, -1.0, 4.0), max: Vec3::new(1.0, 1.0, 6.0) }));
+        assert!(frustum.intersects_aabb(&Aabb { min: Vec3::new(4.0, -1.0, 4.0), max: Vec3::new(7.0, 1.0, 6.0) }));
+        assert!(!frustum.intersects_aabb(&Aabb { min: Vec3::new(8.0, -1.0, 4.0), max: Vec3::new(9.0, 1.0, 6.0) }));
+        assert!(!frustum.intersects_aabb(&Aabb { min: Vec3::new(-1.0, -1.0, 12.0), max: Vec3::new(1.0, 1.0, 14.0) }));
+    }
+
+    #[test]
+    fn uniform_layout() {
+        assert_eq!(CameraUniform::SIZE, 416);
+        assert_eq!(offset_of!(CameraUniform, position), 384);
+        assert_eq!(offset_of!(CameraUniform, near), 396);
+        assert_eq!(offset_of!(CameraUniform, far), 400);
+    }
+
+    #[test]
+    fn orbit_from_camera_keeps_pose() {
+        let projection = Projection::perspective(FRAC_PI_2, 1.0, 0.1, 100.0);
+        let original = Camera::looking_at([1.0, 4.5, -7.0], [0.0, 1.0, 0.0], projection);
+
+        let mut camera = original;
+        let mut controller = OrbitController::from_camera(&camera, [0.0, 1.0, 0.0]);
+        controller.update(&mut camera, &CameraInput::default(), 0.0);
+
+        assert!(camera.position.abs_diff_eq(original.position, 1e-4), "{}", camera.position);
+        assert!(camera.forward().abs_diff_eq(original.forward(), 1e-4));
+        assert!(camera.up().abs_diff_eq(original.up(), 1e-4));
+    }
+}
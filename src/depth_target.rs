@@ -0,0 +1,117 @@
+use std::sync::Arc;
+use wgpu::{TextureFormat, TextureViewDimension, TextureAspect, DepthBiasState};
+use anyhow::{Result as Res};
+use crate::*;
+
+
+// depth only render target, e.g. for shadow maps
+// layers are rendered one at a time, view samples all of them (D2, D2Array or Cube)
+#[derive(Debug)]
+pub struct DepthTarget {
+    pub lot: TextureLot,
+    pub layer_views: Vec<wgpu::TextureView>,
+    pub sampler: Arc<wgpu::Sampler>, // comparison sampler
+}
+
+impl DepthTarget {
+
+    pub fn new(gx: &impl WgxDevice, size: impl Into<[u32; 2]>, format: TextureFormat, layers: u32) -> Self {
+        let [w, h] = size.into();
+        Self::from_descriptor(gx, TexDsc::new_2d([w, h, layers], 1, format, None, TexUse::RENDER_ATTACHMENT | TexUse::TEXTURE_BINDING))
+    }
+
+    // six layers for point lights, sampled with a direction
+    pub fn new_cube(gx: &impl WgxDevice, size: u32, format: TextureFormat) -> Self {
+        let mut descriptor = TexDsc::new_2d([size, size, 6], 1, format, None, TexUse::RENDER_ATTACHMENT | TexUse::TEXTURE_BINDING);
+        descriptor.view_dimension = TextureViewDimension::Cube;
+        Self::from_descriptor(gx, descriptor)
+    }
+
+    fn from_descriptor(gx: &impl WgxDevice, mut descriptor: TexDsc) -> Self {
+        assert!(descriptor.format.is_depth_stencil_format(), "{:?} is no depth format", descriptor.format);
+
+        // only the depth aspect can be sampled
+        if descriptor.format.has_stencil_aspect() { descriptor.view_aspect = TextureAspect::DepthOnly }
+
+        let lot = TextureLot::new(gx, descriptor);
+
+        let layer_views = (0..descriptor.size[2]).map(|layer| lot.texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(TextureViewDimension::D2),
+            base_array_layer: layer,
+            array_layer_count: Some(1),
+            ..wgpu::TextureViewDescriptor::default()
+        })).collect();
+
+        Self { lot, layer_views, sampler: gx.shared_sampler(&SamplerDsc::SHADOW) }
+    }
+
+    pub fn size(&self) -> [u32; 2] { self.lot.descriptor.size_2d() }
+    pub fn layers(&self) -> u32 { self.lot.descriptor.size[2] }
+    pub fn format(&self) -> TextureFormat { self.lot.descriptor.format }
+    pub fn view(&self) -> &wgpu::TextureView { &self.lot.view }
+    pub fn view_dimension(&self) -> TextureViewDimension { self.lot.descriptor.view_dimension }
+
+
+    // render pass
+
+    // layer has to be below layers()
+    pub fn depth_attachment(&self, layer: u32, clear_depth: Option<f32>) -> wgpu::RenderPassDepthStencilAttachment {
+        debug_assert!(layer < self.layers(), "depth target layer {layer} out of range, the target has {} layers", self.layers());
+        DepthAttachment::new(&self.layer_views[layer as usize], self.format(), clear_depth, clear_depth.map(|_| 0)).into()
+    }
+
+    pub fn attachments(&self, layer: u32, clear_depth: Option<f32>) -> RenderAttachments<0> {
+        ([], Some(self.depth_attachment(layer, clear_depth)))
+    }
+
+    pub fn render_bundle<'a>(&self, gx: &'a impl WgxDevice, handler: impl FnOnce(&mut wgpu::RenderBundleEncoder<'a>)) -> wgpu::RenderBundle {
+        gx.render_bundle(&[], Some(self.format()), 1, handler)
+    }
+
+
+    // depth only pipeline, the fragment stage is only needed for alpha testing
+    pub fn render_pipeline(
+        &self, gx: &impl WgxDevice,
+        layout: Option<(&[wgpu::PushConstantRange], &[&wgpu::BindGroupLayout])>,
+        buffers: &[wgpu::VertexBufferLayout],
+        vertex_state: (&wgpu::ShaderModule, &str, Primitive),
+        fragment: Option<(&wgpu::ShaderModule, &str)>,
+        bias: DepthBiasState,
+    ) -> Arc<wgpu::RenderPipeline> {
+
+        let layout = layout.map(|(push_constant_ranges, bind_group_layouts)|
+            gx.shared_pipeline_layout(push_constant_ranges, bind_group_layouts)
+        );
+
+        gx.shared_render_pipeline(
+            1, Some(wgpu::DepthStencilState { bias, ..depth_state(self.format()) }),
+            layout.as_deref(), buffers, vertex_state,
+            fragment.map(|(module, entry_point)| (module, entry_point, &[] as &[wgpu::ColorTargetState; 0])),
+        )
+    }
+
+
+    // shadow sampling
+
+    // texture and comparison sampler entries, matching bind
+    pub fn layout_entries(&self, texture_binding: u32, sampler_binding: u32, stage: Stage) -> [wgpu::BindGroupLayoutEntry; 2] {
+        let mut texture = binding!(texture_binding, stage, DepthTexture, D2);
+        if let wgpu::BindingType::Texture { view_dimension, .. } = &mut texture.ty {
+            *view_dimension = self.view_dimension();
+        }
+        [texture, binding!(sampler_binding, stage, ComparisonSampler)]
+    }
+
+    pub fn bind<'a, G: WgxDevice>(
+        &'a self, builder: BindGroupBuilder<'a, G>, texture_binding: u32, sampler_binding: u32,
+    ) -> BindGroupBuilder<'a, G> {
+        builder
+            .texture_lot(texture_binding, &self.lot)
+            .sampler_dsc(sampler_binding, &self.sampler, &SamplerDsc::SHADOW)
+    }
+
+    // bind group with only the shadow map
+    pub fn bind_group(&self, gx: &impl WgxDevice, layout: &BindGroupLayoutDsc, texture_binding: u32, sampler_binding: u32) -> Res<wgpu::BindGroup> {
+        self.bind(layout.bind(gx), texture_binding, sampler_binding).finish()
+    }
+}
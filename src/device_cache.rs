@@ -0,0 +1,189 @@
+use std::{
+    sync::{Arc, Mutex, MutexGuard}, collections::HashMap, hash::Hash,
+    borrow::Cow,
+};
+use wgpu::{Id, BindGroupLayoutEntry, PushConstantRange, VertexAttribute, VertexStepMode, DepthStencilState};
+use crate::{*};
+
+
+// keys, shared objects are compared by their globally unique id
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct PipelineLayoutKey {
+    push_constant_ranges: Vec<PushConstantRange>,
+    bind_group_layouts: Vec<Id<wgpu::BindGroupLayout>>,
+}
+
+type VertexBufferKey = (wgpu::BufferAddress, VertexStepMode, Vec<VertexAttribute>);
+
+fn vertex_buffer_keys(buffers: &[wgpu::VertexBufferLayout]) -> Vec<VertexBufferKey> {
+    buffers.iter().map(|buffer| (buffer.array_stride, buffer.step_mode, buffer.attributes.to_vec())).collect()
+}
+
+type FragmentKey = (Id<wgpu::ShaderModule>, Box<str>, Vec<wgpu::ColorTargetState>);
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct RenderPipelineKey {
+    msaa: u32,
+    depth_stencil: Option<DepthStencilState>, // depth, stencil and bias state
+    layout: Option<Id<wgpu::PipelineLayout>>,
+    buffers: Vec<VertexBufferKey>,
+    vertex: (Id<wgpu::ShaderModule>, Box<str>, Primitive),
+    fragment: Option<FragmentKey>,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct ComputePipelineKey {
+    layout: Option<Id<wgpu::PipelineLayout>>,
+    module: Id<wgpu::ShaderModule>,
+    entry_point: Box<str>,
+}
+
+
+#[derive(Debug, Default)]
+struct Cache {
+    layouts: HashMap<Vec<BindGroupLayoutEntry>, Arc<wgpu::BindGroupLayout>>,
+    pipeline_layouts: HashMap<PipelineLayoutKey, Arc<wgpu::PipelineLayout>>,
+    samplers: HashMap<SamplerDsc, Arc<wgpu::Sampler>>,
+    shaders: HashMap<Box<str>, Arc<wgpu::ShaderModule>>,
+    render_pipelines: HashMap<RenderPipelineKey, Arc<wgpu::RenderPipeline>>,
+    compute_pipelines: HashMap<ComputePipelineKey, Arc<wgpu::ComputePipeline>>,
+}
+
+fn get_or_insert<K: Hash + Eq, T>(map: &mut HashMap<K, Arc<T>>, key: K, create: impl FnOnce() -> Arc<T>) -> Arc<T> {
+    Arc::clone(map.entry(key).or_insert_with(create))
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CacheStats {
+    pub layouts: usize,
+    pub pipeline_layouts: usize,
+    pub samplers: usize,
+    pub shaders: usize,
+    pub render_pipelines: usize,
+    pub compute_pipelines: usize,
+}
+
+
+// device wrapper which deduplicates layouts, samplers, shaders and pipelines
+// created through the shared_* methods of WgxDevice, as generic code like PostChain does
+#[derive(Debug)]
+pub struct WgxCache<G: WgxDevice> {
+    pub gx: G,
+    cache: Mutex<Cache>,
+}
+
+impl<G: WgxDevice> WgxCache<G> {
+
+    pub fn new(gx: G) -> Self {
+        Self { gx, cache: Mutex::default() }
+    }
+
+    pub fn into_inner(self) -> G { self.gx }
+
+    fn cache(&self) -> MutexGuard<Cache> {
+        self.cache.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let cache = self.cache();
+        CacheStats {
+            layouts: cache.layouts.len(),
+            pipeline_layouts: cache.pipeline_layouts.len(),
+            samplers: cache.samplers.len(),
+            shaders: cache.shaders.len(),
+            render_pipelines: cache.render_pipelines.len(),
+            compute_pipelines: cache.compute_pipelines.len(),
+        }
+    }
+
+    // drop all cached objects, objects still in use stay alive
+    pub fn clear(&self) {
+        *self.cache() = Cache::default();
+    }
+}
+
+impl<G: WgxDevice + WgxQueue> WgxQueue for WgxCache<G> { fn queue(&self) -> &wgpu::Queue { self.gx.queue() } }
+
+impl<G: WgxDevice> WgxDevice for WgxCache<G> {
+
+    fn device(&self) -> &wgpu::Device { self.gx.device() }
+    fn adapter(&self) -> Option<&wgpu::Adapter> { self.gx.adapter() }
+
+
+    fn shared_layout(&self, entries: &[BindGroupLayoutEntry]) -> Arc<wgpu::BindGroupLayout> {
+        get_or_insert(&mut self.cache().layouts, entries.to_vec(), || self.gx.shared_layout(entries))
+    }
+
+    fn shared_pipeline_layout(
+        &self, push_constant_ranges: &[PushConstantRange], bind_group_layouts: &[&wgpu::BindGroupLayout],
+    ) -> Arc<wgpu::PipelineLayout> {
+
+        let key = PipelineLayoutKey {
+            push_constant_ranges: push_constant_ranges.to_vec(),
+            bind_group_layouts: bind_group_layouts.iter().map(|layout| layout.global_id()).collect(),
+        };
+
+        get_or_insert(&mut self.cache().pipeline_layouts, key, ||
+            self.gx.shared_pipeline_layout(push_constant_ranges, bind_group_layouts)
+        )
+    }
+
+    fn shared_sampler(&self, descriptor: &SamplerDsc) -> Arc<wgpu::Sampler> {
+        get_or_insert(&mut self.cache().samplers, *descriptor, || self.gx.shared_sampler(descriptor))
+    }
+
+    fn shared_wgsl<'a>(&self, code: impl Into<Cow<'a, str>>) -> Arc<wgpu::ShaderModule> {
+        let code = code.into();
+        let mut cache = self.cache();
+
+        if let Some(module) = cache.shaders.get(code.as_ref()) {
+            return Arc::clone(module);
+        }
+
+        let module = self.gx.shared_wgsl(code.as_ref());
+        cache.shaders.insert(code.into(), Arc::clone(&module));
+        module
+    }
+
+    fn shared_compute_pipeline(
+        &self, layout: Option<&wgpu::PipelineLayout>, (module, entry_point): (&wgpu::ShaderModule, &str),
+    ) -> Arc<wgpu::ComputePipeline> {
+
+        let key = ComputePipelineKey {
+            layout: layout.map(wgpu::PipelineLayout::global_id),
+            module: module.global_id(),
+            entry_point: entry_point.into(),
+        };
+
+        get_or_insert(&mut self.cache().compute_pipelines, key, ||
+            self.gx.shared_compute_pipeline(layout, (module, entry_point))
+        )
+    }
+
+    fn shared_render_pipeline<const S: usize, T: ColorTarget>(
+        &self,
+        msaa: u32, depth_stencil: Option<DepthStencilState>,
+        layout: Option<&wgpu::PipelineLayout>,
+        buffers: &[wgpu::VertexBufferLayout],
+        (vs_module, vs_entry_point, primitive): (&wgpu::ShaderModule, &str, Primitive),
+        fragment: Option<FragmentDsc<S, T>>,
+    ) -> Arc<wgpu::RenderPipeline> {
+
+        let key = RenderPipelineKey {
+            msaa,
+            depth_stencil: depth_stencil.clone(),
+            layout: layout.map(wgpu::PipelineLayout::global_id),
+            buffers: vertex_buffer_keys(buffers),
+            vertex: (vs_module.global_id(), vs_entry_point.into(), primitive),
+            fragment: fragment.map(|(fs_module, fs_entry_point, targets)|
+                (fs_module.global_id(), fs_entry_point.into(), targets.iter().map(ColorTarget::color_target_state).collect())
+            ),
+        };
+
+        get_or_insert(&mut self.cache().render_pipelines, key, || self.gx.shared_render_pipeline(
+            msaa, depth_stencil, layout, buffers, (vs_module, vs_entry_point, primitive), fragment,
+        ))
+    }
+}
@@ -0,0 +1,755 @@
+
+// immediate mode 2d drawing: shapes are tessellated on the cpu into one vertex buffer
+// and drawn with as few draw calls as the blend mode and texture changes allow
+
+use std::{ops::Range, mem::size_of, f32::consts::{PI, TAU}, sync::Arc};
+use wgpu::FilterMode;
+use anyhow::{Result as Res};
+use crate::{*, math::*, render_target::TargetKey};
+
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Vertex {
+    pub position: [f32; 2], // clip space
+    pub uv: [f32; 2],
+    pub color: [f32; 4],
+}
+
+unsafe impl ReadBytes for Vertex {}
+
+impl Vertex {
+    pub const LAYOUT: wgpu::VertexBufferLayout<'static> = vertex_dsc!(Vertex, 0 => Float32x2, 1 => Float32x2, 2 => Float32x4);
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum BlendMode {
+    #[default] Alpha,
+    Additive,
+    Multiply,
+    Opaque,
+}
+
+impl BlendMode {
+
+    pub const ALL: [Self; 4] = [Self::Alpha, Self::Additive, Self::Multiply, Self::Opaque];
+
+    pub const fn blend(self) -> Option<Blend> {
+        match self {
+            Self::Alpha => Some(Blend::ALPHA_BLENDING),
+            Self::Additive => Some(Blend {
+                color: BlendComponent { src_factor: BlendFactor::SrcAlpha, dst_factor: BlendFactor::One, operation: BlendOperation::Add },
+                alpha: BlendComponent { src_factor: BlendFactor::Zero, dst_factor: BlendFactor::One, operation: BlendOperation::Add },
+            }),
+            Self::Multiply => Some(Blend {
+                color: BlendComponent { src_factor: BlendFactor::Dst, dst_factor: BlendFactor::Zero, operation: BlendOperation::Add },
+                alpha: BlendComponent { src_factor: BlendFactor::Zero, dst_factor: BlendFactor::One, operation: BlendOperation::Add },
+            }),
+            Self::Opaque => None,
+        }
+    }
+}
+
+
+// texture registered with Draw2d, WHITE is used for untextured shapes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct TextureId(pub usize);
+
+impl TextureId {
+    pub const WHITE: Self = Self(0);
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineJoin {
+    #[default] Miter,
+    Bevel,
+    Round,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineCap {
+    #[default] Butt,
+    Square,
+    Round,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stroke {
+    pub width: f32,
+    pub join: LineJoin,
+    pub cap: LineCap,
+    pub miter_limit: f32, // relative to the width, longer miters are beveled
+}
+
+impl Default for Stroke {
+    fn default() -> Self { Self::new(1.0) }
+}
+
+impl Stroke {
+    pub const fn new(width: f32) -> Self { Self { width, join: LineJoin::Miter, cap: LineCap::Butt, miter_limit: 2.0 } }
+    pub const fn join(self, join: LineJoin) -> Self { Self { join, ..self } }
+    pub const fn cap(self, cap: LineCap) -> Self { Self { cap, ..self } }
+    pub const fn miter_limit(self, miter_limit: f32) -> Self { Self { miter_limit, ..self } }
+}
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DrawCall {
+    pub blend: BlendMode,
+    pub texture: TextureId,
+    pub vertices: Range<u32>,
+}
+
+
+// max distance of curve segments to the real curve in pixels
+const TOLERANCE: f32 = 0.25;
+
+fn perp(v: Vec2) -> Vec2 { Vec2::new(-v.y, v.x) }
+
+
+// cpu side of the drawing, recorded every frame and flushed with Draw2d
+// coordinates are pixels with the origin top left, like Region
+#[derive(Debug, Clone)]
+pub struct Batch {
+    pub vertices: Vec<Vertex>,
+    pub calls: Vec<DrawCall>,
+    viewport: Mat4,
+    matrix: Mat4, // viewport * transform
+    transform: Mat4,
+    stack: Vec<Mat4>,
+    blend: BlendMode,
+}
+
+impl Batch {
+
+    pub fn new(size: [f32; 2]) -> Self {
+        let viewport = Mat4::flat_viewport_lh(size[0], size[1], 0.0);
+        Self {
+            vertices: Vec::new(), calls: Vec::new(),
+            viewport, matrix: viewport, transform: Mat4::IDENTITY, stack: Vec::new(),
+            blend: BlendMode::default(),
+        }
+    }
+
+    pub fn for_target(target: &impl RenderTarget) -> Self {
+        Self::new(target.region().size())
+    }
+
+    pub fn is_empty(&self) -> bool { self.vertices.is_empty() }
+
+    // removes all shapes and resets the state, keeping the allocations
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.calls.clear();
+        self.stack.clear();
+        self.set_transform(Mat4::IDENTITY);
+        self.blend = BlendMode::default();
+    }
+
+    pub fn resize(&mut self, size: [f32; 2]) {
+        self.viewport = Mat4::flat_viewport_lh(size[0], size[1], 0.0);
+        self.matrix = self.viewport * self.transform;
+    }
+
+
+    // state
+
+    pub fn blend(&self) -> BlendMode { self.blend }
+    pub fn set_blend(&mut self, blend: BlendMode) { self.blend = blend }
+
+
+    // transform stack
+
+    pub fn transform(&self) -> Mat4 { self.transform }
+
+    pub fn set_transform(&mut self, transform: Mat4) {
+        self.transform = transform;
+        self.matrix = self.viewport * transform;
+    }
+
+    // applied before the current transform
+    pub fn apply(&mut self, transform: Mat4) { self.set_transform(self.transform * transform) }
+
+    pub fn translate(&mut self, offset: impl Into<Vec2>) { self.apply(Mat4::from_translation(offset.into().extend(0.0))) }
+    pub fn rotate(&mut self, angle: f32) { self.apply(Mat4::from_rotation_z(angle)) }
+    pub fn scale(&mut self, scale: impl Into<Vec2>) { self.apply(Mat4::from_scale(scale.into().extend(1.0))) }
+
+    pub fn push(&mut self) { self.stack.push(self.transform) }
+
+    pub fn pop(&mut self) {
+        let transform = self.stack.pop().expect("Batch::pop without push");
+        self.set_transform(transform);
+    }
+
+    // transforms with push and pop around the handler
+    pub fn with_transform<T>(&mut self, transform: Mat4, handler: impl FnOnce(&mut Self) -> T) -> T {
+        self.push();
+        self.apply(transform);
+        let res = handler(self);
+        self.pop();
+        res
+    }
+
+
+    // raw vertices
+
+    fn call(&mut self, texture: TextureId, count: usize) {
+        if count == 0 { return }
+
+        let end = (self.vertices.len() + count) as u32;
+
+        match self.calls.last_mut() {
+            Some(call) if call.blend == self.blend && call.texture == texture && call.vertices.end == self.vertices.len() as u32 => {
+                call.vertices.end = end;
+            },
+            _ => self.calls.push(DrawCall { blend: self.blend, texture, vertices: self.vertices.len() as u32..end }),
+        }
+    }
+
+    fn vertex(&self, position: Vec2, uv: Vec2, color: Color) -> Vertex {
+        Vertex { position: self.matrix.transform_point3(position.extend(0.0)).truncate().into(), uv: uv.into(), color: color.f32() }
+    }
+
+    // triangle list in pixels with uvs, transformed like all shapes
+    pub fn triangles(&mut self, texture: TextureId, vertices: &[(Vec2, Vec2, Color)]) {
+        self.call(texture, vertices.len() - vertices.len() % 3);
+        for chunk in vertices.chunks_exact(3) {
+            for &(position, uv, color) in chunk {
+                let vertex = self.vertex(position, uv, color);
+                self.vertices.push(vertex);
+            }
+        }
+    }
+
+    fn solid(&mut self, triangles: &[[Vec2; 3]], color: Color) {
+        self.call(TextureId::WHITE, triangles.len() * 3);
+        for triangle in triangles {
+            for &position in triangle {
+                let vertex = self.vertex(position, Vec2::ZERO, color);
+                self.vertices.push(vertex);
+            }
+        }
+    }
+
+    fn fan(&mut self, center: Vec2, points: &[Vec2], closed: bool, color: Color) {
+        let mut triangles: Vec<_> = points.windows(2).map(|w| [center, w[0], w[1]]).collect();
+        if closed && points.len() > 2 {
+            triangles.push([center, points[points.len() - 1], points[0]]);
+        }
+        self.solid(&triangles, color);
+    }
+
+    // segments for a full circle with the radius, scaled by the current transform
+    fn segments(&self, radius: f32) -> usize {
+        let radius = radius * self.transform.x_axis.truncate().length().max(self.transform.y_axis.truncate().length());
+        if radius <= TOLERANCE { return 6 }
+        (PI / (1.0 - TOLERANCE / radius).acos()).ceil().clamp(6.0, 256.0) as usize
+    }
+
+    fn arc_points(&self, center: Vec2, radii: Vec2, angles: Range<f32>, points: &mut Vec<Vec2>) {
+        let n = ((self.segments(radii.max_element()) as f32 * (angles.end - angles.start).abs() / TAU).ceil() as usize).max(1);
+        points.extend((0..=n).map(|i| {
+            let angle = angles.start + (angles.end - angles.start) * i as f32 / n as f32;
+            center + radii * Vec2::new(angle.cos(), angle.sin())
+        }));
+    }
+
+
+    // filled shapes
+
+    pub fn rect(&mut self, position: impl Into<Vec2>, size: impl Into<Vec2>, color: Color) {
+        let (a, size) = (position.into(), size.into());
+        let (b, c, d) = (a + Vec2::new(size.x, 0.0), a + size, a + Vec2::new(0.0, size.y));
+        self.solid(&[[a, b, c], [a, c, d]], color);
+    }
+
+    pub fn rounded_rect(&mut self, position: impl Into<Vec2>, size: impl Into<Vec2>, radius: f32, color: Color) {
+        let points = self.rounded_rect_points(position.into(), size.into(), radius);
+        let center = points.iter().sum::<Vec2>() / points.len() as f32;
+        self.fan(center, &points, true, color);
+    }
+
+    pub fn circle(&mut self, center: impl Into<Vec2>, radius: f32, color: Color) {
+        self.ellipse(center, [radius, radius], color);
+    }
+
+    pub fn ellipse(&mut self, center: impl Into<Vec2>, radii: impl Into<Vec2>, color: Color) {
+        let center = center.into();
+        let points = self.ellipse_points(center, radii.into());
+        self.fan(center, &points, true, color);
+    }
+
+    // simple polygon of any winding, may be concave but not self intersecting
+    pub fn polygon(&mut self, points: &[Vec2], color: Color) {
+        let triangles: Vec<_> = triangulate(points).into_iter().map(|[a, b, c]| [points[a], points[b], points[c]]).collect();
+        self.solid(&triangles, color);
+    }
+
+    // textured quad, uv rect as position and size in texture coordinates
+    pub fn image(
+        &mut self, texture: TextureId, position: impl Into<Vec2>, size: impl Into<Vec2>,
+        uv: Option<(Vec2, Vec2)>, tint: Color,
+    ) {
+        let (a, size) = (position.into(), size.into());
+        let (uv_a, uv_size) = uv.unwrap_or((Vec2::ZERO, Vec2::ONE));
+
+        let corner = |f: Vec2| (a + size * f, uv_a + uv_size * f, tint);
+        let [ta, tb, tc, td] = [Vec2::ZERO, Vec2::X, Vec2::ONE, Vec2::Y].map(corner);
+
+        self.triangles(texture, &[ta, tb, tc, ta, tc, td]);
+    }
+
+
+    // strokes
+
+    pub fn line(&mut self, from: impl Into<Vec2>, to: impl Into<Vec2>, stroke: Stroke, color: Color) {
+        self.polyline(&[from.into(), to.into()], false, stroke, color);
+    }
+
+    pub fn stroke_rect(&mut self, position: impl Into<Vec2>, size: impl Into<Vec2>, stroke: Stroke, color: Color) {
+        let (a, size) = (position.into(), size.into());
+        self.polyline(&[a, a + Vec2::new(size.x, 0.0), a + size, a + Vec2::new(0.0, size.y)], true, stroke, color);
+    }
+
+    pub fn stroke_rounded_rect(&mut self, position: impl Into<Vec2>, size: impl Into<Vec2>, radius: f32, stroke: Stroke, color: Color) {
+        let points = self.rounded_rect_points(position.into(), size.into(), radius);
+        self.polyline(&points, true, stroke, color);
+    }
+
+    pub fn stroke_circle(&mut self, center: impl Into<Vec2>, radius: f32, stroke: Stroke, color: Color) {
+        let points = self.ellipse_points(center.into(), Vec2::splat(radius));
+        self.polyline(&points, true, stroke, color);
+    }
+
+    // connected lines, closed connects the last point with the first
+    pub fn polyline(&mut self, points: &[Vec2], closed: bool, stroke: Stroke, color: Color) {
+        let mut points = points.to_vec();
+        points.dedup_by(|a, b| a.distance_squared(*b) < 1e-12);
+        if closed && points.len() > 2 && points[0].distance_squared(points[points.len() - 1]) < 1e-12 { points.pop(); }
+
+        let closed = closed && points.len() > 2;
+        let hw = stroke.width / 2.0;
+
+        if points.len() < 2 || hw <= 0.0 { return }
+
+        let n = points.len();
+        let segments = if closed { n } else { n - 1 };
+        let dir = |i: usize| (points[(i + 1) % n] - points[i]).normalize();
+
+        // left and right points at the start and end of each segment
+        let mut starts: Vec<[Vec2; 2]> = (0..segments).map(|i| { let o = perp(dir(i)) * hw; [points[i] + o, points[i] - o] }).collect();
+        let mut ends: Vec<[Vec2; 2]> = (0..segments).map(|i| { let o = perp(dir(i)) * hw; [points[(i + 1) % n] + o, points[(i + 1) % n] - o] }).collect();
+
+        let mut triangles = Vec::new();
+
+        // joins between segment i - 1 and i
+        for i in if closed { 0..n } else { 1..n - 1 } {
+            let prev = (i + segments - 1) % segments;
+            let (d0, d1) = (dir(prev), dir(i));
+            let cross = d0.perp_dot(d1);
+
+            if cross.abs() < 1e-6 { continue }
+
+            // the left side (index 0) is inside for left turns
+            let (inner, outer) = if cross > 0.0 { (0, 1) } else { (1, 0) };
+            let side = if cross > 0.0 { 1.0 } else { -1.0 };
+
+            let miter = (perp(d0) + perp(d1)).normalize();
+            let miter_length = hw / miter.dot(perp(d0)).max(1e-6);
+
+            // inner point shared by both segments, limited by the segment lengths
+            let max_inner = (points[i] - points[prev]).length().min((points[(i + 1) % n] - points[i]).length());
+            let inner_point = points[i] + miter * side * miter_length.min(max_inner.hypot(hw));
+            ends[prev][inner] = inner_point;
+            starts[i][inner] = inner_point;
+
+            let (a, b) = (ends[prev][outer], starts[i][outer]);
+
+            match stroke.join {
+                LineJoin::Miter if miter_length <= stroke.miter_limit * hw => {
+                    let tip = points[i] - miter * side * miter_length;
+                    triangles.extend([[inner_point, a, tip], [inner_point, tip, b]]);
+                },
+                LineJoin::Round => {
+                    let (a0, a1) = ((a - points[i]).to_angle(), (b - points[i]).to_angle());
+                    let delta = (a1 - a0 + PI).rem_euclid(TAU) - PI;
+                    let mut arc = Vec::new();
+                    self.arc_points(points[i], Vec2::splat(hw), a0..a0 + delta, &mut arc);
+                    triangles.extend(arc.windows(2).map(|w| [inner_point, w[0], w[1]]));
+                },
+                _ => triangles.push([inner_point, a, b]),
+            }
+        }
+
+        // caps
+        if !closed {
+            let (d0, d1) = (dir(0), dir(segments - 1));
+            match stroke.cap {
+                LineCap::Butt => {},
+                LineCap::Square => {
+                    for side in &mut starts[0] { *side -= d0 * hw }
+                    for side in &mut ends[segments - 1] { *side += d1 * hw }
+                },
+                LineCap::Round => {
+                    for (center, d) in [(points[0], -d0), (points[n - 1], d1)] {
+                        let angle = d.to_angle();
+                        let mut arc = Vec::new();
+                        self.arc_points(center, Vec2::splat(hw), angle - PI / 2.0..angle + PI / 2.0, &mut arc);
+                        triangles.extend(arc.windows(2).map(|w| [center, w[0], w[1]]));
+                    }
+                },
+            }
+        }
+
+        for (&[sl, sr], &[el, er]) in starts.iter().zip(&ends) {
+            triangles.extend([[sl, sr, er], [sl, er, el]]);
+        }
+
+        self.solid(&triangles, color);
+    }
+
+
+    // outlines
+
+    fn ellipse_points(&self, center: Vec2, radii: Vec2) -> Vec<Vec2> {
+        let mut points = Vec::new();
+        self.arc_points(center, radii, 0.0..TAU, &mut points);
+        points.pop(); // same as the first
+        points
+    }
+
+    fn rounded_rect_points(&self, a: Vec2, size: Vec2, radius: f32) -> Vec<Vec2> {
+        let radius = radius.min(size.x.abs() / 2.0).min(size.y.abs() / 2.0).max(0.0);
+        let mut points = Vec::new();
+
+        for (corner, angle) in [
+            (a + Vec2::new(size.x - radius, radius), -PI / 2.0),
+            (a + size - radius, 0.0),
+            (a + Vec2::new(radius, size.y - radius), PI / 2.0),
+            (a + radius, PI),
+        ] {
+            self.arc_points(corner, Vec2::splat(radius), angle..angle + PI / 2.0, &mut points);
+        }
+
+        points.dedup_by(|a, b| a.distance_squared(*b) < 1e-12);
+        points
+    }
+}
+
+
+// ear clipping, falls back to a fan for polygons that can't be clipped
+pub fn triangulate(points: &[Vec2]) -> Vec<[usize; 3]> {
+    let n = points.len();
+    if n < 3 { return Vec::new() }
+
+    let area: f32 = (0..n).map(|i| points[i].perp_dot(points[(i + 1) % n])).sum();
+    let orientation = area.signum();
+
+    let mut indices: Vec<usize> = (0..n).collect();
+    let mut triangles = Vec::with_capacity(n - 2);
+
+    // points on the edges count as inside
+    let inside = |p: Vec2, [a, b, c]: [Vec2; 3]| {
+        let (d0, d1, d2) = ((b - a).perp_dot(p - a), (c - b).perp_dot(p - b), (a - c).perp_dot(p - c));
+        d0 * orientation >= 0.0 && d1 * orientation >= 0.0 && d2 * orientation >= 0.0
+    };
+
+    let mut i = 0;
+    let mut misses = 0;
+
+    while indices.len() > 3 {
+        let len = indices.len();
+        let [ia, ib, ic] = [indices[(i + len - 1) % len], indices[i % len], indices[(i + 1) % len]];
+        let triangle = [points[ia], points[ib], points[ic]];
+
+        let convex = (triangle[1] - triangle[0]).perp_dot(triangle[2] - triangle[1]) * orientation > 0.0;
+
+        if convex && !indices.iter().any(|&j| j != ia && j != ib && j != ic && inside(points[j], triangle)) {
+            triangles.push([ia, ib, ic]);
+            indices.remove(i % len);
+            misses = 0;
+        }
+        else {
+            i += 1;
+            misses += 1;
+            if misses > len {
+                // not simple, fan the rest
+                triangles.extend((1..len - 1).map(|k| [indices[0], indices[k], indices[k + 1]]));
+                return triangles;
+            }
+        }
+    }
+
+    triangles.push([indices[0], indices[1], indices[2]]);
+    triangles
+}
+
+
+// gpu side, one pipeline per blend mode matching the render target
+// vertices are appended to the buffer until reset is called after the queue was submitted,
+// reset is required once per frame, otherwise the buffer keeps growing
+#[derive(Debug)]
+pub struct Draw2d {
+    pub layout: BindGroupLayoutDsc,
+    pub pipelines: [Arc<wgpu::RenderPipeline>; 4], // indexed by BlendMode
+    shader: Arc<wgpu::ShaderModule>,
+    pipeline_layout: Arc<wgpu::PipelineLayout>,
+    target: TargetKey,
+    samplers: [Arc<wgpu::Sampler>; 2],
+    textures: Vec<wgpu::BindGroup>,
+    vertices: wgpu::Buffer,
+    capacity: usize,
+    offset: usize, // vertices written since the last reset
+    calls: Vec<DrawCall>,
+    white: TextureLot,
+}
+
+fn draw2d_pipelines(
+    gx: &impl WgxDevice, shader: &wgpu::ShaderModule, layout: &wgpu::PipelineLayout, target: TargetKey,
+) -> [Arc<wgpu::RenderPipeline>; 4] {
+
+    // drawn over the scene without depth testing or writing
+    let depth_stencil = target.depth_testing.map(|format| wgpu::DepthStencilState {
+        depth_write_enabled: false, depth_compare: wgpu::CompareFunction::Always, ..depth_state(format)
+    });
+
+    BlendMode::ALL.map(|mode| gx.shared_render_pipeline(
+        target.msaa, depth_stencil.clone(), Some(layout), &[Vertex::LAYOUT],
+        (shader, "vs_main", Primitive::default()),
+        Some((shader, "fs_main", &[(target.format, mode.blend())])),
+    ))
+}
+
+impl Draw2d {
+
+    pub fn new(gx: &impl WgxDeviceQueue, target: &impl RenderTarget) -> Res<Self> {
+
+        let shader = gx.shared_wgsl(include_str!("shaders/draw2d.wgsl"));
+
+        let layout = gx.layout_dsc(&[
+            binding!(0, Stage::FRAGMENT, Texture, D2),
+            binding!(1, Stage::FRAGMENT, Sampler),
+        ]);
+
+        let pipeline_layout = gx.shared_pipeline_layout(&[], &[&layout.layout]);
+        let target = TargetKey::of(target);
+        let pipelines = draw2d_pipelines(gx, &shader, &pipeline_layout, target);
+
+        let white = TextureLot::new_2d_with_data(
+            gx, [1, 1, 1], 1, DEFAULT_SRGB, None, TexUse::TEXTURE_BINDING, [255u8, 255, 255, 255],
+        );
+
+        let capacity = 1024;
+
+        let mut draw = Self {
+            layout, pipelines, shader, pipeline_layout, target,
+            samplers: [gx.shared_sampler(&SamplerDsc::NEAREST), gx.shared_sampler(&SamplerDsc::LINEAR)],
+            textures: Vec::new(),
+            vertices: gx.buffer(BufUse::VERTEX | BufUse::COPY_DST, (capacity * size_of::<Vertex>()) as u64, false),
+            capacity, offset: 0, calls: Vec::new(),
+            white,
+        };
+
+        let white = draw.bind_texture(gx, &draw.white.view, FilterMode::Nearest)?;
+        draw.textures.push(white);
+
+        Ok(draw)
+    }
+
+    fn bind_texture(&self, gx: &impl WgxDevice, view: &wgpu::TextureView, filter: FilterMode) -> Res<wgpu::BindGroup> {
+        let (sampler, sampler_dsc) = match filter {
+            FilterMode::Nearest => (&self.samplers[0], &SamplerDsc::NEAREST),
+            FilterMode::Linear => (&self.samplers[1], &SamplerDsc::LINEAR),
+        };
+        self.layout.bind(gx).texture(0, view).sampler_dsc(1, sampler, sampler_dsc).finish()
+    }
+
+    pub fn add_texture(&mut self, gx: &impl WgxDevice, view: &wgpu::TextureView, filter: FilterMode) -> Res<TextureId> {
+        let binding = self.bind_texture(gx, view, filter)?;
+        self.textures.push(binding);
+        Ok(TextureId(self.textures.len() - 1))
+    }
+
+    // e.g. after the texture was recreated with a new size
+    pub fn replace_texture(&mut self, gx: &impl WgxDevice, id: TextureId, view: &wgpu::TextureView, filter: FilterMode) -> Res<()> {
+        self.textures[id.0] = self.bind_texture(gx, view, filter)?;
+        Ok(())
+    }
+
+    pub fn pipeline(&self, blend: BlendMode) -> &wgpu::RenderPipeline { &self.pipelines[blend as usize] }
+
+    // follow the render target, e.g. after SurfaceTarget::set_msaa
+    pub fn update(&mut self, gx: &impl WgxDevice, target: &impl RenderTarget) {
+        let target = TargetKey::of(target);
+
+        if target != self.target {
+            self.target = target;
+            self.pipelines = draw2d_pipelines(gx, &self.shader, &self.pipeline_layout, target);
+        }
+    }
+
+
+    // uploads the batch behind the ones prepared since the last reset,
+    // the buffer is written when the queue is submitted, so each prepared batch keeps its own range
+    pub fn prepare(&mut self, gx: &impl WgxDeviceQueue, batch: &Batch) {
+        let len = batch.vertices.len();
+
+        // grows to the vertices of the whole frame, draws recorded before keep using the old buffer
+        if self.offset + len > self.capacity {
+            self.capacity = (self.offset + len).next_power_of_two();
+            self.vertices = gx.buffer(BufUse::VERTEX | BufUse::COPY_DST, (self.capacity * size_of::<Vertex>()) as u64, false);
+            self.offset = 0;
+        }
+        if len > 0 {
+            gx.write_buffer(&self.vertices, (self.offset * size_of::<Vertex>()) as u64, batch.vertices.as_slice());
+        }
+
+        let base = self.offset as u32;
+
+        self.calls.clear();
+        self.calls.extend(batch.calls.iter().map(|call| DrawCall {
+            vertices: call.vertices.start + base..call.vertices.end + base, ..call.clone()
+        }));
+
+        self.offset += len;
+    }
+
+    // call after the queue was submitted, the next batch is written to the start of the buffer again
+    pub fn reset(&mut self) { self.offset = 0 }
+
+    pub fn draw(&self, rpass: &mut wgpu::RenderPass) {
+        if self.calls.is_empty() { return }
+
+        rpass.set_vertex_buffer(0, self.vertices.slice(..));
+
+        let mut state = None;
+
+        for call in &self.calls {
+            if state.map(|(blend, _)| blend) != Some(call.blend) {
+                rpass.set_pipeline(self.pipeline(call.blend));
+            }
+            if state.map(|(_, texture)| texture) != Some(call.texture) {
+                rpass.set_bind_group(0, &self.textures[call.texture.0], &[]);
+            }
+            state = Some((call.blend, call.texture));
+            rpass.draw(call.vertices.clone(), 0..1);
+        }
+    }
+
+    // prepares, draws and clears the batch
+    pub fn flush(&mut self, gx: &impl WgxDeviceQueue, rpass: &mut wgpu::RenderPass, batch: &mut Batch) {
+        self.prepare(gx, batch);
+        self.draw(rpass);
+        batch.clear();
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signed_area([a, b, c]: [Vec2; 3]) -> f32 { (b - a).perp_dot(c - a) / 2.0 }
+
+    fn polygon_area(points: &[Vec2]) -> f32 {
+        (0..points.len()).map(|i| points[i].perp_dot(points[(i + 1) % points.len()])).sum::<f32>() / 2.0
+    }
+
+    // triangles cover the polygon once with its winding
+    fn assert_triangulated(points: &[Vec2]) {
+        let triangles = triangulate(points);
+        assert_eq!(triangles.len(), points.len() - 2);
+
+        let areas: Vec<f32> = triangles.iter().map(|t| signed_area(t.map(|i| points[i]))).collect();
+        assert!(areas.iter().all(|area| area * polygon_area(points).signum() >= 0.0), "{areas:?}");
+        assert!((areas.iter().sum::<f32>() - polygon_area(points)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn triangulates_concave_polygons() {
+        let arrow = [[0.0, 0.0], [4.0, 2.0], [0.0, 4.0], [1.0, 2.0]].map(Vec2::from);
+        assert_triangulated(&arrow);
+
+        let l_shape = [[0.0, 0.0], [3.0, 0.0], [3.0, 1.0], [1.0, 1.0], [1.0, 3.0], [0.0, 3.0]].map(Vec2::from);
+        assert_triangulated(&l_shape);
+
+        // clockwise
+        let mut reversed = l_shape;
+        reversed.reverse();
+        assert_triangulated(&reversed);
+    }
+
+    #[test]
+    fn triangulates_degenerate_polygons() {
+        // collinear point on an edge
+        assert_triangulated(&[[0.0, 0.0], [1.0, 0.0], [2.0, 0.0], [2.0, 2.0], [0.0, 2.0]].map(Vec2::from));
+
+        // all on a line, without area
+        let line = [[0.0, 0.0], [1.0, 0.0], [2.0, 0.0], [3.0, 0.0]].map(Vec2::from);
+        let triangles = triangulate(&line);
+        assert!(triangles.iter().all(|t| signed_area(t.map(|i| line[i])) == 0.0));
+
+        assert!(triangulate(&[Vec2::ZERO, Vec2::X]).is_empty());
+    }
+
+    // batch in pixels instead of clip space
+    fn pixel_batch() -> Batch {
+        let mut batch = Batch::new([1.0, 1.0]);
+        batch.viewport = Mat4::IDENTITY;
+        batch.set_transform(Mat4::IDENTITY);
+        batch
+    }
+
+    fn stroke_area(points: &[[f32; 2]], closed: bool, stroke: Stroke) -> f32 {
+        let mut batch = pixel_batch();
+        batch.polyline(&points.iter().copied().map(Vec2::from).collect::<Vec<_>>(), closed, stroke, Color::WHITE);
+        batch.vertices.chunks_exact(3).map(|t| signed_area([0, 1, 2].map(|i| Vec2::from(t[i].position))).abs()).sum()
+    }
+
+    fn assert_near(a: f32, b: f32) { assert!((a - b).abs() < 1e-3, "{a} != {b}") }
+
+    #[test]
+    fn strokes_lines_with_caps() {
+        let line = [[0.0, 0.0], [10.0, 0.0]];
+        assert_near(stroke_area(&line, false, Stroke::new(2.0)), 20.0);
+        assert_near(stroke_area(&line, false, Stroke::new(2.0).cap(LineCap::Square)), 24.0);
+
+        // two half circles within the curve tolerance
+        let round = stroke_area(&line, false, Stroke::new(2.0).cap(LineCap::Round));
+        assert!(round > 20.0 + PI - 4.0 * TOLERANCE && round <= 20.0 + PI, "{round}");
+    }
+
+    #[test]
+    fn strokes_joins() {
+        // right angle, the union of both segments with a full corner covers 40 pixels
+        let corner = [[0.0, 0.0], [10.0, 0.0], [10.0, 10.0]];
+        assert_near(stroke_area(&corner, false, Stroke::new(2.0)), 40.0);
+        assert_near(stroke_area(&corner, false, Stroke::new(2.0).join(LineJoin::Bevel)), 39.5);
+
+        let round = stroke_area(&corner, false, Stroke::new(2.0).join(LineJoin::Round));
+        assert!(round > 39.5 && round <= 40.0 - (1.0 - PI / 4.0) + 1e-3, "{round}");
+
+        // sharp angles fall back to bevels beyond the miter limit
+        let sharp = [[0.0, 0.0], [10.0, 0.0], [0.0, 1.0]];
+        let limited = stroke_area(&sharp, false, Stroke::new(1.0).miter_limit(2.0));
+        assert_near(limited, stroke_area(&sharp, false, Stroke::new(1.0).join(LineJoin::Bevel)));
+        assert!(stroke_area(&sharp, false, Stroke::new(1.0).miter_limit(100.0)) > limited);
+    }
+
+    #[test]
+    fn strokes_closed_and_degenerate_lines() {
+        // outer 12 x 12 minus inner 8 x 8
+        let square = [[0.0, 0.0], [10.0, 0.0], [10.0, 10.0], [0.0, 10.0]];
+        assert_near(stroke_area(&square, true, Stroke::new(2.0)), 80.0);
+
+        // a repeated closing point doesn't add a segment
+        assert_near(stroke_area(&[[0.0, 0.0], [10.0, 0.0], [10.0, 10.0], [0.0, 10.0], [0.0, 0.0]], true, Stroke::new(2.0)), 80.0);
+
+        // collinear points don't join, duplicates and single points draw nothing
+        assert_near(stroke_area(&[[0.0, 0.0], [5.0, 0.0], [5.0, 0.0], [10.0, 0.0]], false, Stroke::new(2.0)), 20.0);
+        assert_eq!(stroke_area(&[[1.0, 1.0], [1.0, 1.0]], false, Stroke::new(2.0)), 0.0);
+        assert_eq!(stroke_area(&[[0.0, 0.0], [10.0, 0.0]], false, Stroke::new(0.0)), 0.0);
+    }
+}
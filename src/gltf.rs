@@ -0,0 +1,691 @@
+
+// glTF 2.0 and GLB import into meshes, textures and pbr materials, with nodes, skins and animations
+// glTF is right handed, z is negated into the left handed system of wgx,
+// so the imported meshes have counter clockwise front faces like the generated ones
+
+use std::{collections::HashMap, path::Path, ops::{Add, Mul}};
+use wgpu::{AddressMode, FilterMode, TextureFormat};
+use anyhow::{Result as Res, Context, bail, ensure};
+use ::gltf as gl;
+use crate::{*, math::*, mesh::*, atlas::box_mips};
+
+
+// right to left handed
+const FLIP_Z: Mat4 = Mat4::from_diagonal(Vec4::new(1.0, 1.0, -1.0, 1.0));
+
+fn flip(v: [f32; 3]) -> Vec3 { Vec3::new(v[0], v[1], -v[2]) }
+fn flip_rotation(q: [f32; 4]) -> Quat { Quat::from_xyzw(-q[0], -q[1], q[2], q[3]) }
+fn flip_matrix(m: [[f32; 4]; 4]) -> Mat4 { FLIP_Z * Mat4::from_cols_array_2d(&m) * FLIP_Z }
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TextureRef {
+    pub texture: usize, // into GltfScene::textures
+    pub sampler: usize, // into GltfScene::samplers
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlphaMode {
+    Opaque,
+    Mask(f32), // cutoff
+    Blend,
+}
+
+// metallic roughness parameters, texture factors multiply the texture values
+#[derive(Debug, Clone, PartialEq)]
+pub struct Material {
+    pub name: Option<String>,
+    pub base_color: Vec4, // linear
+    pub metallic: f32,
+    pub roughness: f32,
+    pub emissive: Vec3,
+    pub normal_scale: f32,
+    pub occlusion_strength: f32,
+    pub alpha_mode: AlphaMode,
+    pub double_sided: bool,
+    pub base_color_texture: Option<TextureRef>, // srgb
+    pub metallic_roughness_texture: Option<TextureRef>, // linear, roughness in g, metallic in b
+    pub normal_texture: Option<TextureRef>, // linear
+    pub occlusion_texture: Option<TextureRef>, // linear, in r
+    pub emissive_texture: Option<TextureRef>, // srgb
+}
+
+// the glTF default material
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            name: None, base_color: Vec4::ONE, metallic: 1.0, roughness: 1.0, emissive: Vec3::ZERO,
+            normal_scale: 1.0, occlusion_strength: 1.0, alpha_mode: AlphaMode::Opaque, double_sided: false,
+            base_color_texture: None, metallic_roughness_texture: None, normal_texture: None,
+            occlusion_texture: None, emissive_texture: None,
+        }
+    }
+}
+
+
+// all primitives of a glTF mesh as submeshes of one mesh
+#[derive(Debug)]
+pub struct GltfMesh {
+    pub name: Option<String>,
+    pub mesh: Mesh,
+    pub materials: Vec<Option<usize>>, // per submesh, None for the default material
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub name: Option<String>,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+    pub mesh: Option<usize>,
+    pub skin: Option<usize>,
+}
+
+impl Node {
+    pub fn local_transform(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scene {
+    pub name: Option<String>,
+    pub roots: Vec<usize>,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Skin {
+    pub name: Option<String>,
+    pub joints: Vec<usize>, // nodes
+    pub inverse_bind_matrices: Vec<Mat4>,
+    pub skeleton: Option<usize>,
+}
+
+impl Skin {
+    // joint matrices in model space of the skinned mesh node, from the world transforms of all nodes
+    pub fn joint_matrices(&self, world_transforms: &[Mat4], mesh_node: usize) -> Vec<Mat4> {
+        let inverse_mesh = world_transforms[mesh_node].inverse();
+        self.joints.iter().enumerate().map(|(i, &joint)| {
+            inverse_mesh * world_transforms[joint] * self.inverse_bind_matrices.get(i).copied().unwrap_or(Mat4::IDENTITY)
+        }).collect()
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Interpolation { Linear, Step, CubicSpline }
+
+// keyframe values, cubic splines store in tangent, value and out tangent per keyframe
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChannelValues {
+    Translation(Vec<Vec3>),
+    Rotation(Vec<Quat>),
+    Scale(Vec<Vec3>),
+    Weights(Vec<f32>), // morph target weights, all targets per keyframe
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Channel {
+    pub node: usize,
+    pub interpolation: Interpolation,
+    pub times: Vec<f32>,
+    pub values: ChannelValues,
+}
+
+fn sample<T: Copy + Add<Output = T> + Mul<f32, Output = T>>(
+    times: &[f32], values: &[T], interpolation: Interpolation, time: f32, lerp: impl Fn(T, T, f32) -> T,
+) -> Option<T> {
+    let cubic = interpolation == Interpolation::CubicSpline;
+    let value = |i: usize| values.get(if cubic { i * 3 + 1 } else { i }).copied();
+
+    let next = times.partition_point(|&t| t <= time);
+    if next == 0 { return value(0) }
+    if next == times.len() { return value(next - 1) }
+
+    let prev = next - 1;
+    let dt = times[next] - times[prev];
+    let t = if dt > 0.0 { (time - times[prev]) / dt } else { 0.0 };
+
+    match interpolation {
+        Interpolation::Step => value(prev),
+        Interpolation::Linear => Some(lerp(value(prev)?, value(next)?, t)),
+        Interpolation::CubicSpline => {
+            let (t2, t3) = (t * t, t * t * t);
+            let out_tangent = *values.get(prev * 3 + 2)?;
+            let in_tangent = *values.get(next * 3)?;
+            Some(
+                value(prev)? * (2.0 * t3 - 3.0 * t2 + 1.0) + out_tangent * ((t3 - 2.0 * t2 + t) * dt) +
+                value(next)? * (-2.0 * t3 + 3.0 * t2) + in_tangent * ((t3 - t2) * dt)
+            )
+        },
+    }
+}
+
+impl Channel {
+    // sets the animated property of the node, morph target weights are skipped
+    pub fn apply(&self, time: f32, nodes: &mut [Node]) {
+        let Some(node) = nodes.get_mut(self.node) else { return };
+        match &self.values {
+            ChannelValues::Translation(values) => {
+                if let Some(v) = sample(&self.times, values, self.interpolation, time, Vec3::lerp) { node.translation = v }
+            },
+            ChannelValues::Rotation(values) => {
+                if let Some(q) = sample(&self.times, values, self.interpolation, time, Quat::slerp) { node.rotation = q.normalize() }
+            },
+            ChannelValues::Scale(values) => {
+                if let Some(v) = sample(&self.times, values, self.interpolation, time, Vec3::lerp) { node.scale = v }
+            },
+            ChannelValues::Weights(_) => {},
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Animation {
+    pub name: Option<String>,
+    pub channels: Vec<Channel>,
+    pub duration: f32,
+}
+
+impl Animation {
+    pub fn apply(&self, time: f32, nodes: &mut [Node]) {
+        for channel in &self.channels { channel.apply(time, nodes) }
+    }
+}
+
+
+#[derive(Debug)]
+pub struct GltfScene {
+    pub meshes: Vec<GltfMesh>,
+    pub textures: Vec<TextureLot>, // with mips
+    pub samplers: Vec<SamplerDsc>, // the last one is the default sampler
+    pub materials: Vec<Material>,
+    pub nodes: Vec<Node>,
+    pub scenes: Vec<Scene>,
+    pub default_scene: Option<usize>,
+    pub skins: Vec<Skin>,
+    pub animations: Vec<Animation>,
+}
+
+
+// uploads each image once per color space when it's first used
+struct Textures<'a> {
+    buffers: &'a [gl::buffer::Data],
+    base: Option<&'a Path>,
+    lots: Vec<TextureLot>,
+    uploaded: HashMap<(usize, bool), Option<usize>>,
+    default_sampler: usize,
+}
+
+impl Textures<'_> {
+
+    // only textures of uv set 0 as the others aren't imported
+    fn get(&mut self, gx: &impl WgxDeviceQueue, texture: gl::Texture, tex_coord: u32, srgb: bool) -> Option<TextureRef> {
+        if tex_coord != 0 {
+            log::warn!("skipping glTF texture {} of uv set {tex_coord}, only set 0 is imported", texture.index());
+            return None;
+        }
+
+        let image = texture.source();
+
+        let lot = *self.uploaded.entry((image.index(), srgb)).or_insert_with(|| {
+            match gl::image::Data::from_source(image.source(), self.base, self.buffers) {
+                Ok(data) => {
+                    self.lots.push(upload_image(gx, &data, srgb));
+                    Some(self.lots.len() - 1)
+                },
+                Err(err) => {
+                    log::warn!("skipping glTF image {}: {err}", image.index());
+                    None
+                },
+            }
+        });
+
+        Some(TextureRef {
+            texture: lot?,
+            sampler: texture.sampler().index().unwrap_or(self.default_sampler),
+        })
+    }
+}
+
+
+// any pixel format to rgba8, with a full mip chain
+fn upload_image(gx: &impl WgxDeviceQueue, image: &gl::image::Data, srgb: bool) -> TextureLot {
+    use gl::image::Format::*;
+
+    let (channels, depth) = match image.format {
+        R8 => (1, 1), R8G8 => (2, 1), R8G8B8 => (3, 1), R8G8B8A8 => (4, 1),
+        R16 => (1, 2), R16G16 => (2, 2), R16G16B16 => (3, 2), R16G16B16A16 => (4, 2),
+        R32G32B32FLOAT => (3, 4), R32G32B32A32FLOAT => (4, 4),
+    };
+
+    let pixels = &image.pixels;
+    let value = |i: usize| match depth {
+        1 => pixels[i],
+        2 => pixels[i * 2 + 1], // high byte, little endian
+        _ => {
+            let float = f32::from_le_bytes([0, 1, 2, 3].map(|b| pixels[i * 4 + b]));
+            (float.clamp(0.0, 1.0) * 255.0 + 0.5) as u8
+        },
+    };
+
+    let size = [image.width, image.height];
+    let mut data = Vec::with_capacity((size[0] * size[1] * 4) as usize * 4 / 3 + 64);
+
+    for p in 0..(size[0] * size[1]) as usize {
+        let i = p * channels;
+        data.extend(match channels {
+            1 => { let v = value(i); [v, v, v, 255] },
+            2 => { let v = value(i); [v, v, v, value(i + 1)] },
+            3 => [value(i), value(i + 1), value(i + 2), 255],
+            _ => [value(i), value(i + 1), value(i + 2), value(i + 3)],
+        });
+    }
+
+    let mip_levels = 32 - size[0].max(size[1]).max(1).leading_zeros();
+    box_mips(&data.clone(), size, mip_levels, &mut data);
+
+    let format = if srgb { TextureFormat::Rgba8UnormSrgb } else { TextureFormat::Rgba8Unorm };
+    let mut descriptor = TexDsc::new_2d([size[0], size[1], 1], 1, format, None, TexUse::TEXTURE_BINDING | TexUse::COPY_DST);
+    descriptor.mip_level_count = mip_levels;
+
+    TextureLot::new_with_data(gx, descriptor, data.as_slice())
+}
+
+
+fn sampler_dsc(sampler: &gl::texture::Sampler) -> SamplerDsc {
+    use gl::texture::{MagFilter, MinFilter, WrappingMode};
+    use FilterMode::{Nearest, Linear};
+
+    let address = |mode| match mode {
+        WrappingMode::ClampToEdge => AddressMode::ClampToEdge,
+        WrappingMode::MirroredRepeat => AddressMode::MirrorRepeat,
+        WrappingMode::Repeat => AddressMode::Repeat,
+    };
+
+    let mag = match sampler.mag_filter() { Some(MagFilter::Nearest) => Nearest, _ => Linear };
+
+    // min filters without mipmapping only sample the base level
+    let (min, mip, mipmapped) = match sampler.min_filter() {
+        Some(MinFilter::Nearest) => (Nearest, Nearest, false),
+        Some(MinFilter::Linear) => (Linear, Nearest, false),
+        Some(MinFilter::NearestMipmapNearest) => (Nearest, Nearest, true),
+        Some(MinFilter::LinearMipmapNearest) => (Linear, Nearest, true),
+        Some(MinFilter::NearestMipmapLinear) => (Nearest, Linear, true),
+        Some(MinFilter::LinearMipmapLinear) | None => (Linear, Linear, true),
+    };
+
+    let dsc = SamplerDsc::TRILINEAR
+        .address_uvw(address(sampler.wrap_s()), address(sampler.wrap_t()), AddressMode::ClampToEdge)
+        .filter(mag, min, mip);
+
+    if mipmapped { dsc } else { SamplerDsc { lod_max_clamp: 0.0, ..dsc } }
+}
+
+
+fn name(name: Option<&str>) -> Option<String> { name.map(String::from) }
+
+// all checks of the gltf crate except for unsupported required extensions, which are only warned about
+fn validate(document: &gl::Document) -> Res<()> {
+    use gl::json::validation::{Validate, Error};
+
+    let json = document.as_json();
+    let mut errors = Vec::new();
+
+    json.validate(json, gl::json::Path::new, &mut |path, error| {
+        if !matches!(error, Error::Unsupported) { errors.push(format!("{}: {error}", path())) }
+    });
+
+    ensure!(errors.is_empty(), "invalid glTF: {}", errors.join(", "));
+    Ok(())
+}
+
+// sets the parents, nodes have to form trees
+fn link_parents(nodes: &mut [Node]) -> Res<()> {
+    for i in 0..nodes.len() {
+        for child in nodes[i].children.clone() {
+            if nodes[child].parent.is_some() { bail!("glTF node {child} has several parents") }
+            nodes[child].parent = Some(i);
+        }
+    }
+
+    // every chain of parents has to end at a root
+    for i in 0..nodes.len() {
+        let mut parent = nodes[i].parent;
+        for _ in 0..nodes.len() {
+            match parent { Some(p) => parent = nodes[p].parent, None => break }
+        }
+        if parent.is_some() { bail!("glTF node {i} is part of a cycle") }
+    }
+
+    Ok(())
+}
+
+
+impl GltfScene {
+
+    // .gltf or .glb, external buffers and images are resolved relative to the file
+    pub fn load(gx: &impl WgxDeviceQueue, path: impl AsRef<Path>, schema: &VertexSchema) -> Res<Self> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+        Self::from_slice(gx, &bytes, path.parent(), schema)
+    }
+
+    // unsupported extensions and undecodable images are reported as warnings
+    pub fn from_slice(gx: &impl WgxDeviceQueue, bytes: &[u8], base: Option<&Path>, schema: &VertexSchema) -> Res<Self> {
+
+        let gl::Gltf { document, blob } = gl::Gltf::from_slice_without_validation(bytes)?;
+        validate(&document)?;
+
+        for extension in document.extensions_used() {
+            if document.extensions_required().any(|required| required == extension) {
+                log::warn!("required glTF extension {extension} is not supported, the import may be incomplete");
+            } else {
+                log::warn!("glTF extension {extension} is not supported and ignored");
+            }
+        }
+
+        let buffers = gl::import_buffers(&document, base, blob)?;
+        let get_buffer = |buffer: gl::Buffer| buffers.get(buffer.index()).map(|data| &data.0[..]);
+
+        // samplers and materials
+
+        let mut samplers: Vec<SamplerDsc> = document.samplers().map(|sampler| sampler_dsc(&sampler)).collect();
+        samplers.push(SamplerDsc::TRILINEAR.repeat());
+
+        let mut textures = Textures {
+            buffers: &buffers, base,
+            lots: Vec::new(), uploaded: HashMap::new(), default_sampler: samplers.len() - 1,
+        };
+
+        let materials = document.materials().map(|material| {
+            let pbr = material.pbr_metallic_roughness();
+            let info = |textures: &mut Textures, info: Option<gl::texture::Info>, srgb| {
+                info.and_then(|info| textures.get(gx, info.texture(), info.tex_coord(), srgb))
+            };
+
+            Material {
+                name: name(material.name()),
+                base_color: Vec4::from(pbr.base_color_factor()),
+                metallic: pbr.metallic_factor(),
+                roughness: pbr.roughness_factor(),
+                emissive: Vec3::from(material.emissive_factor()),
+                normal_scale: material.normal_texture().map_or(1.0, |texture| texture.scale()),
+                occlusion_strength: material.occlusion_texture().map_or(1.0, |texture| texture.strength()),
+                alpha_mode: match material.alpha_mode() {
+                    gl::material::AlphaMode::Opaque => AlphaMode::Opaque,
+                    gl::material::AlphaMode::Mask => AlphaMode::Mask(material.alpha_cutoff().unwrap_or(0.5)),
+                    gl::material::AlphaMode::Blend => AlphaMode::Blend,
+                },
+                double_sided: material.double_sided(),
+                base_color_texture: info(&mut textures, pbr.base_color_texture(), true),
+                metallic_roughness_texture: info(&mut textures, pbr.metallic_roughness_texture(), false),
+                normal_texture: material.normal_texture().and_then(|texture| {
+                    textures.get(gx, texture.texture(), texture.tex_coord(), false)
+                }),
+                occlusion_texture: material.occlusion_texture().and_then(|texture| {
+                    textures.get(gx, texture.texture(), texture.tex_coord(), false)
+                }),
+                emissive_texture: info(&mut textures, material.emissive_texture(), true),
+            }
+        }).collect();
+
+        let textures = textures.lots;
+
+
+        // meshes
+
+        let meshes = document.meshes().map(|mesh| {
+            let mut data = MeshData::default();
+            let mut materials = Vec::new();
+
+            for primitive in mesh.primitives() {
+                if primitive.mode() != gl::mesh::Mode::Triangles {
+                    log::warn!("skipping glTF primitive with mode {:?} in mesh {}", primitive.mode(), mesh.index());
+                    continue;
+                }
+                if primitive.morph_targets().next().is_some() {
+                    log::warn!("glTF morph targets in mesh {} are not supported", mesh.index());
+                }
+
+                let reader = primitive.reader(get_buffer);
+
+                let Some(positions) = reader.read_positions() else {
+                    log::warn!("skipping glTF primitive without positions in mesh {}", mesh.index());
+                    continue;
+                };
+
+                let mut part = MeshData {
+                    positions: positions.map(flip).collect(),
+                    normals: reader.read_normals().map_or(Vec::new(), |normals| normals.map(flip).collect()),
+                    uvs: reader.read_tex_coords(0).map_or(Vec::new(), |uvs| uvs.into_f32().map(Vec2::from).collect()),
+                    colors: reader.read_colors(0).map_or(Vec::new(), |colors| colors.into_rgba_f32().map(Vec4::from).collect()),
+                    tangents: reader.read_tangents().map_or(Vec::new(), |tangents| {
+                        tangents.map(|[x, y, z, w]| Vec4::new(x, y, -z, -w)).collect()
+                    }),
+                    joints: reader.read_joints(0).map_or(Vec::new(), |joints| joints.into_u16().collect()),
+                    weights: reader.read_weights(0).map_or(Vec::new(), |weights| weights.into_f32().map(Vec4::from).collect()),
+                    indices: reader.read_indices().map(|indices| indices.into_u32().collect()),
+                    submeshes: Vec::new(),
+                };
+
+                // flat normals if there are none
+                if part.normals.is_empty() {
+                    part.flatten();
+                    part.compute_normals();
+                }
+
+                if schema.contains(Attribute::Tangent) && part.tangents.is_empty() && !part.uvs.is_empty() {
+                    part.compute_tangents()?;
+                }
+
+                data.append(&part);
+                materials.push(primitive.material().index());
+            }
+
+            Ok(GltfMesh { name: name(mesh.name()), mesh: data.upload(gx, schema)?, materials })
+        }).collect::<Res<_>>()?;
+
+
+        // nodes and scenes
+
+        let mut nodes: Vec<Node> = document.nodes().map(|node| {
+            let (translation, rotation, scale) = node.transform().decomposed();
+            Node {
+                name: name(node.name()),
+                parent: None,
+                children: node.children().map(|child| child.index()).collect(),
+                translation: flip(translation),
+                rotation: flip_rotation(rotation),
+                scale: Vec3::from(scale),
+                mesh: node.mesh().map(|mesh| mesh.index()),
+                skin: node.skin().map(|skin| skin.index()),
+            }
+        }).collect();
+
+        link_parents(&mut nodes)?;
+
+        let scenes = document.scenes().map(|scene| Scene {
+            name: name(scene.name()),
+            roots: scene.nodes().map(|node| node.index()).collect(),
+        }).collect();
+
+
+        // skins and animations
+
+        let skins = document.skins().map(|skin| Skin {
+            name: name(skin.name()),
+            joints: skin.joints().map(|joint| joint.index()).collect(),
+            inverse_bind_matrices: skin.reader(get_buffer).read_inverse_bind_matrices()
+                .map_or(Vec::new(), |matrices| matrices.map(flip_matrix).collect()),
+            skeleton: skin.skeleton().map(|node| node.index()),
+        }).collect();
+
+        let animations = document.animations().map(|animation| {
+            let channels: Vec<Channel> = animation.channels().filter_map(|channel| {
+                use gl::animation::util::ReadOutputs;
+
+                let reader = channel.reader(get_buffer);
+                let times: Vec<f32> = reader.read_inputs()?.collect();
+
+                let values = match reader.read_outputs()? {
+                    ReadOutputs::Translations(values) => ChannelValues::Translation(values.map(flip).collect()),
+                    ReadOutputs::Rotations(values) => ChannelValues::Rotation(values.into_f32().map(flip_rotation).collect()),
+                    ReadOutputs::Scales(values) => ChannelValues::Scale(values.map(Vec3::from).collect()),
+                    ReadOutputs::MorphTargetWeights(values) => ChannelValues::Weights(values.into_f32().collect()),
+                };
+
+                Some(Channel {
+                    node: channel.target().node().index(),
+                    interpolation: match channel.sampler().interpolation() {
+                        gl::animation::Interpolation::Linear => Interpolation::Linear,
+                        gl::animation::Interpolation::Step => Interpolation::Step,
+                        gl::animation::Interpolation::CubicSpline => Interpolation::CubicSpline,
+                    },
+                    times, values,
+                })
+            }).collect();
+
+            let duration = channels.iter().filter_map(|channel| channel.times.last()).fold(0.0, |a: f32, &b| a.max(b));
+
+            Animation { name: name(animation.name()), channels, duration }
+        }).collect();
+
+        Ok(Self {
+            meshes, textures, samplers, materials, nodes, scenes,
+            default_scene: document.default_scene().map(|scene| scene.index()),
+            skins, animations,
+        })
+    }
+
+
+    pub fn material(&self, index: Option<usize>) -> Material {
+        index.and_then(|i| self.materials.get(i)).cloned().unwrap_or_default()
+    }
+
+    // roots of the default scene, or of the first one, or all nodes without a parent
+    pub fn roots(&self) -> Vec<usize> {
+        match self.scenes.get(self.default_scene.unwrap_or(0)) {
+            Some(scene) => scene.roots.clone(),
+            None => (0..self.nodes.len()).filter(|&i| self.nodes[i].parent.is_none()).collect(),
+        }
+    }
+
+    // world transforms of all nodes
+    pub fn world_transforms(&self) -> Vec<Mat4> {
+        let mut transforms = vec![Mat4::IDENTITY; self.nodes.len()];
+        let mut stack: Vec<(usize, Mat4)> = (0..self.nodes.len())
+            .filter(|&i| self.nodes[i].parent.is_none())
+            .map(|i| (i, Mat4::IDENTITY)).collect();
+
+        while let Some((i, parent)) = stack.pop() {
+            transforms[i] = parent * self.nodes[i].local_transform();
+            stack.extend(self.nodes[i].children.iter().map(|&child| (child, transforms[i])));
+        }
+
+        transforms
+    }
+
+    pub fn animate(&mut self, animation: usize, time: f32) {
+        self.animations[animation].apply(time, &mut self.nodes);
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+    use super::*;
+
+    fn node() -> Node {
+        Node {
+            name: None, parent: None, children: Vec::new(),
+            translation: Vec3::ZERO, rotation: Quat::IDENTITY, scale: Vec3::ONE, mesh: None, skin: None,
+        }
+    }
+
+    fn sample_at(interpolation: Interpolation, values: &[f32], time: f32) -> Option<f32> {
+        sample(&[0.0, 1.0, 2.0], values, interpolation, time, |a, b, t| a + (b - a) * t)
+    }
+
+    #[test]
+    fn samples_step() {
+        let values = [0.0, 10.0, 20.0];
+        assert_eq!(sample_at(Interpolation::Step, &values, -1.0), Some(0.0));
+        assert_eq!(sample_at(Interpolation::Step, &values, 0.5), Some(0.0));
+        assert_eq!(sample_at(Interpolation::Step, &values, 1.5), Some(10.0));
+        assert_eq!(sample_at(Interpolation::Step, &values, 3.0), Some(20.0));
+    }
+
+    #[test]
+    fn samples_linear() {
+        let values = [0.0, 10.0, 30.0];
+        assert_eq!(sample_at(Interpolation::Linear, &values, 0.5), Some(5.0));
+        assert_eq!(sample_at(Interpolation::Linear, &values, 1.25), Some(15.0));
+        assert_eq!(sample_at(Interpolation::Linear, &values, 2.0), Some(30.0));
+    }
+
+    #[test]
+    fn samples_cubic_spline() {
+        // in tangent, value and out tangent per keyframe
+        let flat = [0.0, 0.0, 0.0, 0.0, 10.0, 0.0, 0.0, 20.0, 0.0];
+        assert_eq!(sample_at(Interpolation::CubicSpline, &flat, 0.5), Some(5.0));
+        assert_eq!(sample_at(Interpolation::CubicSpline, &flat, 2.0), Some(20.0));
+
+        // tangents are scaled by the keyframe distance
+        let times = [0.0, 2.0];
+        let out_tangent = [0.0, 0.0, 1.0, 0.0, 0.0, 0.0];
+        let value = sample(&times, &out_tangent, Interpolation::CubicSpline, 1.0, |a, b, t| a + (b - a) * t);
+        assert_eq!(value, Some(0.25));
+    }
+
+    #[test]
+    fn applies_slerped_rotations() {
+        let channel = Channel {
+            node: 0, interpolation: Interpolation::Linear, times: vec![0.0, 1.0],
+            values: ChannelValues::Rotation(vec![Quat::IDENTITY, Quat::from_rotation_y(FRAC_PI_2)]),
+        };
+
+        let mut nodes = vec![node()];
+        channel.apply(0.5, &mut nodes);
+        assert!(nodes[0].rotation.angle_between(Quat::from_rotation_y(FRAC_PI_2 / 2.0)) < 1e-5);
+
+        // channels of other nodes are skipped
+        Channel { node: 1, ..channel }.apply(1.0, &mut nodes);
+        assert!(nodes[0].rotation.angle_between(Quat::from_rotation_y(FRAC_PI_2 / 2.0)) < 1e-5);
+    }
+
+    #[test]
+    fn flips_rotations_like_positions() {
+        let q = Quat::from_axis_angle(Vec3::new(1.0, 2.0, 3.0).normalize(), 0.7);
+        let v = [0.5, -1.0, 2.0];
+
+        let rotated = (q * Vec3::from(v)).to_array();
+        assert!(flip(rotated).abs_diff_eq(flip_rotation(q.to_array()) * flip(v), 1e-5));
+
+        let matrix = flip_matrix(Mat4::from_quat(q).to_cols_array_2d());
+        assert!(matrix.abs_diff_eq(Mat4::from_quat(flip_rotation(q.to_array())), 1e-5));
+    }
+
+    #[test]
+    fn rejects_invalid_node_trees() {
+        let mut nodes = vec![node(), node(), node()];
+        nodes[0].children = vec![1];
+        nodes[1].children = vec![2];
+        assert!(link_parents(&mut nodes.clone()).is_ok());
+
+        // two parents
+        nodes[0].children = vec![1, 2];
+        assert!(link_parents(&mut nodes.clone()).is_err());
+
+        // cycle without a root
+        nodes[0].children = vec![];
+        nodes[2].children = vec![1];
+        assert!(link_parents(&mut nodes).is_err());
+    }
+}
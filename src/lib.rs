@@ -46,20 +46,85 @@ pub use util_extension::*;
 mod render_target;
 pub use render_target::*;
 
+mod region;
+pub use region::*;
+
+mod multi_target;
+pub use multi_target::*;
+
+mod depth_target;
+pub use depth_target::*;
+
+mod stencil;
+pub use stencil::*;
+
 mod buffer_helper;
 pub use buffer_helper::*;
 
+mod sampler;
+pub use sampler::*;
+
+mod bind_group;
+pub use bind_group::*;
+
+mod device_cache;
+pub use device_cache::*;
+
+mod cached_bundle;
+pub use cached_bundle::*;
+
+mod tonemap;
+pub use tonemap::*;
+
+mod blit;
+pub use blit::*;
+
+mod atlas;
+pub use atlas::*;
+
 
 // features
 
 #[cfg(feature = "math")]
 pub mod math;
 
+#[cfg(feature = "math")]
+pub mod draw2d;
+
+#[cfg(feature = "math")]
+pub mod sprite;
+
+#[cfg(feature = "math")]
+pub mod mesh;
+
+#[cfg(feature = "math")]
+pub mod obj;
+
+#[cfg(feature = "math")]
+pub mod ply;
+
+#[cfg(feature = "math")]
+pub mod camera;
+
+#[cfg(feature = "text")]
+pub mod text;
+
+#[cfg(feature = "gltf")]
+pub mod gltf;
+
+#[cfg(feature = "pbr")]
+pub mod pbr;
+
 
 // wgsl modules
 #[cfg(feature = "wgsl_modules")]
 pub use wgsl_modules;
 
+#[cfg(feature = "wgsl_modules")]
+mod post_chain;
+#[cfg(feature = "wgsl_modules")]
+pub use post_chain::*;
+
 
 // control flow helper
 
@@ -79,6 +79,32 @@ macro_rules! binding {
             count: ::core::num::NonZeroU32::new($count),
         }
     };
+    ($loc:expr, $stage:expr, DepthTexture, $dim:ident) => {
+        $crate::binding!($loc, $stage, DepthTexture, $dim, [0])
+    };
+    ($loc:expr, $stage:expr, DepthTexture, $dim:ident, [$count:expr]) => {
+        $crate::wgpu::BindGroupLayoutEntry {
+            binding: $loc,
+            visibility: $stage,
+            ty: $crate::wgpu::BindingType::Texture {
+                sample_type: $crate::wgpu::TextureSampleType::Depth,
+                view_dimension: $crate::wgpu::TextureViewDimension::$dim,
+                multisampled: false,
+            },
+            count: ::core::num::NonZeroU32::new($count),
+        }
+    };
+    ($loc:expr, $stage:expr, ComparisonSampler) => {
+        $crate::binding!($loc, $stage, ComparisonSampler, [0])
+    };
+    ($loc:expr, $stage:expr, ComparisonSampler, [$count:expr]) => {
+        $crate::wgpu::BindGroupLayoutEntry {
+            binding: $loc,
+            visibility: $stage,
+            ty: $crate::wgpu::BindingType::Sampler($crate::wgpu::SamplerBindingType::Comparison),
+            count: ::core::num::NonZeroU32::new($count),
+        }
+    };
 }
 
 
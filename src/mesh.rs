@@ -0,0 +1,746 @@
+
+// meshes with a vertex schema, optional indices and submeshes, bounds and procedural generators
+// front faces are counter clockwise in the left handed system, like in the examples
+
+use std::{ops::Range, f32::consts::{PI, TAU}};
+use wgpu::{VertexFormat, VertexAttribute, VertexStepMode};
+use anyhow::{Result as Res, Context, bail, ensure};
+use crate::{*, math::*};
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Attribute {
+    Position, // Float32x3
+    Normal, // Float32x3
+    Uv, // Float32x2
+    Color, // Float32x4, linear
+    Tangent, // Float32x4, w is the handedness of the bitangent
+    Joints, // Uint16x4, skin joint indices
+    Weights, // Float32x4, skin joint weights
+}
+
+impl Attribute {
+    pub const fn format(self) -> VertexFormat {
+        match self {
+            Self::Position | Self::Normal => VertexFormat::Float32x3,
+            Self::Uv => VertexFormat::Float32x2,
+            Self::Color | Self::Tangent | Self::Weights => VertexFormat::Float32x4,
+            Self::Joints => VertexFormat::Uint16x4,
+        }
+    }
+}
+
+
+// attributes with their shader locations, in one interleaved or one buffer per attribute
+#[derive(Debug, Clone, PartialEq)]
+pub struct VertexSchema {
+    pub attributes: Vec<(Attribute, u32)>,
+    pub interleaved: bool,
+    streams: Vec<(u64, Vec<VertexAttribute>)>, // stride and attributes per buffer
+}
+
+impl VertexSchema {
+
+    pub fn new(attributes: &[(Attribute, u32)], interleaved: bool) -> Self {
+
+        let attribute = |(attribute, location): (Attribute, u32), offset| VertexAttribute {
+            format: attribute.format(), offset, shader_location: location,
+        };
+
+        let streams = if interleaved {
+            let mut offset = 0;
+            let attributes = attributes.iter().map(|&attr| {
+                let attribute = attribute(attr, offset);
+                offset += attr.0.format().size();
+                attribute
+            }).collect();
+            vec![(offset, attributes)]
+        }
+        else {
+            attributes.iter().map(|&attr| (attr.0.format().size(), vec![attribute(attr, 0)])).collect()
+        };
+
+        Self { attributes: attributes.to_vec(), interleaved, streams }
+    }
+
+    pub fn interleaved(attributes: &[(Attribute, u32)]) -> Self { Self::new(attributes, true) }
+    pub fn split(attributes: &[(Attribute, u32)]) -> Self { Self::new(attributes, false) }
+
+    // position, normal and uv at locations 0, 1 and 2
+    pub fn standard() -> Self {
+        Self::interleaved(&[(Attribute::Position, 0), (Attribute::Normal, 1), (Attribute::Uv, 2)])
+    }
+
+    pub fn contains(&self, attribute: Attribute) -> bool {
+        self.attributes.iter().any(|(attr, _)| *attr == attribute)
+    }
+
+    // number of vertex buffers, instance buffers follow at this slot
+    pub fn slots(&self) -> u32 { self.streams.len() as u32 }
+
+    pub fn buffer_layouts(&self) -> Vec<wgpu::VertexBufferLayout<'_>> {
+        self.streams.iter().map(|(array_stride, attributes)| wgpu::VertexBufferLayout {
+            array_stride: *array_stride, step_mode: VertexStepMode::Vertex, attributes,
+        }).collect()
+    }
+}
+
+
+// bounds
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+
+    pub const fn new(min: Vec3, max: Vec3) -> Self { Self { min, max } }
+
+    // inverted box for empty input
+    pub fn from_points(points: impl IntoIterator<Item = Vec3>) -> Self {
+        points.into_iter().fold(Self::new(Vec3::INFINITY, Vec3::NEG_INFINITY), |aabb, p| aabb.extend(p))
+    }
+
+    pub fn is_empty(&self) -> bool { self.min.cmpgt(self.max).any() }
+    pub fn center(&self) -> Vec3 { (self.min + self.max) / 2.0 }
+    pub fn size(&self) -> Vec3 { self.max - self.min }
+
+    pub fn extend(&self, point: Vec3) -> Self { Self::new(self.min.min(point), self.max.max(point)) }
+    pub fn union(&self, other: &Self) -> Self { Self::new(self.min.min(other.min), self.max.max(other.max)) }
+
+    pub fn contains(&self, point: Vec3) -> bool { point.cmpge(self.min).all() && point.cmple(self.max).all() }
+
+    pub fn corners(&self) -> [Vec3; 8] {
+        let (a, b) = (self.min, self.max);
+        [
+            Vec3::new(a.x, a.y, a.z), Vec3::new(b.x, a.y, a.z), Vec3::new(a.x, b.y, a.z), Vec3::new(b.x, b.y, a.z),
+            Vec3::new(a.x, a.y, b.z), Vec3::new(b.x, a.y, b.z), Vec3::new(a.x, b.y, b.z), Vec3::new(b.x, b.y, b.z),
+        ]
+    }
+
+    // box around the transformed box
+    pub fn transform(&self, matrix: &Mat4) -> Self {
+        Self::from_points(self.corners().map(|p| matrix.transform_point3(p)))
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct BoundingSphere {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+
+    // around the center of the bounding box
+    pub fn from_points(points: &[Vec3]) -> Self {
+        let center = Aabb::from_points(points.iter().copied()).center();
+        let radius = points.iter().map(|p| p.distance_squared(center)).fold(0.0, f32::max).sqrt();
+        Self { center, radius }
+    }
+
+    pub fn transform(&self, matrix: &Mat4) -> Self {
+        let scale = matrix.x_axis.truncate().length().max(matrix.y_axis.truncate().length()).max(matrix.z_axis.truncate().length());
+        Self { center: matrix.transform_point3(self.center), radius: self.radius * scale }
+    }
+}
+
+
+// triangles of a planar polygon of vertex indices with the winding of the polygon
+pub fn triangulate_polygon(positions: &[Vec3], polygon: &[u32]) -> Vec<[u32; 3]> {
+    if polygon.len() == 3 { return vec![[polygon[0], polygon[1], polygon[2]]] }
+
+    // newell normal and its plane
+    let points: Vec<Vec3> = polygon.iter().map(|&i| positions[i as usize]).collect();
+    let normal = (0..points.len()).fold(Vec3::ZERO, |normal, i| {
+        let (a, b) = (points[i], points[(i + 1) % points.len()]);
+        normal + Vec3::new((a.y - b.y) * (a.z + b.z), (a.z - b.z) * (a.x + b.x), (a.x - b.x) * (a.y + b.y))
+    });
+    let (u, v) = normal.normalize_or(Vec3::Z).any_orthonormal_pair();
+
+    let projected: Vec<Vec2> = points.iter().map(|p| Vec2::new(p.dot(u), p.dot(v))).collect();
+
+    crate::draw2d::triangulate(&projected).into_iter().map(|t| t.map(|i| polygon[i])).collect()
+}
+
+
+// cpu side mesh, empty attributes are filled with defaults when uploaded
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MeshData {
+    pub positions: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<Vec2>,
+    pub colors: Vec<Vec4>,
+    pub tangents: Vec<Vec4>,
+    pub joints: Vec<[u16; 4]>,
+    pub weights: Vec<Vec4>,
+    pub indices: Option<Vec<u32>>,
+    pub submeshes: Vec<Range<u32>>, // index ranges, or vertex ranges without indices
+}
+
+// outward normal of a front face
+fn face_normal(a: Vec3, b: Vec3, c: Vec3) -> Vec3 { Vec3::normal_from_triangle(a, c, b) }
+
+impl MeshData {
+
+    pub fn vertex_count(&self) -> usize { self.positions.len() }
+
+    pub fn index_count(&self) -> usize {
+        self.indices.as_ref().map_or(self.positions.len(), Vec::len)
+    }
+
+    // smallest index format for the vertex count
+    pub fn index_format(&self) -> Option<IndexFormat> {
+        self.indices.as_ref().map(|_| if self.positions.len() <= u16::MAX as usize + 1 { IndexFormat::Uint16 } else { IndexFormat::Uint32 })
+    }
+
+    pub fn triangles(&self) -> Vec<[u32; 3]> {
+        match &self.indices {
+            Some(indices) => indices.chunks_exact(3).map(|t| [t[0], t[1], t[2]]).collect(),
+            None => (0..self.positions.len() as u32 / 3).map(|i| [i * 3, i * 3 + 1, i * 3 + 2]).collect(),
+        }
+    }
+
+    pub fn aabb(&self) -> Aabb { Aabb::from_points(self.positions.iter().copied()) }
+    pub fn bounding_sphere(&self) -> BoundingSphere { BoundingSphere::from_points(&self.positions) }
+
+    // appends other as a new submesh
+    pub fn append(&mut self, other: &MeshData) {
+        let base = self.positions.len() as u32;
+        let start = self.index_count() as u32;
+
+        // attributes missing on one side are dropped
+        fn join<T: Copy>(a: &mut Vec<T>, b: &[T], a_len: usize) {
+            if a.len() == a_len && !b.is_empty() { a.extend_from_slice(b) } else { a.clear() }
+        }
+        let len = self.positions.len();
+        if len == 0 {
+            *self = MeshData { submeshes: Vec::new(), ..other.clone() };
+        } else {
+            join(&mut self.normals, &other.normals, len);
+            join(&mut self.uvs, &other.uvs, len);
+            join(&mut self.colors, &other.colors, len);
+            join(&mut self.tangents, &other.tangents, len);
+            join(&mut self.joints, &other.joints, len);
+            join(&mut self.weights, &other.weights, len);
+            self.positions.extend_from_slice(&other.positions);
+
+            match (&mut self.indices, &other.indices) {
+                (Some(indices), Some(other)) => indices.extend(other.iter().map(|i| i + base)),
+                (Some(indices), None) => indices.extend(base..base + other.positions.len() as u32),
+                (None, Some(other)) => {
+                    let mut indices: Vec<u32> = (0..base).collect();
+                    indices.extend(other.iter().map(|i| i + base));
+                    self.indices = Some(indices);
+                },
+                (None, None) => {},
+            }
+        }
+
+        if self.submeshes.is_empty() && start > 0 { self.submeshes.push(0..start) }
+        self.submeshes.push(start..self.index_count() as u32);
+    }
+
+
+    // negates z to convert between right and left handed coordinates,
+    // front faces keep their winding and the bitangent sign flips
+    pub fn flip_z(&mut self) {
+        for position in &mut self.positions { position.z = -position.z }
+        for normal in &mut self.normals { normal.z = -normal.z }
+        for tangent in &mut self.tangents { tangent.z = -tangent.z; tangent.w = -tangent.w }
+    }
+
+
+    // normals and tangents
+
+    // unshares vertices so that every triangle gets its own, e.g. for flat normals
+    pub fn flatten(&mut self) {
+        let Some(indices) = self.indices.take() else { return };
+
+        fn pick<T: Copy>(data: &mut Vec<T>, indices: &[u32]) {
+            if !data.is_empty() { *data = indices.iter().map(|&i| data[i as usize]).collect() }
+        }
+        pick(&mut self.positions, &indices);
+        pick(&mut self.normals, &indices);
+        pick(&mut self.uvs, &indices);
+        pick(&mut self.colors, &indices);
+        pick(&mut self.tangents, &indices);
+        pick(&mut self.joints, &indices);
+        pick(&mut self.weights, &indices);
+    }
+
+    // averaged face normals of shared vertices, flat for unshared vertices
+    pub fn compute_normals(&mut self) {
+        let mut normals = vec![Vec3::ZERO; self.positions.len()];
+
+        for [a, b, c] in self.triangles() {
+            let [pa, pb, pc] = [a, b, c].map(|i| self.positions[i as usize]);
+            let normal = face_normal(pa, pb, pc);
+            if normal.is_finite() {
+                for i in [a, b, c] { normals[i as usize] += normal }
+            }
+        }
+
+        self.normals = normals.into_iter().map(|n| n.normalize_or(Vec3::Y)).collect();
+    }
+
+    // tangents along the u direction, requires normals and uvs
+    pub fn compute_tangents(&mut self) -> Res<()> {
+        let len = self.positions.len();
+        ensure!(self.uvs.len() == len, "computing tangents requires uvs");
+        if self.normals.len() != len { self.compute_normals() }
+
+        let (mut tangents, mut bitangents) = (vec![Vec3::ZERO; len], vec![Vec3::ZERO; len]);
+
+        for [a, b, c] in self.triangles() {
+            let [pa, pb, pc] = [a, b, c].map(|i| self.positions[i as usize]);
+            let [ta, tb, tc] = [a, b, c].map(|i| self.uvs[i as usize]);
+
+            let (e1, e2) = (pb - pa, pc - pa);
+            let (d1, d2) = (tb - ta, tc - ta);
+
+            let det = d1.perp_dot(d2);
+            if det.abs() < 1e-12 { continue }
+
+            let tangent = (e1 * d2.y - e2 * d1.y) / det;
+            let bitangent = (e2 * d1.x - e1 * d2.x) / det;
+
+            for i in [a, b, c] {
+                tangents[i as usize] += tangent;
+                bitangents[i as usize] += bitangent;
+            }
+        }
+
+        self.tangents = (0..len).map(|i| {
+            let normal = self.normals[i];
+            let tangent = (tangents[i] - normal * normal.dot(tangents[i])).normalize_or(normal.any_orthonormal_vector());
+            let handedness = if normal.cross(tangent).dot(bitangents[i]) < 0.0 { -1.0 } else { 1.0 };
+            tangent.extend(handedness)
+        }).collect();
+
+        Ok(())
+    }
+
+
+    // generators, centered at the origin with normals and uvs
+
+    fn quad(indices: &mut Vec<u32>, [a, b, c, d]: [u32; 4]) {
+        indices.extend([a, b, c, a, c, d]);
+    }
+
+    pub fn cube(size: f32) -> Self {
+        let h = size / 2.0;
+        let mut data = Self { indices: Some(Vec::with_capacity(36)), ..Self::default() };
+
+        for (normal, up) in [
+            (Vec3::X, Vec3::Y), (Vec3::NEG_X, Vec3::Y), (Vec3::Z, Vec3::Y), (Vec3::NEG_Z, Vec3::Y),
+            (Vec3::Y, Vec3::Z), (Vec3::NEG_Y, Vec3::NEG_Z),
+        ] {
+            // right and up as seen from outside
+            let right = normal.cross(up);
+            let base = data.positions.len() as u32;
+
+            for (s, t) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+                data.positions.push((normal + right * s + up * t) * h);
+                data.normals.push(normal);
+                data.uvs.push(Vec2::new((s + 1.0) / 2.0, (1.0 - t) / 2.0));
+            }
+
+            Self::quad(data.indices.as_mut().unwrap(), [base, base + 1, base + 2, base + 3]);
+        }
+
+        data
+    }
+
+    // sectors around the y-axis, stacks from top to bottom
+    pub fn uv_sphere(radius: f32, sectors: u32, stacks: u32) -> Self {
+        let (sectors, stacks) = (sectors.max(3), stacks.max(2));
+        let mut data = Self::default();
+        let mut indices = Vec::with_capacity((sectors * stacks * 6) as usize);
+
+        for i in 0..=stacks {
+            let phi = PI * i as f32 / stacks as f32;
+            for j in 0..=sectors {
+                let theta = TAU * j as f32 / sectors as f32;
+                let normal = Vec3::new(phi.sin() * theta.cos(), phi.cos(), phi.sin() * theta.sin());
+                data.positions.push(normal * radius);
+                data.normals.push(normal);
+                data.uvs.push(Vec2::new(j as f32 / sectors as f32, i as f32 / stacks as f32));
+            }
+        }
+
+        let row = sectors + 1;
+        for i in 0..stacks {
+            for j in 0..sectors {
+                let [a, b] = [i * row + j, (i + 1) * row + j];
+                // the quads at the poles degenerate to triangles
+                if i != 0 { indices.extend([a, b, a + 1]) }
+                if i != stacks - 1 { indices.extend([a + 1, b, b + 1]) }
+            }
+        }
+
+        data.indices = Some(indices);
+        data
+    }
+
+    // in the xz-plane facing up, subdivided into a grid
+    pub fn plane(size: impl Into<Vec2>, subdivisions: [u32; 2]) -> Self {
+        let size = size.into();
+        let [nx, nz] = subdivisions.map(|s| s.max(1));
+        let mut data = Self::default();
+        let mut indices = Vec::with_capacity((nx * nz * 6) as usize);
+
+        for k in 0..=nz {
+            for i in 0..=nx {
+                let uv = Vec2::new(i as f32 / nx as f32, k as f32 / nz as f32);
+                data.positions.push(Vec3::new((uv.x - 0.5) * size.x, 0.0, (0.5 - uv.y) * size.y));
+                data.normals.push(Vec3::Y);
+                data.uvs.push(uv);
+            }
+        }
+
+        let row = nx + 1;
+        for k in 0..nz {
+            for i in 0..nx {
+                let a = k * row + i;
+                Self::quad(&mut indices, [a, a + row, a + row + 1, a + 1]);
+            }
+        }
+
+        data.indices = Some(indices);
+        data
+    }
+
+    // around the y-axis, caps as separate vertices with their own normals
+    pub fn cylinder(radius: f32, height: f32, sectors: u32, caps: bool) -> Self {
+        let sectors = sectors.max(3);
+        let h = height / 2.0;
+        let mut data = Self::default();
+        let mut indices = Vec::new();
+
+        for j in 0..=sectors {
+            let theta = TAU * j as f32 / sectors as f32;
+            let normal = Vec3::new(theta.cos(), 0.0, theta.sin());
+            for (y, v) in [(h, 0.0), (-h, 1.0)] {
+                data.positions.push(normal * radius + Vec3::Y * y);
+                data.normals.push(normal);
+                data.uvs.push(Vec2::new(j as f32 / sectors as f32, v));
+            }
+        }
+
+        for j in 0..sectors {
+            let a = j * 2;
+            Self::quad(&mut indices, [a, a + 1, a + 3, a + 2]);
+        }
+
+        if caps {
+            for normal in [Vec3::Y, Vec3::NEG_Y] {
+                let center = data.positions.len() as u32;
+                data.positions.push(normal * h);
+                data.normals.push(normal);
+                data.uvs.push(Vec2::splat(0.5));
+
+                for j in 0..sectors {
+                    let theta = TAU * j as f32 / sectors as f32;
+                    let (cos, sin) = (theta.cos(), theta.sin());
+                    data.positions.push(Vec3::new(cos * radius, 0.0, sin * radius) + normal * h);
+                    data.normals.push(normal);
+                    data.uvs.push(Vec2::new(0.5 + cos / 2.0, 0.5 + sin / 2.0));
+                }
+
+                for j in 0..sectors {
+                    let [a, b] = [center + 1 + j, center + 1 + (j + 1) % sectors];
+                    indices.extend(if normal.y > 0.0 { [center, a, b] } else { [center, b, a] });
+                }
+            }
+        }
+
+        data.indices = Some(indices);
+        data
+    }
+
+
+    // uploads the attributes of the schema
+    pub fn upload(&self, gx: &impl WgxDevice, schema: &VertexSchema) -> Res<Mesh> {
+        Mesh::new(gx, self, schema)
+    }
+
+    fn write_attribute(&self, attribute: Attribute, i: usize, bytes: &mut Vec<u8>) {
+        fn extend<T: ReadBytes>(bytes: &mut Vec<u8>, value: T) { bytes.extend_from_slice(value.read_bytes()) }
+
+        match attribute {
+            Attribute::Position => extend(bytes, self.positions[i]),
+            Attribute::Normal => extend(bytes, self.normals.get(i).copied().unwrap_or(Vec3::Y)),
+            Attribute::Uv => extend(bytes, self.uvs.get(i).copied().unwrap_or(Vec2::ZERO)),
+            Attribute::Color => extend(bytes, self.colors.get(i).copied().unwrap_or(Vec4::ONE)),
+            Attribute::Tangent => extend(bytes, self.tangents.get(i).copied().unwrap_or(Vec4::new(1.0, 0.0, 0.0, 1.0))),
+            Attribute::Joints => extend(bytes, self.joints.get(i).copied().unwrap_or([0; 4])),
+            Attribute::Weights => extend(bytes, self.weights.get(i).copied().unwrap_or(Vec4::X)),
+        }
+    }
+
+    fn check(&self) -> Res<()> {
+        let len = self.positions.len();
+        for (name, attr_len) in [
+            ("normals", self.normals.len()), ("uvs", self.uvs.len()), ("colors", self.colors.len()),
+            ("tangents", self.tangents.len()), ("joints", self.joints.len()), ("weights", self.weights.len()),
+        ] {
+            if attr_len != 0 && attr_len != len { bail!("{attr_len} {name} for {len} positions") }
+        }
+        if let Some(index) = self.indices.iter().flatten().find(|&&i| i as usize >= len) {
+            bail!("index {index} out of range of {len} vertices");
+        }
+        for range in &self.submeshes {
+            ensure!(range.start <= range.end && range.end as usize <= self.index_count(), "submesh {range:?} out of range");
+        }
+        Ok(())
+    }
+}
+
+
+// gpu side mesh
+#[derive(Debug)]
+pub struct Mesh {
+    pub schema: VertexSchema,
+    pub buffers: Vec<wgpu::Buffer>, // one per schema stream
+    pub indices: Option<(wgpu::Buffer, IndexFormat)>,
+    pub vertex_count: u32,
+    pub index_count: u32,
+    pub submeshes: Vec<DrawIndexedIndirectArgs>, // whole mesh if not submeshed
+    pub aabb: Aabb,
+    pub sphere: BoundingSphere,
+}
+
+impl Mesh {
+
+    pub fn new(gx: &impl WgxDevice, data: &MeshData, schema: &VertexSchema) -> Res<Self> {
+        data.check()?;
+
+        let len = data.positions.len();
+
+        let buffers = schema.streams.iter().enumerate().map(|(s, (stride, _))| {
+            let attributes: &[(Attribute, u32)] = if schema.interleaved { &schema.attributes } else { &schema.attributes[s..=s] };
+            let mut bytes = Vec::with_capacity(len * *stride as usize);
+            for i in 0..len {
+                for (attribute, _) in attributes { data.write_attribute(*attribute, i, &mut bytes) }
+            }
+            gx.buffer_from_data(BufUse::VERTEX, bytes.as_slice())
+        }).collect();
+
+        let indices = data.indices.as_ref().zip(data.index_format()).map(|(indices, format)| {
+            let buffer = match format {
+                IndexFormat::Uint16 => {
+                    let indices: Vec<u16> = indices.iter().map(|&i| i as u16).collect();
+                    gx.buffer_from_data(BufUse::INDEX, indices.as_slice())
+                },
+                IndexFormat::Uint32 => gx.buffer_from_data(BufUse::INDEX, indices.as_slice()),
+            };
+            (buffer, format)
+        });
+
+        let index_count = data.index_count();
+
+        let whole = 0..index_count as u32;
+        let submeshes = if data.submeshes.is_empty() { std::slice::from_ref(&whole) } else { &data.submeshes[..] };
+
+        let submeshes = submeshes.iter()
+            .map(|range| DrawIndexedIndirectArgs::try_from_offset_ranges(0, range.start as usize..range.end as usize, 0..1))
+            .collect::<Res<_>>()?;
+
+        Ok(Self {
+            schema: schema.clone(), buffers, indices,
+            vertex_count: len as u32, index_count: index_count as u32,
+            submeshes, aabb: data.aabb(), sphere: data.bounding_sphere(),
+        })
+    }
+
+    pub fn buffer_layouts(&self) -> Vec<wgpu::VertexBufferLayout<'_>> { self.schema.buffer_layouts() }
+
+    // buffer with the args of all submeshes for multi_draw_indexed_indirect
+    pub fn indirect_buffer(&self, gx: &impl WgxDevice, instances: Range<u32>) -> wgpu::Buffer {
+        let args: Vec<_> = self.submeshes.iter().map(|args| DrawIndexedIndirectArgs {
+            first_instance: instances.start, instance_count: instances.len() as u32, ..*args
+        }).collect();
+
+        gx.buffer_from_data(BufUse::INDIRECT, args.as_slice())
+    }
+
+    fn draw_range(&self, rpass: &mut wgpu::RenderPass, range: Range<u32>, instances: Range<u32>) {
+        for (slot, buffer) in self.buffers.iter().enumerate() {
+            rpass.set_vertex_buffer(slot as u32, buffer.slice(..));
+        }
+        match &self.indices {
+            Some((buffer, format)) => {
+                rpass.set_index_buffer(buffer.slice(..), *format);
+                rpass.draw_indexed(range, 0, instances);
+            },
+            None => rpass.draw(range, instances),
+        }
+    }
+
+    pub fn draw(&self, rpass: &mut wgpu::RenderPass) {
+        self.draw_instanced(rpass, 0..1);
+    }
+
+    pub fn draw_instanced(&self, rpass: &mut wgpu::RenderPass, instances: Range<u32>) {
+        self.draw_range(rpass, 0..self.index_count, instances);
+    }
+
+    pub fn draw_submesh(&self, rpass: &mut wgpu::RenderPass, submesh: usize, instances: Range<u32>) -> Res<()> {
+        let range = self.submeshes.get(submesh).with_context(|| format!("submesh {submesh} out of range"))?.index_range()?;
+        self.draw_range(rpass, range, instances);
+        Ok(())
+    }
+
+    // same for render bundles
+    pub fn draw_bundle<'a>(&'a self, encoder: &mut wgpu::RenderBundleEncoder<'a>, instances: Range<u32>) {
+        for (slot, buffer) in self.buffers.iter().enumerate() {
+            encoder.set_vertex_buffer(slot as u32, buffer.slice(..));
+        }
+        match &self.indices {
+            Some((buffer, format)) => {
+                encoder.set_index_buffer(buffer.slice(..), *format);
+                encoder.draw_indexed(0..self.index_count, 0, instances);
+            },
+            None => encoder.draw(0..self.index_count, instances),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generated() -> [(&'static str, MeshData); 5] {
+        [
+            ("cube", MeshData::cube(2.0)),
+            ("sphere", MeshData::uv_sphere(1.0, 8, 6)),
+            ("plane", MeshData::plane([2.0, 1.0], [3, 2])),
+            ("cylinder", MeshData::cylinder(1.0, 2.0, 8, false)),
+            ("capped cylinder", MeshData::cylinder(1.0, 2.0, 8, true)),
+        ]
+    }
+
+    // face normals of the front faces point the way of the vertex normals
+    fn assert_consistent_winding(name: &str, mesh: &MeshData) {
+        for [a, b, c] in mesh.triangles() {
+            let [pa, pb, pc] = [a, b, c].map(|i| mesh.positions[i as usize]);
+            let normal = face_normal(pa, pb, pc);
+            for i in [a, b, c] {
+                assert!(normal.dot(mesh.normals[i as usize]) > 0.0, "{name}: triangle {:?} faces inwards", [a, b, c]);
+            }
+        }
+    }
+
+    #[test]
+    fn generators_are_valid() {
+        for (name, mesh) in generated() {
+            mesh.check().unwrap();
+            assert_eq!(mesh.normals.len(), mesh.vertex_count(), "{name}");
+            assert_eq!(mesh.uvs.len(), mesh.vertex_count(), "{name}");
+            assert!(mesh.normals.iter().all(|n| (n.length() - 1.0).abs() < 1e-5), "{name}");
+        }
+        assert_eq!(MeshData::cube(1.0).index_count(), 36);
+        assert_eq!(MeshData::plane([1.0, 1.0], [3, 2]).index_count(), 36);
+    }
+
+    #[test]
+    fn generators_face_outwards() {
+        for (name, mesh) in generated() { assert_consistent_winding(name, &mesh) }
+
+        // the normals point away from the center
+        let sphere = MeshData::uv_sphere(2.0, 8, 6);
+        assert!(sphere.positions.iter().zip(&sphere.normals).all(|(p, n)| p.normalize().abs_diff_eq(*n, 1e-5)));
+    }
+
+    #[test]
+    fn computed_normals_match_generated() {
+        for (name, mesh) in [("cube", MeshData::cube(1.0)), ("plane", MeshData::plane([1.0, 1.0], [2, 2]))] {
+            let mut computed = mesh.clone();
+            computed.compute_normals();
+            assert!(computed.normals.iter().zip(&mesh.normals).all(|(a, b)| a.abs_diff_eq(*b, 1e-5)), "{name}");
+        }
+
+        // shared vertices get averaged normals pointing outwards
+        let mut sphere = MeshData::uv_sphere(1.0, 16, 8);
+        sphere.compute_normals();
+        assert_consistent_winding("sphere", &sphere);
+    }
+
+    #[test]
+    fn computes_tangents_along_u() {
+        let mut plane = MeshData::plane([2.0, 2.0], [1, 1]);
+        plane.compute_tangents().unwrap();
+        assert!(plane.tangents.iter().all(|t| t.abs_diff_eq(Vec4::new(1.0, 0.0, 0.0, 1.0), 1e-5)));
+
+        // mirrored uvs flip the handedness
+        for uv in &mut plane.uvs { uv.y = 1.0 - uv.y }
+        plane.compute_tangents().unwrap();
+        assert!(plane.tangents.iter().all(|t| t.w == -1.0));
+
+        plane.uvs.clear();
+        assert!(plane.compute_tangents().is_err());
+    }
+
+    #[test]
+    fn appends_submeshes() {
+        let mut mesh = MeshData::cube(1.0);
+        mesh.append(&MeshData::plane([1.0, 1.0], [1, 1]));
+        mesh.check().unwrap();
+
+        assert_eq!(mesh.vertex_count(), 24 + 4);
+        assert_eq!(mesh.submeshes, [0..36, 36..42]);
+        assert_eq!(mesh.indices.as_ref().unwrap()[36..], [24, 26, 27, 24, 27, 25]);
+        assert_consistent_winding("appended", &mesh);
+
+        // attributes missing on one side are dropped, meshes without indices get theirs
+        let mut flat = MeshData::plane([1.0, 1.0], [1, 1]);
+        flat.flatten();
+        let mut other = MeshData::cube(1.0);
+        other.uvs.clear();
+        flat.append(&other);
+        flat.check().unwrap();
+        assert!(flat.uvs.is_empty());
+        assert_eq!(flat.indices.as_ref().unwrap()[..6], [0, 1, 2, 3, 4, 5]);
+        assert_eq!(flat.submeshes, [0..6, 6..42]);
+    }
+
+    #[test]
+    fn flattens_triangles() {
+        let cube = MeshData::cube(1.0);
+        let mut flat = cube.clone();
+        flat.flatten();
+        flat.check().unwrap();
+
+        assert_eq!(flat.indices, None);
+        assert_eq!(flat.vertex_count(), 36);
+        assert_eq!(flat.index_count(), 36);
+
+        let positions = |mesh: &MeshData| -> Vec<[Vec3; 3]> {
+            mesh.triangles().iter().map(|t| t.map(|i| mesh.positions[i as usize])).collect()
+        };
+        assert_eq!(positions(&flat), positions(&cube));
+        assert_consistent_winding("flat", &flat);
+    }
+
+    #[test]
+    fn check_rejects_invalid_data() {
+        let mut mesh = MeshData::cube(1.0);
+        mesh.normals.pop();
+        assert!(mesh.check().is_err());
+
+        let mut mesh = MeshData::cube(1.0);
+        mesh.indices.as_mut().unwrap()[0] = 24;
+        assert!(mesh.check().is_err());
+
+        let mut mesh = MeshData::cube(1.0);
+        mesh.submeshes = vec![0..6, 6..37];
+        assert!(mesh.check().is_err());
+        mesh.submeshes = vec![0..6, Range { start: 6, end: 3 }];
+        assert!(mesh.check().is_err());
+    }
+}
@@ -0,0 +1,150 @@
+use arrayvec::ArrayVec;
+use anyhow::Result as Res;
+use wgpu::{TextureFormat, StoreOp};
+use crate::*;
+
+
+// attachment description
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AttachmentDsc {
+    pub format: TextureFormat,
+    pub view_format: Option<TextureFormat>,
+    pub usage: TexUse, // RENDER_ATTACHMENT is always added
+    pub clear: Option<ClearValue>, // None loads
+    pub store: StoreOp, // with msaa and resolve: Discard keeps only the resolved texture
+    pub blend: Option<Blend>,
+    pub resolve: bool, // with msaa: resolve into a single sampled texture, otherwise keep the multisampled one
+}
+
+impl AttachmentDsc {
+    pub fn new(format: TextureFormat) -> Self {
+        Self {
+            format, view_format: None, usage: TexUse::TEXTURE_BINDING,
+            clear: Some(ClearValue::default_for(format)), store: StoreOp::Store, blend: None, resolve: true,
+        }
+    }
+
+    pub const fn view_format(self, view_format: TextureFormat) -> Self { Self { view_format: Some(view_format), ..self } }
+    pub const fn usage(self, usage: TexUse) -> Self { Self { usage, ..self } }
+    pub const fn clear(self, clear: Option<ClearValue>) -> Self { Self { clear, ..self } }
+    pub const fn store(self, store: StoreOp) -> Self { Self { store, ..self } }
+    pub const fn blend(self, blend: Option<Blend>) -> Self { Self { blend, ..self } }
+    pub const fn resolve(self, resolve: bool) -> Self { Self { resolve, ..self } }
+}
+
+
+#[derive(Debug)]
+pub struct TargetAttachment {
+    pub dsc: AttachmentDsc,
+    pub lot: TextureLot, // output texture, multisampled if not resolved
+    pub msaa_opt: Option<TextureLot>,
+}
+
+impl TargetAttachment {
+    fn new(gx: &impl WgxDevice, [w, h]: [u32; 2], msaa: u32, dsc: AttachmentDsc) -> Self {
+        let resolved = msaa > 1 && dsc.resolve;
+        let usage = dsc.usage | TexUse::RENDER_ATTACHMENT;
+
+        Self {
+            dsc,
+            lot: TextureLot::new_2d(gx, [w, h, 1], if resolved { 1 } else { msaa }, dsc.format, dsc.view_format, usage),
+            msaa_opt: resolved.then(||
+                TextureLot::new_2d(gx, [w, h, 1], msaa, dsc.format, dsc.view_format, TexUse::RENDER_ATTACHMENT)
+            ),
+        }
+    }
+
+    pub fn view_format(&self) -> TextureFormat { self.lot.descriptor.view_format }
+
+    // resolve target and multisampled view
+    pub fn views(&self) -> (&wgpu::TextureView, Option<&wgpu::TextureView>) {
+        (&self.lot.view, self.msaa_opt.as_ref().map(|o| &o.view))
+    }
+}
+
+
+#[derive(Debug)]
+pub struct MultiTextureTarget<const N: usize> {
+    pub size: [u32; 2],
+    pub msaa: u32,
+    pub attachments: [TargetAttachment; N],
+    pub depth_opt: Option<TextureLot>,
+    pub generation: u64,
+}
+
+// the first attachment is the target's format and view_format, as used by single target helpers like PostChain
+impl<const N: usize> RenderTarget for MultiTextureTarget<N> {
+    fn size(&self) -> [u32; 2] { self.size }
+    fn msaa(&self) -> u32 { self.msaa }
+    fn depth_testing(&self) -> Option<TextureFormat> { self.depth_opt.as_ref().map(|d| d.view_format()) }
+    fn format(&self) -> TextureFormat { self.attachments[0].dsc.format }
+    fn view_format(&self) -> TextureFormat { self.attachments[0].view_format() }
+    fn color_formats(&self) -> ArrayVec<TextureFormat, 8> {
+        self.attachments.iter().map(TargetAttachment::view_format).collect()
+    }
+    fn generation(&self) -> u64 { self.generation }
+}
+
+impl<const N: usize> RenderAttachable for MultiTextureTarget<N> {
+    fn color_views(&self) -> (&wgpu::TextureView, Option<&wgpu::TextureView>) {
+        self.attachments[0].views()
+    }
+    fn depth_view(&self) -> Option<(&wgpu::TextureView, TextureFormat)> {
+        self.depth_opt.as_ref().map(|d| (&d.view, d.view_format()))
+    }
+}
+
+impl<const N: usize> MultiTextureTarget<N> {
+
+    pub fn new(
+        gx: &impl WgxDevice, size: impl Into<[u32; 2]>, msaa: u32, depth_testing: Option<TextureFormat>,
+        attachments: [AttachmentDsc; N],
+    ) -> Self {
+        let size = size.into();
+        let [w, h] = size;
+        Self {
+            size, msaa,
+            attachments: attachments.map(|dsc| TargetAttachment::new(gx, size, msaa, dsc)),
+            depth_opt: depth_testing.map(|depth_format|
+                TextureLot::new_2d(gx, [w, h, 1], msaa, depth_format, None, TexUse::RENDER_ATTACHMENT)
+            ),
+            generation: 0,
+        }
+    }
+
+    // recreates all textures, bind groups using them have to be recreated
+    pub fn update(&mut self, gx: &impl WgxDevice, size: impl Into<[u32; 2]>) {
+        let size = size.into();
+        if size == self.size { return }
+        *self = Self {
+            generation: self.generation,
+            ..Self::new(gx, size, self.msaa, self.depth_testing(), self.attachments.each_ref().map(|att| att.dsc))
+        };
+    }
+
+    pub fn lot(&self, index: usize) -> &TextureLot { &self.attachments[index].lot }
+    pub fn view(&self, index: usize) -> &wgpu::TextureView { &self.attachments[index].lot.view }
+
+    // blend states of the attachments, for RenderTarget::multi_render_pipeline
+    pub fn blends(&self) -> [Option<Blend>; N] {
+        self.attachments.each_ref().map(|att| att.dsc.blend)
+    }
+
+    // all attachments, colors are cleared and stored as described
+    // fails if a clear value doesn't match its attachment format
+    pub fn color_attachments(&self) -> Res<[Option<wgpu::RenderPassColorAttachment>; N]> {
+        let mut attachments = [const { None }; N];
+
+        for (attachment, att) in attachments.iter_mut().zip(&self.attachments) {
+            let (view, msaa) = att.views();
+            let dsc = ColorAttachment::new_checked(view, msaa, att.dsc.clear, att.view_format())?;
+            *attachment = Some(dsc.store(att.dsc.store).into());
+        }
+
+        Ok(attachments)
+    }
+
+    pub fn all_attachments(&self, clear_depth: Option<f32>, clear_stencil: Option<u32>) -> Res<RenderAttachments<N>> {
+        Ok((self.color_attachments()?, self.depth_attachment(clear_depth, clear_stencil)))
+    }
+}
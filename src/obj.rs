@@ -0,0 +1,466 @@
+
+// wavefront obj and mtl import and export
+// obj is right handed, z is negated into the left handed system of wgx and back when exported
+
+use std::{collections::HashMap, path::{Path, PathBuf}, str::SplitWhitespace};
+use anyhow::{Result as Res, Context, bail};
+use crate::{math::*, mesh::*};
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjMaterial {
+    pub name: String,
+    pub ambient: Vec3,
+    pub diffuse: Vec3,
+    pub specular: Vec3,
+    pub emissive: Vec3,
+    pub shininess: f32,
+    pub opacity: f32,
+    pub diffuse_map: Option<PathBuf>, // relative to the obj file
+    pub specular_map: Option<PathBuf>,
+    pub emissive_map: Option<PathBuf>,
+    pub normal_map: Option<PathBuf>,
+    pub opacity_map: Option<PathBuf>,
+}
+
+impl ObjMaterial {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ambient: Vec3::ZERO, diffuse: Vec3::ONE, specular: Vec3::ZERO, emissive: Vec3::ZERO,
+            shininess: 0.0, opacity: 1.0,
+            diffuse_map: None, specular_map: None, emissive_map: None, normal_map: None, opacity_map: None,
+        }
+    }
+}
+
+
+// groups, objects and material changes each start a submesh of the same index
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjGroup {
+    pub name: String,
+    pub material_name: Option<String>,
+    pub material: Option<usize>, // into ObjModel::materials
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ObjModel {
+    pub mesh: MeshData,
+    pub groups: Vec<ObjGroup>,
+    pub materials: Vec<ObjMaterial>,
+    pub material_libs: Vec<String>,
+}
+
+
+fn floats(tokens: SplitWhitespace) -> Res<Vec<f32>> {
+    tokens.map(|token| token.parse::<f32>().with_context(|| format!("invalid number {token:?}"))).collect()
+}
+
+fn vec3(values: &[f32]) -> Res<Vec3> {
+    match values {
+        [x, y, z, ..] => Ok(Vec3::new(*x, *y, *z)),
+        _ => bail!("expected 3 numbers, got {}", values.len()),
+    }
+}
+
+// one based, negative indices count back from the last element
+fn index(token: &str, len: usize) -> Res<usize> {
+    let i: i64 = token.parse().with_context(|| format!("invalid index {token:?}"))?;
+    let resolved = if i < 0 { len as i64 + i } else { i - 1 };
+    if i == 0 || resolved < 0 || resolved >= len as i64 { bail!("index {i} out of range of {len}") }
+    Ok(resolved as usize)
+}
+
+// the rest of a line after the keyword
+fn rest<'a>(line: &'a str, keyword: &str) -> &'a str { line[keyword.len()..].trim() }
+
+
+impl ObjModel {
+
+    // with the materials of the referenced mtl files, missing ones are reported as warnings
+    pub fn load(path: impl AsRef<Path>) -> Res<Self> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        let mut model = Self::parse(&source)?;
+
+        let base = path.parent().unwrap_or(Path::new(""));
+        let mut materials = Vec::new();
+
+        for lib in &model.material_libs {
+            match std::fs::read_to_string(base.join(lib)) {
+                Ok(source) => materials.extend(parse_mtl(&source, base).with_context(|| format!("parsing {lib}"))?),
+                Err(err) => log::warn!("skipping material library {lib}: {err}"),
+            }
+        }
+
+        model.set_materials(materials);
+        Ok(model)
+    }
+
+    // without materials, groups get their material index from set_materials
+    pub fn parse(source: &str) -> Res<Self> {
+
+        let (mut positions, mut colors, mut uvs, mut normals) = (Vec::<Vec3>::new(), Vec::<Vec4>::new(), Vec::<Vec2>::new(), Vec::<Vec3>::new());
+        let mut has_colors = false;
+
+        let mut mesh = MeshData { indices: Some(Vec::new()), ..MeshData::default() };
+        let mut vertices: HashMap<[usize; 3], u32> = HashMap::new(); // position, uv and normal
+        let mut missing_uvs = false;
+        let mut missing_normals: Vec<bool> = Vec::new(); // per vertex
+
+        let mut groups: Vec<(String, Option<String>, u32)> = Vec::new(); // name, material, start
+        let mut material_libs = Vec::new();
+
+        // a new group replaces the previous one if that has no faces
+        fn begin(groups: &mut Vec<(String, Option<String>, u32)>, name: String, material: Option<String>, start: u32) {
+            match groups.last_mut() {
+                Some(group) if group.2 == start => *group = (name, material, start),
+                _ => groups.push((name, material, start)),
+            }
+        }
+
+        for (number, line) in source.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let mut tokens = line.split_whitespace();
+
+            let Some(keyword) = tokens.next() else { continue };
+
+            let result = (|| -> Res<()> {
+                match keyword {
+                    "v" => {
+                        let values = floats(tokens)?;
+                        positions.push(vec3(&values)?);
+                        // vertex colors as an extension after the position
+                        if values.len() >= 6 { has_colors = true }
+                        colors.push(if values.len() >= 6 { Vec4::new(values[3], values[4], values[5], 1.0) } else { Vec4::ONE });
+                    },
+                    "vt" => {
+                        let values = floats(tokens)?;
+                        let u = *values.first().context("expected a texture coordinate")?;
+                        uvs.push(Vec2::new(u, 1.0 - values.get(1).copied().unwrap_or(0.0)));
+                    },
+                    "vn" => normals.push(vec3(&floats(tokens)?)?),
+                    "f" => {
+                        let mut polygon = Vec::new();
+
+                        for corner in tokens {
+                            let mut parts = corner.split('/');
+                            let position = index(parts.next().unwrap_or(""), positions.len())?;
+                            let uv = match parts.next() { Some(i) if !i.is_empty() => index(i, uvs.len())?, _ => usize::MAX };
+                            let normal = match parts.next() { Some(i) if !i.is_empty() => index(i, normals.len())?, _ => usize::MAX };
+
+                            missing_uvs |= uv == usize::MAX;
+
+                            let vertex = *vertices.entry([position, uv, normal]).or_insert_with(|| {
+                                mesh.positions.push(positions[position]);
+                                mesh.colors.push(colors[position]);
+                                mesh.uvs.push(uvs.get(uv).copied().unwrap_or(Vec2::ZERO));
+                                mesh.normals.push(normals.get(normal).copied().unwrap_or(Vec3::ZERO));
+                                missing_normals.push(normal == usize::MAX);
+                                mesh.positions.len() as u32 - 1
+                            });
+                            polygon.push(vertex);
+                        }
+
+                        if polygon.len() < 3 { bail!("face with {} vertices", polygon.len()) }
+
+                        if groups.is_empty() { begin(&mut groups, "default".to_string(), None, 0) }
+
+                        let indices = mesh.indices.as_mut().unwrap();
+                        for triangle in triangulate_polygon(&mesh.positions, &polygon) { indices.extend(triangle) }
+                    },
+                    "g" | "o" => {
+                        let material = groups.last().and_then(|group| group.1.clone());
+                        begin(&mut groups, rest(line, keyword).to_string(), material, mesh.index_count() as u32);
+                    },
+                    "usemtl" => {
+                        let name = groups.last().map_or("default".to_string(), |group| group.0.clone());
+                        begin(&mut groups, name, Some(rest(line, keyword).to_string()), mesh.index_count() as u32);
+                    },
+                    "mtllib" => material_libs.extend(tokens.map(String::from)),
+                    _ => {}, // smoothing groups, lines, points, free form geometry
+                }
+                Ok(())
+            })();
+
+            result.with_context(|| format!("obj line {}", number + 1))?;
+        }
+
+        if !has_colors { mesh.colors.clear() }
+        if uvs.is_empty() { mesh.uvs.clear() }
+        else if missing_uvs { log::warn!("obj faces without texture coordinates get zero uvs") }
+
+        let end = mesh.index_count() as u32;
+        mesh.submeshes = groups.iter().enumerate().map(|(i, group)| {
+            group.2..groups.get(i + 1).map_or(end, |next| next.2)
+        }).collect();
+
+        mesh.flip_z();
+
+        // computed normals only for vertices without one
+        if missing_normals.contains(&true) {
+            let normals = std::mem::take(&mut mesh.normals);
+            mesh.compute_normals();
+            for (computed, (normal, missing)) in mesh.normals.iter_mut().zip(normals.into_iter().zip(missing_normals)) {
+                if !missing { *computed = normal }
+            }
+        }
+
+        Ok(Self {
+            mesh,
+            groups: groups.into_iter().map(|(name, material, _)| ObjGroup { name, material_name: material, material: None }).collect(),
+            materials: Vec::new(),
+            material_libs,
+        })
+    }
+
+    // resolves the material names of the groups
+    pub fn set_materials(&mut self, materials: Vec<ObjMaterial>) {
+        for group in &mut self.groups {
+            group.material = group.material_name.as_ref().and_then(|name| {
+                let index = materials.iter().position(|material| &material.name == name);
+                if index.is_none() { log::warn!("obj material {name} not found") }
+                index
+            });
+        }
+        self.materials = materials;
+    }
+
+    pub fn material(&self, group: usize) -> Option<&ObjMaterial> {
+        self.groups.get(group)?.material.map(|i| &self.materials[i])
+    }
+}
+
+
+// map paths are joined to base
+pub fn parse_mtl(source: &str, base: &Path) -> Res<Vec<ObjMaterial>> {
+    let mut materials: Vec<ObjMaterial> = Vec::new();
+
+    for (number, line) in source.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        let mut tokens = line.split_whitespace();
+
+        let Some(keyword) = tokens.next() else { continue };
+
+        if keyword == "newmtl" {
+            materials.push(ObjMaterial::new(rest(line, keyword)));
+            continue;
+        }
+
+        let Some(material) = materials.last_mut() else { continue };
+
+        let result = (|| -> Res<()> {
+            // map options come before the file name
+            let map = || tokens.clone().last().map(|file| base.join(file));
+
+            match keyword {
+                "Ka" => material.ambient = vec3(&floats(tokens.clone())?)?,
+                "Kd" => material.diffuse = vec3(&floats(tokens.clone())?)?,
+                "Ks" => material.specular = vec3(&floats(tokens.clone())?)?,
+                "Ke" => material.emissive = vec3(&floats(tokens.clone())?)?,
+                "Ns" => material.shininess = *floats(tokens.clone())?.first().context("expected a number")?,
+                "d" => material.opacity = *floats(tokens.clone())?.first().context("expected a number")?,
+                "Tr" => material.opacity = 1.0 - *floats(tokens.clone())?.first().context("expected a number")?,
+                "map_Kd" => material.diffuse_map = map(),
+                "map_Ks" => material.specular_map = map(),
+                "map_Ke" => material.emissive_map = map(),
+                "map_Bump" | "map_bump" | "bump" | "norm" => material.normal_map = map(),
+                "map_d" => material.opacity_map = map(),
+                _ => {}, // illumination models and unsupported maps
+            }
+            Ok(())
+        })();
+
+        result.with_context(|| format!("mtl line {}", number + 1))?;
+    }
+
+    Ok(materials)
+}
+
+
+// export, one group per submesh, submeshes of meshes without indices are vertex ranges
+
+pub fn write(mesh: &MeshData) -> String {
+    let mut mesh = mesh.clone();
+    mesh.flip_z();
+
+    let mut out = format!("# wgx mesh with {} vertices and {} triangles\n", mesh.vertex_count(), mesh.index_count() / 3);
+
+    for (i, p) in mesh.positions.iter().enumerate() {
+        match mesh.colors.get(i) {
+            Some(c) => out += &format!("v {} {} {} {} {} {}\n", p.x, p.y, p.z, c.x, c.y, c.z),
+            None => out += &format!("v {} {} {}\n", p.x, p.y, p.z),
+        }
+    }
+    for uv in &mesh.uvs { out += &format!("vt {} {}\n", uv.x, 1.0 - uv.y) }
+    for n in &mesh.normals { out += &format!("vn {} {} {}\n", n.x, n.y, n.z) }
+
+    let (has_uvs, has_normals) = (!mesh.uvs.is_empty(), !mesh.normals.is_empty());
+    let triangles = mesh.triangles();
+
+    let whole = 0..mesh.index_count() as u32;
+    let submeshes = if mesh.submeshes.is_empty() { std::slice::from_ref(&whole) } else { &mesh.submeshes[..] };
+
+    for (s, range) in submeshes.iter().enumerate() {
+        out += &format!("g submesh_{s}\n");
+
+        let (start, end) = (range.start as usize / 3, (range.end as usize / 3).min(triangles.len()));
+
+        for triangle in triangles.get(start..end).unwrap_or_default() {
+            out += "f";
+            for i in triangle.iter().map(|i| i + 1) {
+                out += &match (has_uvs, has_normals) {
+                    (false, false) => format!(" {i}"),
+                    (true, false) => format!(" {i}/{i}"),
+                    (false, true) => format!(" {i}//{i}"),
+                    (true, true) => format!(" {i}/{i}/{i}"),
+                };
+            }
+            out += "\n";
+        }
+    }
+
+    out
+}
+
+pub fn save(path: impl AsRef<Path>, mesh: &MeshData) -> Res<()> {
+    let path = path.as_ref();
+    std::fs::write(path, write(mesh)).with_context(|| format!("writing {}", path.display()))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(indices: &[u32]) -> Vec<u32> {
+        let mut indices = indices.to_vec();
+        indices.sort();
+        indices.dedup();
+        indices
+    }
+
+    #[test]
+    fn resolves_negative_indices() {
+        let model = ObjModel::parse("v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf -4 -3 -2\nf 1 3 -1\n").unwrap();
+        let indices = model.mesh.indices.unwrap();
+        assert_eq!(indices.len(), 6);
+        assert_eq!(sorted(&indices), [0, 1, 2, 3]);
+        assert_eq!(model.mesh.positions[3], Vec3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn triangulates_polygons() {
+        // concave pentagon
+        let model = ObjModel::parse("v 0 0 0\nv 2 0 0\nv 2 2 0\nv 1 1 0\nv 0 2 0\nf 1 2 3 4 5\n").unwrap();
+        let indices = model.mesh.indices.unwrap();
+        assert_eq!(indices.len(), 9);
+        assert_eq!(sorted(&indices), [0, 1, 2, 3, 4]);
+
+        // all triangles keep the counter clockwise winding of the polygon
+        let normal = |t: &[u32]| {
+            let [a, b, c] = [0, 1, 2].map(|i| model.mesh.positions[t[i] as usize]);
+            (b - a).cross(c - a).z
+        };
+        assert!(indices.chunks_exact(3).all(|t| normal(t) > 0.0));
+    }
+
+    #[test]
+    fn shares_vertices_with_equal_attributes() {
+        let model = ObjModel::parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvt 1 1\nf 1/1 2/1 3/1\nf 1/1 3/1 2/2\n").unwrap();
+        assert_eq!(model.mesh.vertex_count(), 4);
+        assert_eq!(model.mesh.uvs[3], Vec2::new(1.0, 0.0));
+    }
+
+    #[test]
+    fn splits_groups_and_materials() {
+        let source = "v 0 0 0\nv 1 0 0\nv 0 1 0\ng a\nusemtl red\nf 1 2 3\nusemtl blue\nf 1 2 3\ng b\nf 1 2 3\n";
+        let model = ObjModel::parse(source).unwrap();
+        assert_eq!(model.mesh.submeshes, [0..3, 3..6, 6..9]);
+
+        let names: Vec<_> = model.groups.iter().map(|group| (group.name.as_str(), group.material_name.as_deref())).collect();
+        assert_eq!(names, [("a", Some("red")), ("a", Some("blue")), ("b", Some("blue"))]);
+    }
+
+    #[test]
+    fn rejects_invalid_indices() {
+        for face in ["f 0 1 2", "f 1 2 4", "f -4 1 2", "f 1 2", "f 1 x 2"] {
+            assert!(ObjModel::parse(&format!("v 0 0 0\nv 1 0 0\nv 0 1 0\n{face}\n")).is_err(), "{face}");
+        }
+    }
+
+    #[test]
+    fn keeps_provided_normals() {
+        let model = ObjModel::parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 0\nvn 1 0 0\nf 1//1 2//1 3//1\nf 2 4 3\n").unwrap();
+        let normals = &model.mesh.normals;
+        assert_eq!(normals.len(), 6);
+        assert!(normals[..3].iter().all(|&n| n == Vec3::X));
+        assert!(normals[3..].iter().all(|n| (n.length() - 1.0).abs() < 1e-6 && n.x == 0.0));
+    }
+
+    fn triangle() -> MeshData {
+        MeshData {
+            positions: vec![Vec3::new(0.0, 0.0, 1.0), Vec3::new(1.0, 0.0, 1.0), Vec3::new(0.0, 1.0, 2.0)],
+            normals: vec![Vec3::Z; 3],
+            uvs: vec![Vec2::new(0.0, 0.25), Vec2::new(1.0, 0.5), Vec2::new(0.5, 1.0)],
+            indices: Some(vec![0, 1, 2]),
+            ..MeshData::default()
+        }
+    }
+
+    #[test]
+    fn round_trips() {
+        let mesh = triangle();
+        let parsed = ObjModel::parse(&write(&mesh)).unwrap().mesh;
+        assert_eq!(parsed.positions, mesh.positions);
+        assert_eq!(parsed.normals, mesh.normals);
+        assert_eq!(parsed.uvs, mesh.uvs);
+        assert_eq!(parsed.indices, mesh.indices);
+    }
+
+    #[test]
+    fn writes_meshes_without_indices() {
+        let mut mesh = triangle();
+        mesh.append(&triangle());
+        mesh.flatten();
+        assert_eq!(mesh.submeshes, [0..3, 3..6]);
+
+        let source = write(&mesh);
+        assert_eq!(source.lines().filter(|line| line.starts_with("f ")).count(), 2);
+
+        let parsed = ObjModel::parse(&source).unwrap();
+        assert_eq!(parsed.mesh.submeshes, [0..3, 3..6]);
+        assert_eq!(parsed.mesh.positions, mesh.positions);
+    }
+
+    #[test]
+    fn parses_materials() {
+        let source = "newmtl red # comment\nKd 1 0 0\nd 0.5\nmap_Kd -o 1 1 red.png\nnewmtl glass\nTr 0.75\nbump glass normal.png\n";
+        let materials = parse_mtl(source, Path::new("textures")).unwrap();
+
+        assert_eq!(materials.len(), 2);
+        assert_eq!(materials[0].name, "red");
+        assert_eq!(materials[0].diffuse, Vec3::X);
+        assert_eq!(materials[0].opacity, 0.5);
+        assert_eq!(materials[0].diffuse_map, Some(Path::new("textures").join("red.png")));
+        assert_eq!(materials[1].opacity, 0.25);
+        assert_eq!(materials[1].normal_map, Some(Path::new("textures").join("normal.png")));
+
+        assert!(parse_mtl("newmtl a\nKd 1 0\n", Path::new("")).is_err());
+    }
+
+    #[test]
+    fn resolves_group_materials() {
+        let mut model = ObjModel::parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl b\nf 1 2 3\nusemtl c\nf 1 2 3\n").unwrap();
+        model.set_materials(vec![ObjMaterial::new("a"), ObjMaterial::new("b")]);
+        assert_eq!(model.material(0).map(|material| material.name.as_str()), Some("b"));
+        assert_eq!(model.material(1), None);
+    }
+
+    #[test]
+    fn rejects_truncated_input() {
+        assert!(ObjModel::parse("v 0 0\n").is_err());
+        assert!(ObjModel::parse("v 0 0 0\nvn 0 1\n").is_err());
+        assert!(ObjModel::parse("vt\n").is_err());
+    }
+}
@@ -0,0 +1,904 @@
+
+// physically based forward rendering with metallic roughness materials, punctual lights,
+// image based lighting and shadow maps
+//
+// the shaders are exported as wgsl package `wgx_pbr` by the wgx_pbr_shaders crate, custom shaders for
+// PbrRenderer::with_shader include "wgx_pbr::pbr.wgsl" and "wgx_pbr::vertex.wgsl" after depending on
+// wgx_pbr_shaders and importing the package with `wgsl_modules_loader::build::import_packages` in their build script
+
+use std::{ops::Range, mem::size_of, sync::Arc};
+use wgpu::{TextureViewDimension, DepthBiasState, AddressMode};
+use anyhow::{Result as Res, Context};
+use crate::{*, math::*, mesh::*, camera::{Camera, Projection, look_rotation}, render_target::TargetKey};
+
+
+pub const ENVIRONMENT_FORMAT: TextureFormat = DEFAULT_HDR;
+
+const IRRADIANCE_SIZE: u32 = 32;
+const PREFILTERED_SIZE: u32 = 256;
+const PREFILTERED_MIPS: u32 = 6;
+const BRDF_LUT_SIZE: u32 = 256;
+
+
+// position, normal, uv and tangent at locations 0 to 3
+pub fn vertex_schema() -> VertexSchema {
+    VertexSchema::interleaved(&[
+        (Attribute::Position, 0), (Attribute::Normal, 1), (Attribute::Uv, 2), (Attribute::Tangent, 3),
+    ])
+}
+
+// per instance model matrix at locations 4 to 7, normals assume uniform scale
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PbrInstance {
+    pub model: Mat4,
+}
+
+unsafe impl ReadBytes for PbrInstance {}
+
+impl PbrInstance {
+    pub const LAYOUT: wgpu::VertexBufferLayout<'static> = vertex_dsc!(Instance,
+        4 => Float32x4, 5 => Float32x4, 6 => Float32x4, 7 => Float32x4
+    );
+}
+
+impl From<Mat4> for PbrInstance {
+    fn from(model: Mat4) -> Self { Self { model } }
+}
+
+
+// lights
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightKind {
+    Directional,
+    Point,
+    Spot { inner: f32, outer: f32 }, // cone half angles in radians
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Light {
+    pub kind: LightKind,
+    pub position: Vec3,
+    pub direction: Vec3, // the direction the light travels
+    pub color: Color, // linear
+    pub intensity: f32,
+    pub range: f32, // distance where point and spot lights fade out, 0 for inverse square falloff only
+    pub shadow: bool, // directional and spot lights only
+    pub shadow_bias: f32, // world space offset along the normal
+}
+
+impl Light {
+
+    fn new(kind: LightKind, position: Vec3, direction: Vec3, color: Color, intensity: f32) -> Self {
+        Self { kind, position, direction, color, intensity, range: 0.0, shadow: false, shadow_bias: 0.02 }
+    }
+
+    pub fn directional(direction: impl Into<Vec3>, color: Color, intensity: f32) -> Self {
+        Self::new(LightKind::Directional, Vec3::ZERO, direction.into().normalize_or(Vec3::NEG_Y), color, intensity)
+    }
+
+    pub fn point(position: impl Into<Vec3>, color: Color, intensity: f32) -> Self {
+        Self::new(LightKind::Point, position.into(), Vec3::NEG_Y, color, intensity)
+    }
+
+    pub fn spot(position: impl Into<Vec3>, direction: impl Into<Vec3>, inner: f32, outer: f32, color: Color, intensity: f32) -> Self {
+        let kind = LightKind::Spot { inner: inner.min(outer), outer };
+        Self::new(kind, position.into(), direction.into().normalize_or(Vec3::NEG_Y), color, intensity)
+    }
+
+    pub fn range(self, range: f32) -> Self { Self { range, ..self } }
+    pub fn shadow(self, shadow: bool) -> Self { Self { shadow, ..self } }
+    pub fn shadow_bias(self, shadow_bias: f32) -> Self { Self { shadow_bias, ..self } }
+
+    // view projection of the shadow map, directional lights cover the bounds, point lights have none
+    pub fn shadow_matrix(&self, bounds: &BoundingSphere) -> Option<Mat4> {
+        let direction = self.direction.normalize_or(Vec3::NEG_Y);
+        let up = if direction.y.abs() > 0.99 { Vec3::Z } else { Vec3::Y };
+
+        match self.kind {
+            LightKind::Directional => {
+                let radius = bounds.radius.max(1e-3);
+                let projection = Projection::orthographic(2.0 * radius, 1.0, radius, 3.0 * radius);
+                let eye = bounds.center - direction * 2.0 * radius;
+                Some(Camera::new(eye, look_rotation(direction, up), projection).view_projection())
+            },
+            LightKind::Spot { outer, .. } => {
+                let far = if self.range > 0.0 { self.range } else { self.position.distance(bounds.center) + bounds.radius };
+                let far = far.max(1e-2);
+                let projection = Projection::perspective((2.0 * outer).clamp(1e-3, 3.1), 1.0, far * 1e-3, far);
+                Some(Camera::new(self.position, look_rotation(direction, up), projection).view_projection())
+            },
+            LightKind::Point => None,
+        }
+    }
+
+    fn data(&self, shadow: i32) -> LightData {
+        let (kind, cone) = match self.kind {
+            LightKind::Directional => (0, [1.0, 1.0]),
+            LightKind::Point => (1, [-1.0, -1.0]),
+            LightKind::Spot { inner, outer } => (2, [inner.cos().max(outer.cos() + 1e-4), outer.cos()]), // smoothstep needs distinct edges
+        };
+        LightData {
+            position: self.position, range: self.range,
+            direction: self.direction.normalize_or(Vec3::NEG_Y), kind,
+            color: Vec3::from_array(self.color.f32_rgb()), intensity: self.intensity,
+            cone, shadow, shadow_bias: self.shadow_bias,
+        }
+    }
+}
+
+// matches Light in wgx_pbr::bindings.wgsl
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct LightData {
+    position: Vec3, range: f32,
+    direction: Vec3, kind: u32,
+    color: Vec3, intensity: f32,
+    cone: [f32; 2], shadow: i32, shadow_bias: f32,
+}
+
+unsafe impl ReadBytes for LightData {}
+
+// matches Frame in wgx_pbr::bindings.wgsl
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct FrameData {
+    view_projection: Mat4,
+    camera_position: Vec3, light_count: u32,
+    environment_intensity: f32, prefiltered_mips: f32, shadow_texel: f32, _pad: f32,
+}
+
+unsafe impl ReadBytes for FrameData {}
+
+
+// materials
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlphaMode {
+    Opaque,
+    Mask(f32), // cutoff
+    Blend, // drawn without depth writes, draw blended meshes last and back to front
+}
+
+// metallic roughness parameters, texture factors multiply the texture values,
+// missing textures are white, or flat for the normal texture
+#[derive(Debug, Clone, Copy)]
+pub struct PbrMaterial<'a> {
+    pub base_color: Vec4, // linear
+    pub metallic: f32,
+    pub roughness: f32,
+    pub emissive: Vec3, // linear
+    pub normal_scale: f32,
+    pub occlusion_strength: f32,
+    pub alpha_mode: AlphaMode,
+    pub double_sided: bool,
+    pub base_color_texture: Option<&'a wgpu::TextureView>, // srgb view
+    pub metallic_roughness_texture: Option<&'a wgpu::TextureView>, // roughness in g, metallic in b
+    pub normal_texture: Option<&'a wgpu::TextureView>,
+    pub occlusion_texture: Option<&'a wgpu::TextureView>, // in r
+    pub emissive_texture: Option<&'a wgpu::TextureView>, // srgb view
+    pub sampler: Option<&'a wgpu::Sampler>, // for all textures, repeating trilinear by default
+}
+
+impl Default for PbrMaterial<'_> {
+    fn default() -> Self {
+        Self {
+            base_color: Vec4::ONE, metallic: 0.0, roughness: 0.5, emissive: Vec3::ZERO,
+            normal_scale: 1.0, occlusion_strength: 1.0, alpha_mode: AlphaMode::Opaque, double_sided: false,
+            base_color_texture: None, metallic_roughness_texture: None, normal_texture: None,
+            occlusion_texture: None, emissive_texture: None, sampler: None,
+        }
+    }
+}
+
+impl<'a> PbrMaterial<'a> {
+
+    pub fn new(base_color: Color, metallic: f32, roughness: f32) -> Self {
+        Self { base_color: Vec4::from_array(base_color.f32()), metallic, roughness, ..Self::default() }
+    }
+
+    // textures of the scene, only uv set 0 is supported
+    #[cfg(feature = "gltf")]
+    pub fn from_gltf(scene: &'a crate::gltf::GltfScene, material: &crate::gltf::Material, sampler: Option<&'a wgpu::Sampler>) -> Self {
+        let texture = |texture: Option<crate::gltf::TextureRef>| texture.map(|texture| &scene.textures[texture.texture].view);
+
+        Self {
+            base_color: material.base_color, metallic: material.metallic, roughness: material.roughness,
+            emissive: material.emissive, normal_scale: material.normal_scale, occlusion_strength: material.occlusion_strength,
+            alpha_mode: match material.alpha_mode {
+                crate::gltf::AlphaMode::Opaque => AlphaMode::Opaque,
+                crate::gltf::AlphaMode::Mask(cutoff) => AlphaMode::Mask(cutoff),
+                crate::gltf::AlphaMode::Blend => AlphaMode::Blend,
+            },
+            double_sided: material.double_sided,
+            base_color_texture: texture(material.base_color_texture),
+            metallic_roughness_texture: texture(material.metallic_roughness_texture),
+            normal_texture: texture(material.normal_texture),
+            occlusion_texture: texture(material.occlusion_texture),
+            emissive_texture: texture(material.emissive_texture),
+            sampler,
+        }
+    }
+
+    fn params(&self) -> MaterialParams {
+        MaterialParams {
+            base_color: self.base_color, emissive: self.emissive,
+            metallic: self.metallic, roughness: self.roughness,
+            normal_scale: self.normal_scale, occlusion_strength: self.occlusion_strength,
+            alpha_cutoff: match self.alpha_mode { AlphaMode::Mask(cutoff) => cutoff, _ => 0.0 },
+        }
+    }
+
+    // index into PbrRenderer::pipelines
+    fn pipeline(&self) -> usize {
+        (self.alpha_mode == AlphaMode::Blend) as usize * 2 + self.double_sided as usize
+    }
+}
+
+// matches Material in wgx_pbr::bindings.wgsl
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct MaterialParams {
+    base_color: Vec4,
+    emissive: Vec3, metallic: f32,
+    roughness: f32, normal_scale: f32, occlusion_strength: f32, alpha_cutoff: f32,
+}
+
+unsafe impl ReadBytes for MaterialParams {}
+
+
+// image based lighting
+
+// irradiance and prefiltered specular cube maps with the brdf lookup table of the split sum approximation
+#[derive(Debug)]
+pub struct Environment {
+    pub irradiance: TextureLot,
+    pub prefiltered: TextureLot, // roughness from 0 to 1 over the mips
+    pub brdf_lut: TextureLot,
+}
+
+fn cube_lot(gx: &impl WgxDevice, size: u32, mip_levels: u32) -> TextureLot {
+    let mut descriptor = TexDsc::new_2d([size, size, 6], 1, ENVIRONMENT_FORMAT, None, TexUse::RENDER_ATTACHMENT | TexUse::TEXTURE_BINDING);
+    descriptor.view_dimension = TextureViewDimension::Cube;
+    descriptor.mip_level_count = mip_levels;
+    TextureLot::new(gx, descriptor)
+}
+
+fn cube_view(lot: &TextureLot, mip: u32, mip_levels: u32) -> wgpu::TextureView {
+    lot.texture.create_view(&wgpu::TextureViewDescriptor {
+        dimension: Some(TextureViewDimension::Cube),
+        base_mip_level: mip, mip_level_count: Some(mip_levels),
+        ..wgpu::TextureViewDescriptor::default()
+    })
+}
+
+fn face_view(lot: &TextureLot, mip: u32, face: u32) -> wgpu::TextureView {
+    lot.texture.create_view(&wgpu::TextureViewDescriptor {
+        dimension: Some(TextureViewDimension::D2),
+        base_mip_level: mip, mip_level_count: Some(1),
+        base_array_layer: face, array_layer_count: Some(1),
+        ..wgpu::TextureViewDescriptor::default()
+    })
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct GenerateParams { roughness: f32, lod: f32, samples: u32, _pad: f32 }
+
+unsafe impl ReadBytes for GenerateParams {}
+
+// full screen passes of wgx_pbr::ibl_gen.wgsl
+struct Generator {
+    module: Arc<wgpu::ShaderModule>,
+    cube_layout: BindGroupLayoutDsc,
+    equirect_layout: BindGroupLayoutDsc,
+    lut_layout: BindGroupLayoutDsc,
+    sampler: Arc<wgpu::Sampler>,
+    equirect_sampler: Arc<wgpu::Sampler>, // repeating around the horizon
+}
+
+impl Generator {
+
+    fn new(gx: &impl WgxDevice) -> Self {
+        Self {
+            module: gx.shared_wgsl(wgsl_modules::include!("wgx_pbr::ibl_gen.wgsl")),
+            cube_layout: gx.layout_dsc(&[
+                binding!(0, Stage::FRAGMENT, Texture, Cube),
+                binding!(1, Stage::FRAGMENT, Sampler),
+                binding!(2, Stage::FRAGMENT, UniformBuffer, 16),
+            ]),
+            equirect_layout: gx.layout_dsc(&[
+                binding!(1, Stage::FRAGMENT, Sampler),
+                binding!(2, Stage::FRAGMENT, UniformBuffer, 16),
+                binding!(3, Stage::FRAGMENT, Texture, D2),
+            ]),
+            lut_layout: gx.layout_dsc(&[binding!(2, Stage::FRAGMENT, UniformBuffer, 16)]),
+            sampler: gx.shared_sampler(&SamplerDsc::TRILINEAR),
+            equirect_sampler: gx.shared_sampler(&SamplerDsc::TRILINEAR.address_uvw(AddressMode::Repeat, AddressMode::ClampToEdge, AddressMode::ClampToEdge)),
+        }
+    }
+
+    fn pipeline(&self, gx: &impl WgxDevice, layout: &BindGroupLayoutDsc, entry_point: &str) -> Arc<wgpu::RenderPipeline> {
+        gx.shared_render_pipeline(
+            1, None, Some(&gx.shared_pipeline_layout(&[], &[&layout.layout])), &[],
+            (&self.module, "vs_main", Primitive::default()),
+            Some((&self.module, entry_point, &[(ENVIRONMENT_FORMAT, None)])),
+        )
+    }
+
+    fn params(gx: &impl WgxDevice, roughness: f32, lod: f32, samples: u32) -> wgpu::Buffer {
+        gx.buffer_from_data(BufUse::UNIFORM, GenerateParams { roughness, lod, samples, _pad: 0.0 })
+    }
+
+    fn bind_cube(&self, gx: &impl WgxDevice, view: &wgpu::TextureView, params: &wgpu::Buffer) -> Res<wgpu::BindGroup> {
+        self.cube_layout.bind(gx).texture(0, view).sampler(1, &self.sampler).buffer(2, params).finish()
+    }
+
+    // all six faces of one mip, the face is the instance index
+    fn render_cube(encoder: &mut wgpu::CommandEncoder, pipeline: &wgpu::RenderPipeline, binding: &wgpu::BindGroup, target: &TextureLot, mip: u32) {
+        for face in 0..6 {
+            let view = face_view(target, mip, face);
+            let mut rpass = encoder.render_pass(([Some(ColorAttachment::new(&view, None, None).into())], None));
+            rpass.set_pipeline(pipeline);
+            rpass.set_bind_group(0, binding, &[]);
+            rpass.draw(0..3, face..face + 1);
+        }
+    }
+
+    fn brdf_lut(&self, gx: &impl WgxDevice, encoder: &mut wgpu::CommandEncoder) -> Res<TextureLot> {
+        let lut = TextureLot::new_2d(
+            gx, [BRDF_LUT_SIZE, BRDF_LUT_SIZE, 1], 1, ENVIRONMENT_FORMAT, None,
+            TexUse::RENDER_ATTACHMENT | TexUse::TEXTURE_BINDING,
+        );
+
+        let pipeline = self.pipeline(gx, &self.lut_layout, "fs_brdf_lut");
+        let params = Self::params(gx, 0.0, 0.0, 512);
+        let binding = self.lut_layout.bind(gx).buffer(2, &params).finish()?;
+
+        let mut rpass = encoder.render_pass(([Some(ColorAttachment::new(&lut.view, None, None).into())], None));
+        rpass.set_pipeline(&pipeline);
+        rpass.set_bind_group(0, &binding, &[]);
+        rpass.draw(0..3, 0..1);
+
+        Ok(lut)
+    }
+}
+
+enum Source<'a> {
+    Equirect(&'a wgpu::TextureView),
+    Cube(&'a wgpu::TextureView),
+}
+
+impl Environment {
+
+    // the same radiance from all directions
+    pub fn uniform(gx: &impl WgxDeviceQueue, color: Color) -> Res<Self> {
+        let generator = Generator::new(gx);
+        let mut encoder = gx.command_encoder();
+
+        let irradiance = cube_lot(gx, 1, 1);
+        let prefiltered = cube_lot(gx, 1, 1);
+
+        for lot in [&irradiance, &prefiltered] {
+            for face in 0..6 {
+                let view = face_view(lot, 0, face);
+                encoder.render_pass(([Some(ColorAttachment::new(&view, None, Some(color.into())).into())], None));
+            }
+        }
+
+        let brdf_lut = generator.brdf_lut(gx, &mut encoder)?;
+        gx.queue().submit([encoder.finish()]);
+
+        Ok(Self { irradiance, prefiltered, brdf_lut })
+    }
+
+    // from an equirectangular panorama with +y up and the center towards +x,
+    // resampled to a cube with the given face size, the view needs a filterable format like DEFAULT_HDR
+    pub fn from_equirect(gx: &impl WgxDeviceQueue, view: &wgpu::TextureView, size: u32) -> Res<Self> {
+        Self::generate(gx, Source::Equirect(view), size)
+    }
+
+    // from a cube view, resampled to the given face size
+    pub fn from_cube(gx: &impl WgxDeviceQueue, view: &wgpu::TextureView, size: u32) -> Res<Self> {
+        Self::generate(gx, Source::Cube(view), size)
+    }
+
+    fn generate(gx: &impl WgxDeviceQueue, source: Source, size: u32) -> Res<Self> {
+        let generator = Generator::new(gx);
+        let mut encoder = gx.command_encoder();
+
+        let size = size.max(1);
+        let mip_levels = size.ilog2() + 1;
+
+        let copy = generator.pipeline(gx, &generator.cube_layout, "fs_copy");
+
+        // source cube with mips, sparse samples read from coarser mips
+        let cube = cube_lot(gx, size, mip_levels);
+
+        let params = Generator::params(gx, 0.0, 0.0, 0);
+
+        match source {
+            Source::Equirect(view) => {
+                let pipeline = generator.pipeline(gx, &generator.equirect_layout, "fs_equirect");
+                let binding = generator.equirect_layout.bind(gx)
+                    .sampler(1, &generator.equirect_sampler)
+                    .buffer(2, &params)
+                    .texture(3, view)
+                    .finish()?;
+                Generator::render_cube(&mut encoder, &pipeline, &binding, &cube, 0);
+            },
+            Source::Cube(view) => {
+                Generator::render_cube(&mut encoder, &copy, &generator.bind_cube(gx, view, &params)?, &cube, 0);
+            },
+        }
+
+        for mip in 1..mip_levels {
+            let binding = generator.bind_cube(gx, &cube_view(&cube, mip - 1, 1), &params)?;
+            Generator::render_cube(&mut encoder, &copy, &binding, &cube, mip);
+        }
+
+        let source_view = &cube.view;
+
+        // irradiance
+        let irradiance_size = IRRADIANCE_SIZE.min(size);
+        let irradiance = cube_lot(gx, irradiance_size, 1);
+
+        let pipeline = generator.pipeline(gx, &generator.cube_layout, "fs_irradiance");
+        let lod = (size / irradiance_size).ilog2() as f32;
+        let binding = generator.bind_cube(gx, source_view, &Generator::params(gx, 0.0, lod, 64))?;
+        Generator::render_cube(&mut encoder, &pipeline, &binding, &irradiance, 0);
+
+        // prefiltered specular, mip 0 is a mirror
+        let prefiltered_size = PREFILTERED_SIZE.min(size);
+        let prefiltered_mips = PREFILTERED_MIPS.min(prefiltered_size.ilog2() + 1);
+        let prefiltered = cube_lot(gx, prefiltered_size, prefiltered_mips);
+
+        let lod = (size / prefiltered_size).ilog2() as f32;
+        let binding = generator.bind_cube(gx, source_view, &Generator::params(gx, 0.0, lod, 0))?;
+        Generator::render_cube(&mut encoder, &copy, &binding, &prefiltered, 0);
+
+        let pipeline = generator.pipeline(gx, &generator.cube_layout, "fs_prefilter");
+
+        for mip in 1..prefiltered_mips {
+            let roughness = mip as f32 / (prefiltered_mips - 1) as f32;
+            let binding = generator.bind_cube(gx, source_view, &Generator::params(gx, roughness, 0.0, 256))?;
+            Generator::render_cube(&mut encoder, &pipeline, &binding, &prefiltered, mip);
+        }
+
+        let brdf_lut = generator.brdf_lut(gx, &mut encoder)?;
+        gx.queue().submit([encoder.finish()]);
+
+        Ok(Self { irradiance, prefiltered, brdf_lut })
+    }
+
+    pub fn prefiltered_mips(&self) -> u32 { self.prefiltered.descriptor.mip_level_count }
+}
+
+
+// renderer
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct MaterialId(pub usize);
+
+#[derive(Debug)]
+struct MaterialEntry {
+    binding: wgpu::BindGroup,
+    params: wgpu::Buffer,
+    pipeline: usize,
+}
+
+// group 0 with frame uniforms, lights, shadows and environment, group 1 per material,
+// see wgx_pbr::bindings.wgsl
+#[derive(Debug)]
+pub struct PbrRenderer {
+    pub lights: Vec<Light>,
+    pub shadow_bounds: BoundingSphere, // covered by the shadows of directional lights
+    pub environment_intensity: f32,
+    pub frame_layout: BindGroupLayoutDsc,
+    pub material_layout: BindGroupLayoutDsc,
+    pub pipeline_layout: Arc<wgpu::PipelineLayout>,
+    pub shadows: DepthTarget, // one layer per shadow casting light
+    environment: Environment,
+    pipelines: [Arc<wgpu::RenderPipeline>; 4], // opaque, double sided, blended, blended and double sided
+    shader: Arc<wgpu::ShaderModule>,
+    target: TargetKey,
+    depth_compare: wgpu::CompareFunction,
+    shadow_pipeline: Arc<wgpu::RenderPipeline>,
+    shadow_passes: Vec<(wgpu::Buffer, wgpu::BindGroup)>, // view projection per layer
+    shadow_count: usize, // layers rendered this frame
+    frame: wgpu::Buffer,
+    light_buffer: wgpu::Buffer,
+    light_capacity: usize,
+    shadow_matrices: wgpu::Buffer,
+    frame_binding: wgpu::BindGroup,
+    environment_sampler: Arc<wgpu::Sampler>,
+    material_sampler: Arc<wgpu::Sampler>,
+    white: TextureLot,
+    flat_normal: TextureLot,
+    materials: Vec<MaterialEntry>,
+}
+
+fn pbr_pipelines(
+    gx: &impl WgxDevice, shader: &wgpu::ShaderModule, layout: &wgpu::PipelineLayout,
+    target: TargetKey, depth_compare: wgpu::CompareFunction,
+) -> [Arc<wgpu::RenderPipeline>; 4] {
+
+    let schema = vertex_schema();
+    let mut buffers = schema.buffer_layouts();
+    buffers.push(PbrInstance::LAYOUT);
+
+    let pipeline = |blended: bool, double_sided: bool| {
+        let depth_stencil = target.depth_testing.map(|format| wgpu::DepthStencilState {
+            depth_write_enabled: !blended, depth_compare, ..depth_state(format)
+        });
+        let primitive = Primitive { cull_mode: (!double_sided).then_some(Face::Back), ..Primitive::default() };
+        let blend = blended.then_some(Blend::ALPHA_BLENDING);
+
+        gx.shared_render_pipeline(
+            target.msaa, depth_stencil, Some(layout), &buffers,
+            (shader, "vs_main", primitive),
+            Some((shader, "fs_main", &[(target.format, blend)])),
+        )
+    };
+
+    [pipeline(false, false), pipeline(false, true), pipeline(true, false), pipeline(true, true)]
+}
+
+fn light_buffer(gx: &impl WgxDevice, capacity: usize) -> wgpu::Buffer {
+    gx.buffer(BufUse::STORAGE | BufUse::COPY_DST, (capacity * size_of::<LightData>()) as u64, false)
+}
+
+impl PbrRenderer {
+
+    pub fn new(gx: &impl WgxDeviceQueue, target: &impl RenderTarget, environment: Environment, shadow_size: u32, shadow_layers: u32) -> Res<Self> {
+        let code = wgsl_modules::include!("wgx_pbr::forward.wgsl");
+        Self::with_shader(gx, target, code, environment, shadow_size, shadow_layers)
+    }
+
+    // custom shader with the entry points vs_main and fs_main on the same bindings and vertex layout
+    pub fn with_shader(
+        gx: &impl WgxDeviceQueue, target: &impl RenderTarget, code: &str,
+        environment: Environment, shadow_size: u32, shadow_layers: u32,
+    ) -> Res<Self> {
+
+        let mut shadows = DepthTarget::new(gx, [shadow_size, shadow_size], DEFAULT_DEPTH, shadow_layers.max(1));
+
+        // sampled as array, also with a single layer
+        shadows.lot.descriptor.view_dimension = TextureViewDimension::D2Array;
+        shadows.lot.update_view();
+
+        let [shadow_texture, shadow_sampler] = shadows.layout_entries(3, 4, Stage::FRAGMENT);
+
+        let frame_layout = gx.layout_dsc(&[
+            binding!(0, Stage::VERTEX_FRAGMENT, UniformBuffer, size_of::<FrameData>() as u64),
+            binding!(1, Stage::FRAGMENT, StorageBuffer, size_of::<LightData>() as u64, true),
+            binding!(2, Stage::FRAGMENT, StorageBuffer, 64, true),
+            shadow_texture, shadow_sampler,
+            binding!(5, Stage::FRAGMENT, Texture, Cube),
+            binding!(6, Stage::FRAGMENT, Texture, Cube),
+            binding!(7, Stage::FRAGMENT, Texture, D2),
+            binding!(8, Stage::FRAGMENT, Sampler),
+        ]);
+
+        let material_layout = gx.layout_dsc(&[
+            binding!(0, Stage::FRAGMENT, UniformBuffer, size_of::<MaterialParams>() as u64),
+            binding!(1, Stage::FRAGMENT, Texture, D2),
+            binding!(2, Stage::FRAGMENT, Texture, D2),
+            binding!(3, Stage::FRAGMENT, Texture, D2),
+            binding!(4, Stage::FRAGMENT, Texture, D2),
+            binding!(5, Stage::FRAGMENT, Texture, D2),
+            binding!(6, Stage::FRAGMENT, Sampler),
+        ]);
+
+        let pipeline_layout = gx.shared_pipeline_layout(&[], &[&frame_layout.layout, &material_layout.layout]);
+
+        let schema = vertex_schema();
+        let mut buffers = schema.buffer_layouts();
+        buffers.push(PbrInstance::LAYOUT);
+
+        let shader = gx.shared_wgsl(code);
+        let target = TargetKey::of(target);
+        let depth_compare = wgpu::CompareFunction::LessEqual;
+        let pipelines = pbr_pipelines(gx, &shader, &pipeline_layout, target, depth_compare);
+
+        // shadow casters
+        let shadow_module = gx.shared_wgsl(wgsl_modules::include!("wgx_pbr::shadow.wgsl"));
+        let shadow_layout = gx.layout_dsc(&[binding!(0, Stage::VERTEX, UniformBuffer, 64)]);
+
+        let shadow_pipeline = shadows.render_pipeline(
+            gx, Some((&[], &[&shadow_layout.layout])), &buffers,
+            (&shadow_module, "vs_main", Primitive::default()), None,
+            DepthBiasState { constant: 2, slope_scale: 2.0, clamp: 0.0 },
+        );
+
+        let shadow_passes = (0..shadows.layers()).map(|_| {
+            let buffer = gx.buffer(BufUse::UNIFORM | BufUse::COPY_DST, 64, false);
+            let binding = shadow_layout.bind(gx).buffer(0, &buffer).finish()?;
+            Ok((buffer, binding))
+        }).collect::<Res<_>>()?;
+
+        let frame = gx.buffer(BufUse::UNIFORM | BufUse::COPY_DST, size_of::<FrameData>() as u64, false);
+        let light_capacity = 16;
+        let light_buffer = light_buffer(gx, light_capacity);
+        let shadow_matrices = gx.buffer(BufUse::STORAGE | BufUse::COPY_DST, shadows.layers() as u64 * 64, false);
+        let environment_sampler = gx.shared_sampler(&SamplerDsc::TRILINEAR);
+
+        let frame_binding = Self::bind_frame_group(
+            gx, &frame_layout, &frame, &light_buffer, &shadow_matrices, &shadows, &environment, &environment_sampler,
+        )?;
+
+        let texture = |data: [u8; 4]| TextureLot::new_2d_with_data(gx, [1, 1, 1], 1, DEFAULT_LINEAR, None, TexUse::TEXTURE_BINDING, data);
+
+        Ok(Self {
+            lights: Vec::new(), shadow_bounds: BoundingSphere { center: Vec3::ZERO, radius: 10.0 }, environment_intensity: 1.0,
+            frame_layout, material_layout, pipeline_layout, shadows, environment,
+            pipelines, shader, target, depth_compare, shadow_pipeline, shadow_passes, shadow_count: 0,
+            frame, light_buffer, light_capacity, shadow_matrices, frame_binding,
+            environment_sampler,
+            material_sampler: gx.shared_sampler(&SamplerDsc::TRILINEAR.repeat()),
+            white: texture([255; 4]), flat_normal: texture([128, 128, 255, 255]),
+            materials: Vec::new(),
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn bind_frame_group(
+        gx: &impl WgxDevice, layout: &BindGroupLayoutDsc, frame: &wgpu::Buffer, lights: &wgpu::Buffer, shadow_matrices: &wgpu::Buffer,
+        shadows: &DepthTarget, environment: &Environment, sampler: &wgpu::Sampler,
+    ) -> Res<wgpu::BindGroup> {
+        let builder = layout.bind(gx)
+            .buffer(0, frame)
+            .buffer(1, lights)
+            .buffer(2, shadow_matrices);
+
+        shadows.bind(builder, 3, 4)
+            .texture_lot(5, &environment.irradiance)
+            .texture_lot(6, &environment.prefiltered)
+            .texture_lot(7, &environment.brdf_lut)
+            .sampler(8, sampler)
+            .finish()
+    }
+
+    // follow the render target, e.g. after SurfaceTarget::set_msaa
+    pub fn update(&mut self, gx: &impl WgxDevice, target: &impl RenderTarget) {
+        let target = TargetKey::of(target);
+
+        if target != self.target {
+            self.target = target;
+            self.pipelines = pbr_pipelines(gx, &self.shader, &self.pipeline_layout, target, self.depth_compare);
+        }
+    }
+
+    // depth test of the camera projection, GreaterEqual for reversed z, shadow maps keep their own
+    pub fn set_projection(&mut self, gx: &impl WgxDevice, projection: &Projection) {
+        let depth_compare = projection.depth_compare();
+
+        if depth_compare != self.depth_compare {
+            self.depth_compare = depth_compare;
+            self.pipelines = pbr_pipelines(gx, &self.shader, &self.pipeline_layout, self.target, depth_compare);
+        }
+    }
+
+    fn rebind_frame(&mut self, gx: &impl WgxDevice) -> Res<()> {
+        self.frame_binding = Self::bind_frame_group(
+            gx, &self.frame_layout, &self.frame, &self.light_buffer, &self.shadow_matrices,
+            &self.shadows, &self.environment, &self.environment_sampler,
+        )?;
+        Ok(())
+    }
+
+    pub fn environment(&self) -> &Environment { &self.environment }
+
+    pub fn set_environment(&mut self, gx: &impl WgxDevice, environment: Environment) -> Res<()> {
+        self.environment = environment;
+        self.rebind_frame(gx)
+    }
+
+
+    // materials
+
+    pub fn add_material(&mut self, gx: &impl WgxDevice, material: &PbrMaterial) -> Res<MaterialId> {
+        let params = gx.buffer_from_data(BufUse::UNIFORM | BufUse::COPY_DST, material.params());
+
+        let binding = self.material_layout.bind(gx)
+            .buffer(0, &params)
+            .texture(1, material.base_color_texture.unwrap_or(&self.white.view))
+            .texture(2, material.metallic_roughness_texture.unwrap_or(&self.white.view))
+            .texture(3, material.normal_texture.unwrap_or(&self.flat_normal.view))
+            .texture(4, material.occlusion_texture.unwrap_or(&self.white.view))
+            .texture(5, material.emissive_texture.unwrap_or(&self.white.view))
+            .sampler(6, material.sampler.unwrap_or(&self.material_sampler))
+            .finish()?;
+
+        self.materials.push(MaterialEntry { binding, params, pipeline: material.pipeline() });
+        Ok(MaterialId(self.materials.len() - 1))
+    }
+
+    // updates the factors, textures, alpha mode and double sidedness stay as added
+    pub fn write_material(&self, gx: &impl WgxQueue, id: MaterialId, material: &PbrMaterial) -> Res<()> {
+        let entry = self.materials.get(id.0).context("unknown material")?;
+        gx.write_buffer(&entry.params, 0, material.params());
+        Ok(())
+    }
+
+
+    // frame
+
+    // uploads the frame, lights and shadow matrices, shadow layers go to the first shadow casting
+    // directional and spot lights, buffers are written when the queue is submitted, so prepare only once per submit
+    pub fn prepare(&mut self, gx: &impl WgxDeviceQueue, view_projection: Mat4, camera_position: Vec3) -> Res<()> {
+
+        let layers = self.shadows.layers() as usize;
+        let mut matrices = Vec::with_capacity(layers);
+
+        let lights: Vec<LightData> = self.lights.iter().map(|light| {
+            let layer = match light.shadow_matrix(&self.shadow_bounds) {
+                Some(matrix) if light.shadow && matrices.len() < layers => {
+                    matrices.push(matrix);
+                    matrices.len() as i32 - 1
+                },
+                _ => -1,
+            };
+            light.data(layer)
+        }).collect();
+
+        if lights.len() > self.light_capacity {
+            self.light_capacity = lights.len().next_power_of_two();
+            self.light_buffer = light_buffer(gx, self.light_capacity);
+            self.rebind_frame(gx)?;
+        }
+        if !lights.is_empty() {
+            gx.write_buffer(&self.light_buffer, 0, lights.as_slice());
+        }
+        if !matrices.is_empty() {
+            gx.write_buffer(&self.shadow_matrices, 0, matrices.as_slice());
+        }
+        for ((buffer, _), matrix) in self.shadow_passes.iter().zip(&matrices) {
+            gx.write_buffer(buffer, 0, matrix);
+        }
+        self.shadow_count = matrices.len();
+
+        gx.write_buffer(&self.frame, 0, FrameData {
+            view_projection, camera_position, light_count: lights.len() as u32,
+            environment_intensity: self.environment_intensity,
+            prefiltered_mips: self.environment.prefiltered_mips() as f32,
+            shadow_texel: 1.0 / self.shadows.size()[0] as f32, _pad: 0.0,
+        });
+
+        Ok(())
+    }
+
+    // one depth pass per shadow layer of this frame, draw all shadow casters with draw_shadow_caster
+    pub fn render_shadows(&self, encoder: &mut wgpu::CommandEncoder, mut draw: impl FnMut(&mut wgpu::RenderPass)) {
+        for (layer, (_, binding)) in self.shadow_passes[..self.shadow_count].iter().enumerate() {
+            let mut rpass = encoder.render_pass(self.shadows.attachments(layer as u32, Some(1.0)));
+            rpass.set_pipeline(&self.shadow_pipeline);
+            rpass.set_bind_group(0, binding, &[]);
+            draw(&mut rpass);
+        }
+    }
+
+    // depth only, alpha masked and blended materials cast the shadow of the whole mesh
+    pub fn draw_shadow_caster(&self, rpass: &mut wgpu::RenderPass, mesh: &Mesh, instances: &wgpu::Buffer, range: Range<u32>) {
+        rpass.set_vertex_buffer(mesh.schema.slots(), instances.slice(..));
+        mesh.draw_instanced(rpass, range);
+    }
+
+
+    // drawing meshes uploaded with vertex_schema and instance buffers of PbrInstance
+
+    // call once per render pass before drawing
+    pub fn bind_frame(&self, rpass: &mut wgpu::RenderPass) {
+        rpass.set_bind_group(0, &self.frame_binding, &[]);
+    }
+
+    fn set_material(&self, rpass: &mut wgpu::RenderPass, mesh: &Mesh, material: MaterialId, instances: &wgpu::Buffer) -> Res<()> {
+        let entry = self.materials.get(material.0).context("unknown material")?;
+        rpass.set_pipeline(&self.pipelines[entry.pipeline]);
+        rpass.set_bind_group(1, &entry.binding, &[]);
+        rpass.set_vertex_buffer(mesh.schema.slots(), instances.slice(..));
+        Ok(())
+    }
+
+    pub fn draw(
+        &self, rpass: &mut wgpu::RenderPass, mesh: &Mesh, material: MaterialId, instances: &wgpu::Buffer, range: Range<u32>,
+    ) -> Res<()> {
+        self.set_material(rpass, mesh, material, instances)?;
+        mesh.draw_instanced(rpass, range);
+        Ok(())
+    }
+
+    pub fn draw_submesh(
+        &self, rpass: &mut wgpu::RenderPass, mesh: &Mesh, submesh: usize,
+        material: MaterialId, instances: &wgpu::Buffer, range: Range<u32>,
+    ) -> Res<()> {
+        self.set_material(rpass, mesh, material, instances)?;
+        mesh.draw_submesh(rpass, submesh, range)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem::offset_of;
+
+    // offsets and sizes by the wgsl layout rules of wgx_pbr::bindings.wgsl
+
+    #[test]
+    fn frame_layout() {
+        assert_eq!(size_of::<FrameData>(), 96);
+        assert_eq!(offset_of!(FrameData, camera_position), 64);
+        assert_eq!(offset_of!(FrameData, light_count), 76);
+        assert_eq!(offset_of!(FrameData, environment_intensity), 80);
+        assert_eq!(offset_of!(FrameData, prefiltered_mips), 84);
+        assert_eq!(offset_of!(FrameData, shadow_texel), 88);
+    }
+
+    #[test]
+    fn light_layout() {
+        assert_eq!(size_of::<LightData>(), 64);
+        assert_eq!(offset_of!(LightData, range), 12);
+        assert_eq!(offset_of!(LightData, direction), 16);
+        assert_eq!(offset_of!(LightData, kind), 28);
+        assert_eq!(offset_of!(LightData, color), 32);
+        assert_eq!(offset_of!(LightData, intensity), 44);
+        assert_eq!(offset_of!(LightData, cone), 48);
+        assert_eq!(offset_of!(LightData, shadow), 56);
+        assert_eq!(offset_of!(LightData, shadow_bias), 60);
+    }
+
+    #[test]
+    fn material_layout() {
+        assert_eq!(size_of::<MaterialParams>(), 48);
+        assert_eq!(offset_of!(MaterialParams, emissive), 16);
+        assert_eq!(offset_of!(MaterialParams, metallic), 28);
+        assert_eq!(offset_of!(MaterialParams, roughness), 32);
+        assert_eq!(offset_of!(MaterialParams, normal_scale), 36);
+        assert_eq!(offset_of!(MaterialParams, occlusion_strength), 40);
+        assert_eq!(offset_of!(MaterialParams, alpha_cutoff), 44);
+    }
+
+    fn inside_clip(matrix: Mat4, point: Vec3) -> bool {
+        let clip = matrix.project_point3(point);
+        clip.x.abs() <= 1.0 + 1e-4 && clip.y.abs() <= 1.0 + 1e-4 && (-1e-4..=1.0 + 1e-4).contains(&clip.z)
+    }
+
+    fn sphere_points(bounds: &BoundingSphere) -> Vec<Vec3> {
+        [Vec3::X, Vec3::NEG_X, Vec3::Y, Vec3::NEG_Y, Vec3::Z, Vec3::NEG_Z, Vec3::ONE.normalize(), Vec3::ZERO]
+            .into_iter().map(|offset| bounds.center + offset * bounds.radius).collect()
+    }
+
+    #[test]
+    fn directional_shadow_contains_bounds() {
+        let bounds = BoundingSphere { center: Vec3::new(1.0, 2.0, -3.0), radius: 5.0 };
+
+        for direction in [Vec3::NEG_Y, Vec3::new(1.0, -1.0, 0.5), Vec3::Y] {
+            let matrix = Light::directional(direction, Color::WHITE, 1.0).shadow_matrix(&bounds).unwrap();
+            for point in sphere_points(&bounds) {
+                assert!(inside_clip(matrix, point), "{point} outside for {direction}");
+            }
+        }
+    }
+
+    #[test]
+    fn spot_shadow_contains_cone() {
+        let bounds = BoundingSphere { center: Vec3::ZERO, radius: 2.0 };
+        let light = Light::spot([0.0, 5.0, 0.0], [0.0, -1.0, 0.0], 0.3, 0.5, Color::WHITE, 1.0);
+        let matrix = light.shadow_matrix(&bounds).unwrap();
+
+        // inside the outer angle and in front of the far plane
+        for point in [Vec3::ZERO, Vec3::new(0.0, -1.9, 0.0), Vec3::new(5.0 * 0.45f32.tan(), 0.0, 0.0), Vec3::new(0.0, 4.0, 0.0)] {
+            assert!(inside_clip(matrix, point), "{point} outside");
+        }
+        assert!(!inside_clip(matrix, Vec3::new(4.0, 0.0, 0.0)));
+        assert!(!inside_clip(matrix, Vec3::new(0.0, 6.0, 0.0)));
+    }
+
+    #[test]
+    fn point_light_has_no_shadow() {
+        let bounds = BoundingSphere { center: Vec3::ZERO, radius: 1.0 };
+        assert_eq!(Light::point([0.0, 1.0, 0.0], Color::WHITE, 1.0).shadow(true).shadow_matrix(&bounds), None);
+    }
+}
@@ -0,0 +1,463 @@
+
+// stanford ply import and export, ascii and binary with arbitrary elements and properties
+// like obj, z is negated into the left handed system of wgx and back when exported
+
+use std::path::Path;
+use anyhow::{Result as Res, Context, bail, ensure};
+use crate::{*, math::*, mesh::*};
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PlyFormat { Ascii, BinaryLittleEndian, BinaryBigEndian }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PlyType { I8, U8, I16, U16, I32, U32, F32, F64 }
+
+impl PlyType {
+
+    pub fn parse(name: &str) -> Res<Self> {
+        Ok(match name {
+            "char" | "int8" => Self::I8,
+            "uchar" | "uint8" => Self::U8,
+            "short" | "int16" => Self::I16,
+            "ushort" | "uint16" => Self::U16,
+            "int" | "int32" => Self::I32,
+            "uint" | "uint32" => Self::U32,
+            "float" | "float32" => Self::F32,
+            "double" | "float64" => Self::F64,
+            _ => bail!("unknown ply type {name:?}"),
+        })
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::I8 => "char", Self::U8 => "uchar", Self::I16 => "short", Self::U16 => "ushort",
+            Self::I32 => "int", Self::U32 => "uint", Self::F32 => "float", Self::F64 => "double",
+        }
+    }
+
+    pub const fn size(self) -> usize {
+        match self {
+            Self::I8 | Self::U8 => 1,
+            Self::I16 | Self::U16 => 2,
+            Self::I32 | Self::U32 | Self::F32 => 4,
+            Self::F64 => 8,
+        }
+    }
+}
+
+
+// all values are widened to f64, which holds every ply type exactly
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlyValues {
+    Scalar(Vec<f64>),
+    List(Vec<Vec<f64>>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlyProperty {
+    pub name: String,
+    pub data_type: PlyType,
+    pub count_type: Option<PlyType>, // for lists
+    pub values: PlyValues,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlyElement {
+    pub name: String,
+    pub count: usize,
+    pub properties: Vec<PlyProperty>,
+}
+
+impl PlyElement {
+
+    pub fn property(&self, name: &str) -> Option<&PlyProperty> {
+        self.properties.iter().find(|property| property.name == name)
+    }
+
+    pub fn scalar(&self, name: &str) -> Option<&[f64]> {
+        match &self.property(name)?.values { PlyValues::Scalar(values) => Some(values), _ => None }
+    }
+
+    pub fn list(&self, name: &str) -> Option<&[Vec<f64>]> {
+        match &self.property(name)?.values { PlyValues::List(values) => Some(values), _ => None }
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlyData {
+    pub format: PlyFormat,
+    pub comments: Vec<String>,
+    pub elements: Vec<PlyElement>,
+}
+
+
+enum Body<'a> {
+    Ascii(std::str::SplitAsciiWhitespace<'a>),
+    Binary(&'a [u8], bool), // big endian
+}
+
+impl Body<'_> {
+    fn read(&mut self, data_type: PlyType) -> Res<f64> {
+        match self {
+            Self::Ascii(tokens) => {
+                let token = tokens.next().context("unexpected end of ply data")?;
+                token.parse().with_context(|| format!("invalid ply value {token:?}"))
+            },
+            Self::Binary(data, big_endian) => {
+                let size = data_type.size();
+                ensure!(data.len() >= size, "unexpected end of ply data");
+                let (bytes, rest) = data.split_at(size);
+                *data = rest;
+
+                macro_rules! number { ($type:ty) => {{
+                    let bytes = bytes.try_into().unwrap();
+                    (if *big_endian { <$type>::from_be_bytes(bytes) } else { <$type>::from_le_bytes(bytes) }) as f64
+                }}}
+
+                Ok(match data_type {
+                    PlyType::I8 => number!(i8), PlyType::U8 => number!(u8),
+                    PlyType::I16 => number!(i16), PlyType::U16 => number!(u16),
+                    PlyType::I32 => number!(i32), PlyType::U32 => number!(u32),
+                    PlyType::F32 => number!(f32), PlyType::F64 => number!(f64),
+                })
+            },
+        }
+    }
+}
+
+
+impl PlyData {
+
+    pub fn load(path: impl AsRef<Path>) -> Res<Self> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+        Self::parse(&bytes)
+    }
+
+    pub fn parse(bytes: &[u8]) -> Res<Self> {
+
+        // header, up to the first line that is only end_header
+        let mut line_start = 0;
+        let (end, body_start) = loop {
+            ensure!(line_start < bytes.len(), "ply header without end_header");
+            let line_end = bytes[line_start..].iter().position(|&b| b == b'\n').map_or(bytes.len(), |i| line_start + i);
+            if bytes[line_start..line_end].trim_ascii() == b"end_header" { break (line_start, line_end + 1) }
+            line_start = line_end + 1;
+        };
+        let body_start = body_start.min(bytes.len());
+
+        let header = std::str::from_utf8(&bytes[..end]).context("ply header is no valid utf-8")?;
+        let mut lines = header.lines().map(str::trim);
+
+        ensure!(lines.next() == Some("ply"), "not a ply file");
+
+        let mut format = None;
+        let mut comments = Vec::new();
+        let mut elements: Vec<PlyElement> = Vec::new();
+
+        for line in lines {
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            match tokens.as_slice() {
+                ["format", name, _version] => format = Some(match *name {
+                    "ascii" => PlyFormat::Ascii,
+                    "binary_little_endian" => PlyFormat::BinaryLittleEndian,
+                    "binary_big_endian" => PlyFormat::BinaryBigEndian,
+                    _ => bail!("unknown ply format {name:?}"),
+                }),
+                ["comment" | "obj_info", ..] => comments.push(line.split_once(' ').map_or("", |(_, comment)| comment).to_string()),
+                ["element", name, count] => elements.push(PlyElement {
+                    name: name.to_string(),
+                    count: count.parse().with_context(|| format!("invalid ply element count {count:?}"))?,
+                    properties: Vec::new(),
+                }),
+                ["property", "list", count_type, data_type, name] => {
+                    elements.last_mut().context("ply property before any element")?.properties.push(PlyProperty {
+                        name: name.to_string(), data_type: PlyType::parse(data_type)?,
+                        count_type: Some(PlyType::parse(count_type)?), values: PlyValues::List(Vec::new()),
+                    });
+                },
+                ["property", data_type, name] => {
+                    elements.last_mut().context("ply property before any element")?.properties.push(PlyProperty {
+                        name: name.to_string(), data_type: PlyType::parse(data_type)?,
+                        count_type: None, values: PlyValues::Scalar(Vec::new()),
+                    });
+                },
+                [] => {},
+                _ => bail!("invalid ply header line {line:?}"),
+            }
+        }
+
+        let format = format.context("ply header without format")?;
+
+        // body, element by element and row by row
+        let data = &bytes[body_start..];
+        let mut body = match format {
+            PlyFormat::Ascii => Body::Ascii(std::str::from_utf8(data).context("ascii ply data is no valid utf-8")?.split_ascii_whitespace()),
+            PlyFormat::BinaryLittleEndian => Body::Binary(data, false),
+            PlyFormat::BinaryBigEndian => Body::Binary(data, true),
+        };
+
+        for element in &mut elements {
+            for _ in 0..element.count {
+                for property in &mut element.properties {
+                    match (&mut property.values, property.count_type) {
+                        (PlyValues::Scalar(values), _) => values.push(body.read(property.data_type)?),
+                        (PlyValues::List(lists), Some(count_type)) => {
+                            let count = body.read(count_type)? as usize;
+                            let list = (0..count).map(|_| body.read(property.data_type)).collect::<Res<_>>()?;
+                            lists.push(list);
+                        },
+                        (PlyValues::List(_), None) => unreachable!(),
+                    }
+                }
+            }
+        }
+
+        Ok(Self { format, comments, elements })
+    }
+
+    pub fn element(&self, name: &str) -> Option<&PlyElement> {
+        self.elements.iter().find(|element| element.name == name)
+    }
+
+
+    // positions, normals, uvs and colors of the vertex element, faces are triangulated,
+    // point clouds without faces have no indices
+    pub fn mesh(&self) -> Res<MeshData> {
+        let vertex = self.element("vertex").context("ply data without vertex element")?;
+        let len = vertex.count;
+
+        let vec3 = |[x, y, z]: [&str; 3]| -> Option<Vec<Vec3>> {
+            let [x, y, z] = [vertex.scalar(x)?, vertex.scalar(y)?, vertex.scalar(z)?];
+            Some((0..len).map(|i| Vec3::new(x[i] as f32, y[i] as f32, z[i] as f32)).collect())
+        };
+
+        let positions = vec3(["x", "y", "z"]).context("ply vertices without positions")?;
+        let normals = vec3(["nx", "ny", "nz"]).unwrap_or_default();
+
+        let uvs = [["u", "v"], ["s", "t"], ["texture_u", "texture_v"]].iter().find_map(|&[u, v]| {
+            let [u, v] = [vertex.scalar(u)?, vertex.scalar(v)?];
+            Some((0..len).map(|i| Vec2::new(u[i] as f32, 1.0 - v[i] as f32)).collect())
+        }).unwrap_or_default();
+
+        // integer colors are srgb, float colors are taken as they are
+        let colors = ["red", "green", "blue"].map(|name| vertex.property(name));
+
+        let colors = if let [Some(r), Some(g), Some(b)] = colors {
+            let alpha = vertex.property("alpha");
+            let channel = |property: &PlyProperty, i: usize| match &property.values {
+                PlyValues::Scalar(values) => match property.data_type {
+                    PlyType::U8 => values[i] as f32 / 255.0,
+                    PlyType::U16 => values[i] as f32 / 65535.0,
+                    _ => values[i] as f32,
+                },
+                PlyValues::List(_) => 1.0,
+            };
+            (0..len).map(|i| {
+                let color = Color::new(channel(r, i), channel(g, i), channel(b, i), alpha.map_or(1.0, |a| channel(a, i)));
+                let color = if r.data_type == PlyType::F32 || r.data_type == PlyType::F64 { color } else { color.linear() };
+                Vec4::from(color.f32())
+            }).collect()
+        }
+        else { Vec::new() };
+
+        let mut mesh = MeshData { positions, normals, uvs, colors, ..MeshData::default() };
+
+        let faces = self.element("face").and_then(|face| face.list("vertex_indices").or_else(|| face.list("vertex_index")));
+
+        if let Some(faces) = faces {
+            let mut indices = Vec::new();
+
+            for face in faces {
+                if let Some(i) = face.iter().find(|&&i| i < 0.0 || i.fract() != 0.0 || i >= len as f64) {
+                    bail!("ply face index {i} out of range of {len} vertices");
+                }
+                let polygon: Vec<u32> = face.iter().map(|&i| i as u32).collect();
+                if polygon.len() >= 3 {
+                    for triangle in triangulate_polygon(&mesh.positions, &polygon) { indices.extend(triangle) }
+                }
+            }
+
+            mesh.indices = Some(indices);
+        }
+
+        mesh.flip_z();
+        if mesh.normals.is_empty() && mesh.indices.is_some() { mesh.compute_normals() }
+
+        Ok(mesh)
+    }
+}
+
+
+// export of positions, normals, uvs, colors and triangles
+
+pub fn write(mesh: &MeshData, format: PlyFormat) -> Vec<u8> {
+    let mut mesh = mesh.clone();
+    mesh.flip_z();
+
+    let triangles = mesh.triangles();
+    let (has_normals, has_uvs, has_colors) = (!mesh.normals.is_empty(), !mesh.uvs.is_empty(), !mesh.colors.is_empty());
+
+    let mut header = format!("ply\nformat {} 1.0\ncomment wgx mesh\nelement vertex {}\n", match format {
+        PlyFormat::Ascii => "ascii",
+        PlyFormat::BinaryLittleEndian => "binary_little_endian",
+        PlyFormat::BinaryBigEndian => "binary_big_endian",
+    }, mesh.vertex_count());
+
+    let mut properties = vec!["x", "y", "z"];
+    if has_normals { properties.extend(["nx", "ny", "nz"]) }
+    if has_uvs { properties.extend(["u", "v"]) }
+    for name in &properties { header += &format!("property float {name}\n") }
+    if has_colors { for name in ["red", "green", "blue", "alpha"] { header += &format!("property uchar {name}\n") } }
+
+    header += &format!("element face {}\nproperty list uchar uint vertex_indices\nend_header\n", triangles.len());
+
+    let mut out = header.into_bytes();
+
+    // vertex rows as floats and color bytes
+    let rows = (0..mesh.vertex_count()).map(|i| {
+        let mut floats = mesh.positions[i].to_array().to_vec();
+        if has_normals { floats.extend(mesh.normals[i].to_array()) }
+        if has_uvs { floats.extend([mesh.uvs[i].x, 1.0 - mesh.uvs[i].y]) }
+        let color = has_colors.then(|| {
+            Color::from_f32(mesh.colors[i].to_array()).srgb().f32().map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+        });
+        (floats, color)
+    });
+
+    match format {
+        PlyFormat::Ascii => {
+            let mut text = String::new();
+            for (floats, color) in rows {
+                let mut values: Vec<String> = floats.iter().map(f32::to_string).collect();
+                values.extend(color.iter().flatten().map(u8::to_string));
+                text += &(values.join(" ") + "\n");
+            }
+            for [a, b, c] in &triangles { text += &format!("3 {a} {b} {c}\n") }
+            out.extend(text.into_bytes());
+        },
+        PlyFormat::BinaryLittleEndian | PlyFormat::BinaryBigEndian => {
+            let big_endian = format == PlyFormat::BinaryBigEndian;
+            let u32_bytes = |value: u32| if big_endian { value.to_be_bytes() } else { value.to_le_bytes() };
+
+            for (floats, color) in rows {
+                for value in floats { out.extend(if big_endian { value.to_be_bytes() } else { value.to_le_bytes() }) }
+                out.extend(color.iter().flatten());
+            }
+            for triangle in &triangles {
+                out.push(3);
+                for &i in triangle { out.extend(u32_bytes(i)) }
+            }
+        },
+    }
+
+    out
+}
+
+pub fn save(path: impl AsRef<Path>, mesh: &MeshData, format: PlyFormat) -> Res<()> {
+    let path = path.as_ref();
+    std::fs::write(path, write(mesh, format)).with_context(|| format!("writing {}", path.display()))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUAD: &str = "ply
+format ascii 1.0
+comment the header ends after end_header
+element vertex 4
+property float x
+property float y
+property float z
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0
+1 0 0
+1 1 1
+0 1 1
+4 0 1 2 3
+";
+
+    fn triangle() -> MeshData {
+        MeshData {
+            positions: vec![Vec3::new(0.0, 0.0, 1.0), Vec3::new(1.0, 0.0, 1.0), Vec3::new(0.0, 1.0, 2.0)],
+            normals: vec![Vec3::Z; 3],
+            uvs: vec![Vec2::new(0.0, 0.25), Vec2::new(1.0, 0.5), Vec2::new(0.5, 1.0)],
+            indices: Some(vec![0, 1, 2]),
+            ..MeshData::default()
+        }
+    }
+
+    #[test]
+    fn parses_ascii_with_comments() {
+        let ply = PlyData::parse(QUAD.as_bytes()).unwrap();
+        assert_eq!(ply.format, PlyFormat::Ascii);
+        assert_eq!(ply.comments, ["the header ends after end_header"]);
+
+        let mesh = ply.mesh().unwrap();
+        assert_eq!(mesh.positions[2], Vec3::new(1.0, 1.0, -1.0));
+
+        // the quad is split into two triangles
+        let mut indices = mesh.indices.unwrap();
+        assert_eq!(indices.len(), 6);
+        indices.sort();
+        indices.dedup();
+        assert_eq!(indices, [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn round_trips_all_formats() {
+        let mesh = triangle();
+
+        for format in [PlyFormat::Ascii, PlyFormat::BinaryLittleEndian, PlyFormat::BinaryBigEndian] {
+            let ply = PlyData::parse(&write(&mesh, format)).unwrap();
+            assert_eq!(ply.format, format);
+
+            let parsed = ply.mesh().unwrap();
+            assert_eq!(parsed.positions, mesh.positions);
+            assert_eq!(parsed.normals, mesh.normals);
+            assert_eq!(parsed.uvs, mesh.uvs);
+            assert_eq!(parsed.indices, mesh.indices);
+        }
+    }
+
+    #[test]
+    fn reads_big_endian_values() {
+        let mut bytes = b"ply\nformat binary_big_endian 1.0\nelement value 2\nproperty short a\nproperty uint b\nend_header\n".to_vec();
+        bytes.extend((-2i16).to_be_bytes());
+        bytes.extend(7u32.to_be_bytes());
+        bytes.extend(3i16.to_be_bytes());
+        bytes.extend(u32::MAX.to_be_bytes());
+
+        let ply = PlyData::parse(&bytes).unwrap();
+        let element = ply.element("value").unwrap();
+        assert_eq!(element.scalar("a").unwrap(), [-2.0, 3.0]);
+        assert_eq!(element.scalar("b").unwrap(), [7.0, u32::MAX as f64]);
+    }
+
+    #[test]
+    fn rejects_invalid_face_indices() {
+        for face in ["3 0 -1 2", "3 0 1 4", "3 0 1 1.5"] {
+            let source = QUAD.replace("4 0 1 2 3", face);
+            assert!(PlyData::parse(source.as_bytes()).unwrap().mesh().is_err(), "{face}");
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_input() {
+        let ascii = QUAD.replace("4 0 1 2 3\n", "4 0 1 2\n");
+        assert!(PlyData::parse(ascii.as_bytes()).is_err());
+
+        let binary = write(&triangle(), PlyFormat::BinaryLittleEndian);
+        assert!(PlyData::parse(&binary[..binary.len() - 1]).is_err());
+
+        let header = QUAD.split("end_header").next().unwrap();
+        assert!(PlyData::parse(header.as_bytes()).is_err());
+        assert!(PlyData::parse(b"").is_err());
+    }
+}
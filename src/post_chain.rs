@@ -0,0 +1,361 @@
+use std::{ops::Range, sync::{Arc, Mutex}};
+use wgpu::TextureFormat;
+use anyhow::{Result as Res, ensure};
+use crate::*;
+
+
+// post effects are fragment shaders with the bindings from shaders/post/bindings.wgsl,
+// taking the uv at location 0, optionally with uniforms at binding 3 and a texture at binding 4
+
+#[derive(Debug)]
+pub struct PostEffectDsc<'a> {
+    pub code: &'a str,
+    pub entry_point: &'a str,
+    pub uniform_size: u64, // 0 without uniforms
+    pub texture: Option<TextureLot>,
+}
+
+impl<'a> PostEffectDsc<'a> {
+    pub fn new(code: &'a str, entry_point: &'a str, uniform_size: u64) -> Self {
+        Self { code, entry_point, uniform_size, texture: None }
+    }
+    pub fn texture(self, texture: TextureLot) -> Self { Self { texture: Some(texture), ..self } }
+}
+
+
+// views and buffers a bind group was created for: input, scene, uniforms and texture
+type BindingKey = (
+    wgpu::Id<wgpu::TextureView>, wgpu::Id<wgpu::TextureView>, Option<wgpu::Id<wgpu::Buffer>>, Option<wgpu::Id<wgpu::TextureView>>,
+);
+
+#[derive(Debug)]
+pub struct PostEffect {
+    pub enabled: bool,
+    pub layout: BindGroupLayoutDsc,
+    pub uniforms: Option<wgpu::Buffer>,
+    pub texture: Option<TextureLot>,
+    module: Arc<wgpu::ShaderModule>,
+    entry_point: Box<str>,
+    pipeline_layout: Arc<wgpu::PipelineLayout>,
+    pipeline: Arc<wgpu::RenderPipeline>, // writing to the chains format
+    output_pipeline: Arc<wgpu::RenderPipeline>, // writing to the output target
+    binding: Mutex<Option<(BindingKey, wgpu::BindGroup)>>, // of the last render, recreated when an input changes
+}
+
+impl PostEffect {
+
+    pub fn write_uniforms<T: ReadBytes>(&self, gx: &impl WgxQueue, data: T) {
+        if let Some(uniforms) = &self.uniforms { gx.write_buffer(uniforms, 0, data) }
+    }
+
+    fn binding_key(&self, src: &wgpu::TextureView, scene: &wgpu::TextureView) -> BindingKey {
+        (
+            src.global_id(), scene.global_id(),
+            self.uniforms.as_ref().map(|uniforms| uniforms.global_id()),
+            self.texture.as_ref().map(|texture| texture.view.global_id()),
+        )
+    }
+
+    fn bind(
+        &self, gx: &impl WgxDevice, src: &wgpu::TextureView, scene: &wgpu::TextureView,
+        (sampler, sampler_dsc): (&wgpu::Sampler, &SamplerDsc),
+    ) -> Res<wgpu::BindGroup> {
+        let mut builder = self.layout.bind(gx)
+            .texture(0, src)
+            .sampler_dsc(1, sampler, sampler_dsc)
+            .texture(2, scene);
+
+        if let Some(uniforms) = &self.uniforms { builder = builder.buffer(3, uniforms) }
+        if let Some(texture) = &self.texture { builder = builder.texture_lot(4, texture) }
+
+        builder.finish()
+    }
+}
+
+
+// output target properties the output pipelines are built for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct OutputKey { format: TextureFormat, msaa: u32, generation: u64 }
+
+impl OutputKey {
+    fn of(target: &impl RenderTarget) -> Self {
+        Self { format: target.view_format(), msaa: target.msaa(), generation: target.generation() }
+    }
+}
+
+
+// ordered full screen passes from a scene texture to an output target, using two ping-pong textures
+#[derive(Debug)]
+pub struct PostChain {
+    pub format: TextureFormat,
+    pub effects: Vec<PostEffect>,
+    sampler: (Arc<wgpu::Sampler>, &'static SamplerDsc),
+    vertex: Arc<wgpu::ShaderModule>,
+    copy: PostEffect, // used when no effect is enabled
+    lots: [TextureLot; 2],
+    output: OutputKey,
+}
+
+fn effect_pipeline(
+    gx: &impl WgxDevice, vertex: &wgpu::ShaderModule, layout: &wgpu::PipelineLayout,
+    module: &wgpu::ShaderModule, entry_point: &str, format: TextureFormat, msaa: u32,
+) -> Arc<wgpu::RenderPipeline> {
+    gx.shared_render_pipeline(
+        msaa, None, Some(layout), &[],
+        (vertex, "vs_main", Primitive::default()),
+        Some((module, entry_point, &[(format, None)])),
+    )
+}
+
+fn ping_pong_lot(gx: &impl WgxDevice, [w, h]: [u32; 2], format: TextureFormat) -> TextureLot {
+    TextureLot::new_2d(gx, [w, h, 1], 1, format, None, TexUse::RENDER_ATTACHMENT | TexUse::TEXTURE_BINDING)
+}
+
+impl PostChain {
+
+    // format of the intermediate textures, use DEFAULT_HDR for hdr scenes
+    pub fn new(gx: &impl WgxDevice, target: &impl RenderTarget, format: TextureFormat) -> Self {
+        let output = OutputKey::of(target);
+        let vertex = gx.shared_wgsl(wgsl_modules::include!("shaders/post/fullscreen.wgsl"));
+        let size = target.size();
+
+        let copy = Self::create_effect(
+            gx, &vertex, format, output,
+            PostEffectDsc::new(wgsl_modules::include!("shaders/post/copy.wgsl"), "fs_main", 0),
+        );
+
+        Self {
+            format, effects: Vec::new(),
+            sampler: (gx.shared_sampler(&SamplerDsc::LINEAR), &SamplerDsc::LINEAR),
+            vertex, copy,
+            lots: [ping_pong_lot(gx, size, format), ping_pong_lot(gx, size, format)],
+            output,
+        }
+    }
+
+    fn create_effect(
+        gx: &impl WgxDevice, vertex: &wgpu::ShaderModule, format: TextureFormat, output: OutputKey, dsc: PostEffectDsc,
+    ) -> PostEffect {
+        let mut entries = vec![
+            binding!(0, Stage::FRAGMENT, Texture, D2),
+            binding!(1, Stage::FRAGMENT, Sampler),
+            binding!(2, Stage::FRAGMENT, Texture, D2),
+        ];
+
+        if dsc.uniform_size != 0 {
+            entries.push(binding!(3, Stage::FRAGMENT, UniformBuffer, dsc.uniform_size));
+        }
+
+        if let Some(texture) = &dsc.texture {
+            let mut entry = binding!(4, Stage::FRAGMENT, Texture, D2);
+            if let wgpu::BindingType::Texture { view_dimension, .. } = &mut entry.ty {
+                *view_dimension = texture.descriptor.view_dimension;
+            }
+            entries.push(entry);
+        }
+
+        let layout = gx.layout_dsc(&entries);
+        let pipeline_layout = gx.shared_pipeline_layout(&[], &[&layout.layout]);
+        let module = gx.shared_wgsl(dsc.code);
+
+        PostEffect {
+            enabled: true,
+            uniforms: (dsc.uniform_size != 0).then(||
+                gx.buffer(BufUse::UNIFORM | BufUse::COPY_DST, dsc.uniform_size, false)
+            ),
+            texture: dsc.texture,
+            pipeline: effect_pipeline(gx, vertex, &pipeline_layout, &module, dsc.entry_point, format, 1),
+            output_pipeline: effect_pipeline(gx, vertex, &pipeline_layout, &module, dsc.entry_point, output.format, output.msaa),
+            layout, pipeline_layout, module,
+            entry_point: dsc.entry_point.into(),
+            binding: Mutex::default(),
+        }
+    }
+
+    // returns the index of the effect
+    pub fn add(&mut self, gx: &impl WgxDevice, dsc: PostEffectDsc) -> usize {
+        let effect = Self::create_effect(gx, &self.vertex, self.format, self.output, dsc);
+        self.effects.push(effect);
+        self.effects.len() - 1
+    }
+
+    // sampler of the effect inputs, linear by default
+    pub fn set_sampler(&mut self, gx: &impl WgxDevice, sampler_dsc: &'static SamplerDsc) {
+        self.sampler = (gx.shared_sampler(sampler_dsc), sampler_dsc);
+
+        for effect in self.effects.iter_mut().chain([&mut self.copy]) {
+            *effect.binding.get_mut().unwrap_or_else(|err| err.into_inner()) = None;
+        }
+    }
+
+    // follow the output target, e.g. after SurfaceTarget::update or set_msaa
+    pub fn update(&mut self, gx: &impl WgxDevice, target: &impl RenderTarget) {
+        let size = target.size();
+
+        if size != self.lots[0].descriptor.size_2d() {
+            self.lots = [ping_pong_lot(gx, size, self.format), ping_pong_lot(gx, size, self.format)];
+        }
+
+        let output = OutputKey::of(target);
+
+        if output != self.output {
+            self.output = output;
+            for effect in self.effects.iter_mut().chain([&mut self.copy]) {
+                effect.output_pipeline = effect_pipeline(
+                    gx, &self.vertex, &effect.pipeline_layout, &effect.module, &effect.entry_point, output.format, output.msaa,
+                );
+            }
+        }
+    }
+
+
+    // runs all enabled effects, the last one writes to the target
+    // bind groups are kept per effect until one of its inputs changes, e.g. the scene view after a resize
+    pub fn render(
+        &self, gx: &impl WgxDevice, encoder: &mut wgpu::CommandEncoder,
+        scene: &wgpu::TextureView, target: &impl RenderAttachable,
+    ) -> Res<()> {
+        ensure!(
+            OutputKey::of(target) == self.output && target.size() == self.lots[0].descriptor.size_2d(),
+            "post chain output doesn't match the target, call update",
+        );
+
+        let mut effects: Vec<&PostEffect> = self.effects.iter().filter(|effect| effect.enabled).collect();
+        if effects.is_empty() { effects.push(&self.copy) }
+
+        let last = effects.len() - 1;
+
+        for (i, effect) in effects.into_iter().enumerate() {
+
+            let src = if i == 0 { scene } else { &self.lots[(i - 1) % 2].view };
+            let key = effect.binding_key(src, scene);
+
+            let mut binding = effect.binding.lock().unwrap_or_else(|err| err.into_inner());
+
+            let bind_group = match &mut *binding {
+                Some((cached, bind_group)) if *cached == key => bind_group,
+                slot => &slot.insert((key, effect.bind(gx, src, scene, (&self.sampler.0, self.sampler.1))?)).1,
+            };
+
+            let (attachment, pipeline) = if i == last {
+                (target.color_attachment(None), &effect.output_pipeline)
+            } else {
+                (ColorAttachment::new(&self.lots[i % 2].view, None, None).into(), &effect.pipeline)
+            };
+
+            let mut rpass = encoder.render_pass(([Some(attachment)], None));
+            rpass.set_pipeline(pipeline);
+            rpass.set_bind_group(0, bind_group, &[]);
+            rpass.draw(0..3, 0..1);
+        }
+
+        Ok(())
+    }
+
+
+    // built-in effects
+
+    // extract, horizontal and vertical blur and composite, combines with the chains input, so add it first
+    pub fn add_bloom(&mut self, gx: &impl WgxDeviceQueue, bloom: &Bloom) -> Range<usize> {
+        let code = wgsl_modules::include!("shaders/post/bloom.wgsl");
+        let start = self.effects.len();
+
+        for entry_point in ["fs_extract", "fs_blur", "fs_blur", "fs_composite"] {
+            self.add(gx, PostEffectDsc::new(code, entry_point, 32));
+        }
+
+        self.write_bloom(gx, start..start+4, bloom);
+        start..start+4
+    }
+
+    pub fn write_bloom(&self, gx: &impl WgxQueue, range: Range<usize>, bloom: &Bloom) {
+        let directions = [[0.0, 0.0], [1.0, 0.0], [0.0, 1.0], [0.0, 0.0]];
+        for (effect, direction) in self.effects[range].iter().zip(directions) {
+            effect.write_uniforms(gx, bloom.params(direction));
+        }
+    }
+
+    // expects gamma encoded colors, add it after tonemapping and Gamma
+    pub fn add_fxaa(&mut self, gx: &impl WgxDeviceQueue, fxaa: &Fxaa) -> usize {
+        let index = self.add(gx, PostEffectDsc::new(wgsl_modules::include!("shaders/post/fxaa.wgsl"), "fs_main", 16));
+        self.effects[index].write_uniforms(gx, fxaa.params());
+        index
+    }
+
+    // lut: 3d texture indexed by color
+    pub fn add_lut(&mut self, gx: &impl WgxDeviceQueue, lut: TextureLot, strength: f32) -> usize {
+        let index = self.add(gx, PostEffectDsc::new(wgsl_modules::include!("shaders/post/lut.wgsl"), "fs_main", 32).texture(lut));
+        self.effects[index].write_uniforms(gx, [strength, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+        index
+    }
+
+    pub fn add_vignette(&mut self, gx: &impl WgxDeviceQueue, vignette: &Vignette) -> usize {
+        let index = self.add(gx, PostEffectDsc::new(wgsl_modules::include!("shaders/post/vignette.wgsl"), "fs_main", 32));
+        self.effects[index].write_uniforms(gx, vignette.params());
+        index
+    }
+
+    // gamma 0.0 applies the exact srgb curve
+    pub fn add_gamma(&mut self, gx: &impl WgxDeviceQueue, gamma: f32) -> usize {
+        let index = self.add(gx, PostEffectDsc::new(wgsl_modules::include!("shaders/post/gamma.wgsl"), "fs_main", 32));
+        self.effects[index].write_uniforms(gx, [gamma, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+        index
+    }
+}
+
+
+// built-in effect settings
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bloom {
+    pub threshold: f32,
+    pub knee: f32,
+    pub intensity: f32,
+    pub radius: f32, // blur spread in texels
+}
+
+impl Default for Bloom {
+    fn default() -> Self { Self { threshold: 1.0, knee: 0.5, intensity: 0.8, radius: 1.5 } }
+}
+
+impl Bloom {
+    fn params(&self, [x, y]: [f32; 2]) -> [f32; 8] {
+        [self.threshold, self.knee, self.intensity, self.radius, x, y, 0.0, 0.0]
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fxaa {
+    pub edge_threshold: f32,
+    pub edge_threshold_min: f32,
+    pub subpixel: f32,
+}
+
+impl Default for Fxaa {
+    fn default() -> Self { Self { edge_threshold: 0.125, edge_threshold_min: 0.0312, subpixel: 0.75 } }
+}
+
+impl Fxaa {
+    pub fn params(&self) -> [f32; 4] { [self.edge_threshold, self.edge_threshold_min, self.subpixel, 0.0] }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Vignette {
+    pub color: [f32; 3],
+    pub intensity: f32,
+    pub radius: f32, // distance from the center, 1.0 at the edges
+    pub smoothness: f32,
+}
+
+impl Default for Vignette {
+    fn default() -> Self { Self { color: [0.0; 3], intensity: 0.5, radius: 0.75, smoothness: 0.5 } }
+}
+
+impl Vignette {
+    pub fn params(&self) -> [f32; 8] {
+        let [r, g, b] = self.color;
+        [r, g, b, self.intensity, self.radius, self.smoothness, 0.0, 0.0]
+    }
+}
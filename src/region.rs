@@ -0,0 +1,166 @@
+use crate::*;
+
+
+// rectangle of a render target in physical pixels, origin top left
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Region {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Region {
+
+    pub const fn new(x: f32, y: f32, width: f32, height: f32) -> Self { Self { x, y, width, height } }
+
+    pub fn full([width, height]: [u32; 2]) -> Self { Self::new(0.0, 0.0, width as f32, height as f32) }
+
+    // logical pixels are physical pixels divided by the scale factor
+    pub fn logical(x: f32, y: f32, width: f32, height: f32, scale_factor: f32) -> Self {
+        Self::new(x * scale_factor, y * scale_factor, width * scale_factor, height * scale_factor)
+    }
+
+    pub fn to_logical(&self, scale_factor: f32) -> Self {
+        Self::new(self.x / scale_factor, self.y / scale_factor, self.width / scale_factor, self.height / scale_factor)
+    }
+
+    pub fn is_empty(&self) -> bool { self.width <= 0.0 || self.height <= 0.0 }
+    pub fn aspect(&self) -> f32 { self.width / self.height }
+    pub fn size(&self) -> [f32; 2] { [self.width, self.height] }
+
+    pub fn contains(&self, [x, y]: [f32; 2]) -> bool {
+        x >= self.x && y >= self.y && x < self.x + self.width && y < self.y + self.height
+    }
+
+    // an empty intersection keeps its origin within self
+    pub fn intersect(&self, other: &Self) -> Self {
+        let x = self.x.max(other.x).min(self.x + self.width);
+        let y = self.y.max(other.y).min(self.y + self.height);
+        Self::new(
+            x, y,
+            ((self.x + self.width).min(other.x + other.width) - x).max(0.0),
+            ((self.y + self.height).min(other.y + other.height) - y).max(0.0),
+        )
+    }
+
+    pub fn clamp(&self, size: [u32; 2]) -> Self { self.intersect(&Self::full(size)) }
+
+
+    // nested regions, relative to and clamped to self
+
+    pub fn sub(&self, x: f32, y: f32, width: f32, height: f32) -> Self {
+        self.intersect(&Self::new(self.x + x, self.y + y, width, height))
+    }
+
+    pub fn sub_logical(&self, x: f32, y: f32, width: f32, height: f32, scale_factor: f32) -> Self {
+        let Self { x, y, width, height } = Self::logical(x, y, width, height, scale_factor);
+        self.sub(x, y, width, height)
+    }
+
+    // position and size as fractions of self
+    pub fn sub_relative(&self, x: f32, y: f32, width: f32, height: f32) -> Self {
+        self.sub(x * self.width, y * self.height, width * self.width, height * self.height)
+    }
+
+    pub fn split_x(&self, fraction: f32) -> [Self; 2] {
+        [self.sub_relative(0.0, 0.0, fraction, 1.0), self.sub_relative(fraction, 0.0, 1.0 - fraction, 1.0)]
+    }
+
+    pub fn split_y(&self, fraction: f32) -> [Self; 2] {
+        [self.sub_relative(0.0, 0.0, 1.0, fraction), self.sub_relative(0.0, fraction, 1.0, 1.0 - fraction)]
+    }
+
+
+    // scissor rect [x, y, width, height] covering all touched pixels within a target of the given size
+    pub fn scissor(&self, [width, height]: [u32; 2]) -> [u32; 4] {
+        let [w, h] = [width as f32, height as f32];
+        let [x0, y0] = [self.x.clamp(0.0, w).floor(), self.y.clamp(0.0, h).floor()];
+        let [x1, y1] = [(self.x + self.width).clamp(x0, w).ceil(), (self.y + self.height).clamp(y0, h).ceil()];
+        [x0 as u32, y0 as u32, (x1 - x0) as u32, (y1 - y0) as u32]
+    }
+}
+
+
+// render pass extension
+pub trait RenderPassExtension {
+    // sets viewport and scissor to the region clamped to a target of the given size
+    fn set_region(&mut self, region: &Region, target_size: [u32; 2]);
+}
+
+impl RenderPassExtension for wgpu::RenderPass<'_> {
+    fn set_region(&mut self, region: &Region, target_size: [u32; 2]) {
+        let region = region.clamp(target_size);
+        let [x, y, width, height] = region.scissor(target_size);
+        self.set_scissor_rect(x, y, width, height);
+
+        // an empty viewport is invalid, the empty scissor discards everything anyway
+        if !region.is_empty() {
+            self.set_viewport(region.x, region.y, region.width, region.height, 0.0, 1.0);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_and_out_of_bounds() {
+        assert!(Region::new(10.0, 10.0, 0.0, 5.0).is_empty());
+        assert!(Region::new(10.0, 10.0, 5.0, -1.0).is_empty());
+        assert!(!Region::new(10.0, 10.0, 5.0, 5.0).is_empty());
+
+        let outside = Region::new(120.0, 20.0, 30.0, 30.0).clamp([100, 100]);
+        assert!(outside.is_empty());
+        assert_eq!(outside, Region::new(120.0, 20.0, 0.0, 30.0));
+        assert_eq!(outside.scissor([100, 100]), [100, 20, 0, 30]);
+
+        let partly = Region::new(-10.0, 90.0, 30.0, 30.0).clamp([100, 100]);
+        assert_eq!(partly, Region::new(0.0, 90.0, 20.0, 10.0));
+    }
+
+    #[test]
+    fn intersect_keeps_origin() {
+        let region = Region::new(10.0, 20.0, 50.0, 40.0);
+
+        let right = region.intersect(&Region::new(100.0, 30.0, 10.0, 10.0));
+        assert_eq!(right, Region::new(60.0, 30.0, 0.0, 10.0));
+
+        let above = region.intersect(&Region::new(0.0, 0.0, 100.0, 5.0));
+        assert_eq!(above, Region::new(10.0, 20.0, 50.0, 0.0));
+
+        assert_eq!(region.intersect(&region), region);
+    }
+
+    #[test]
+    fn nested_sub_regions() {
+        let outer = Region::new(100.0, 50.0, 400.0, 300.0);
+
+        let inner = outer.sub(10.0, 20.0, 200.0, 100.0);
+        assert_eq!(inner, Region::new(110.0, 70.0, 200.0, 100.0));
+        assert_eq!(inner.sub(150.0, 50.0, 100.0, 100.0), Region::new(260.0, 120.0, 50.0, 50.0));
+
+        // logical offsets and sizes are scaled, the origin of self is already physical
+        assert_eq!(outer.sub_logical(10.0, 20.0, 50.0, 40.0, 2.0), Region::new(120.0, 90.0, 100.0, 80.0));
+        assert_eq!(outer.sub_logical(10.0, 20.0, 500.0, 40.0, 2.0).width, 380.0);
+
+        assert_eq!(outer.sub_relative(0.5, 0.5, 0.5, 0.5), Region::new(300.0, 200.0, 200.0, 150.0));
+        assert_eq!(outer.split_x(0.25), [Region::new(100.0, 50.0, 100.0, 300.0), Region::new(200.0, 50.0, 300.0, 300.0)]);
+        assert_eq!(outer.split_y(0.5)[1], Region::new(100.0, 200.0, 400.0, 150.0));
+
+        let logical = Region::logical(5.0, 10.0, 20.0, 30.0, 1.5);
+        assert_eq!(logical, Region::new(7.5, 15.0, 30.0, 45.0));
+        assert_eq!(logical.to_logical(1.5), Region::new(5.0, 10.0, 20.0, 30.0));
+    }
+
+    #[test]
+    fn scissor_rounding() {
+        // floor the start and ceil the end to cover all touched pixels
+        assert_eq!(Region::new(1.5, 2.25, 3.0, 4.5).scissor([100, 100]), [1, 2, 4, 5]);
+        assert_eq!(Region::new(1.0, 2.0, 3.0, 4.0).scissor([100, 100]), [1, 2, 3, 4]);
+        assert_eq!(Region::new(98.5, 0.5, 10.0, 0.25).scissor([100, 100]), [98, 0, 2, 1]);
+        assert_eq!(Region::new(-3.5, -1.0, 5.0, 2.0).scissor([100, 100]), [0, 0, 2, 1]);
+    }
+}
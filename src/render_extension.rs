@@ -1,6 +1,7 @@
 
 use wgpu::{StoreOp, TextureFormat};
-use crate::Color;
+use anyhow::{Result as Res, bail};
+use crate::{Color, Region, RenderPassExtension};
 
 // render attachments
 pub type RenderAttachments<'a, const S: usize> = (
@@ -8,11 +9,80 @@ pub type RenderAttachments<'a, const S: usize> = (
     Option<wgpu::RenderPassDepthStencilAttachment<'a>>
 );
 
+// clear value, integer formats are cleared exactly with their own types
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClearValue {
+    Float(Color),
+    Uint([u32; 4]),
+    Sint([i32; 4]),
+}
+
+impl From<Color> for ClearValue {
+    fn from(color: Color) -> Self { Self::Float(color) }
+}
+
+impl From<ClearValue> for wgpu::Color {
+    // f64 represents all 32 bit integers exactly
+    fn from(value: ClearValue) -> Self {
+        match value {
+            ClearValue::Float(color) => color.into(),
+            ClearValue::Uint([r, g, b, a]) => wgpu::Color { r: r as f64, g: g as f64, b: b as f64, a: a as f64 },
+            ClearValue::Sint([r, g, b, a]) => wgpu::Color { r: r as f64, g: g as f64, b: b as f64, a: a as f64 },
+        }
+    }
+}
+
+impl ClearValue {
+
+    // black for float formats, zero for integer formats
+    pub fn default_for(format: TextureFormat) -> Self {
+        match format.sample_type(None, None) {
+            Some(wgpu::TextureSampleType::Uint) => Self::Uint([0; 4]),
+            Some(wgpu::TextureSampleType::Sint) => Self::Sint([0; 4]),
+            _ => Self::Float(Color::BLACK),
+        }
+    }
+
+    pub fn matches(&self, format: TextureFormat) -> bool {
+        use wgpu::TextureSampleType as T;
+        matches!((self, format.sample_type(None, None)),
+            (Self::Float(_), Some(T::Float {..})) | (Self::Uint(_), Some(T::Uint)) | (Self::Sint(_), Some(T::Sint))
+        )
+    }
+}
+
+
 #[derive(Debug, Clone, Copy)]
 pub struct ColorAttachment<'a> {
     pub view: &'a wgpu::TextureView,
     pub msaa: Option<&'a wgpu::TextureView>,
-    pub clear: Option<Color>,
+    pub clear: Option<ClearValue>,
+    // with msaa the multisampled texture is stored or discarded, the resolved view is always written
+    pub store: StoreOp,
+}
+
+impl<'a> ColorAttachment<'a> {
+    pub fn new(view: &'a wgpu::TextureView, msaa: Option<&'a wgpu::TextureView>, clear: Option<ClearValue>) -> Self {
+        Self { view, msaa, clear, store: StoreOp::Store }
+    }
+
+    // fails if the clear value doesn't match the format of the view
+    pub fn new_checked(
+        view: &'a wgpu::TextureView, msaa: Option<&'a wgpu::TextureView>, clear: Option<ClearValue>, format: TextureFormat,
+    ) -> Res<Self> {
+        if let Some(value) = clear {
+            if !value.matches(format) { bail!("clear value {value:?} doesn't match {format:?}") }
+        }
+        Ok(Self::new(view, msaa, clear))
+    }
+
+    pub fn store(self, store: StoreOp) -> Self { Self { store, ..self } }
+
+    // keep only the resolved result, the multisampled texture is discarded
+    // without msaa there is nothing to discard and the view is stored
+    pub fn resolve_only(self) -> Self {
+        if self.msaa.is_some() { self.store(StoreOp::Discard) } else { self }
+    }
 }
 
 impl<'a> From<ColorAttachment<'a>> for wgpu::RenderPassColorAttachment<'a> {
@@ -22,10 +92,10 @@ impl<'a> From<ColorAttachment<'a>> for wgpu::RenderPassColorAttachment<'a> {
             resolve_target: if att.msaa.is_some() { Some(att.view) } else { None },
             ops: wgpu::Operations {
                 load: match att.clear {
-                    Some(color) => wgpu::LoadOp::Clear(color.into()),
+                    Some(value) => wgpu::LoadOp::Clear(value.into()),
                     None => wgpu::LoadOp::Load,
                 },
-                store: StoreOp::Store,
+                store: att.store,
             }
         }
     }
@@ -37,6 +107,21 @@ pub struct DepthAttachment<'a> {
     pub format: TextureFormat,
     pub clear_depth: Option<f32>,
     pub clear_stencil: Option<u32>,
+    pub store: StoreOp,
+    // read only aspects can't be cleared or written, they can be bound for sampling at the same time
+    pub depth_read_only: bool,
+    pub stencil_read_only: bool,
+}
+
+impl<'a> DepthAttachment<'a> {
+    pub fn new(view: &'a wgpu::TextureView, format: TextureFormat, clear_depth: Option<f32>, clear_stencil: Option<u32>) -> Self {
+        Self { view, format, clear_depth, clear_stencil, store: StoreOp::Store, depth_read_only: false, stencil_read_only: false }
+    }
+    pub fn store(self, store: StoreOp) -> Self { Self { store, ..self } }
+
+    pub fn read_only(self, depth_read_only: bool, stencil_read_only: bool) -> Self {
+        Self { depth_read_only, stencil_read_only, ..self }
+    }
 }
 
 impl<'a> From<DepthAttachment<'a>> for wgpu::RenderPassDepthStencilAttachment<'a> {
@@ -44,23 +129,23 @@ impl<'a> From<DepthAttachment<'a>> for wgpu::RenderPassDepthStencilAttachment<'a
         Self {
             view: att.view,
 
-            depth_ops: if att.format.has_depth_aspect() {
+            depth_ops: if att.format.has_depth_aspect() && !att.depth_read_only {
                 Some(wgpu::Operations {
                     load: match att.clear_depth {
                         Some(depth) => wgpu::LoadOp::Clear(depth),
                         None => wgpu::LoadOp::Load,
                     },
-                    store: StoreOp::Store,
+                    store: att.store,
                 })
             } else { None },
 
-            stencil_ops: if att.format.has_stencil_aspect() {
+            stencil_ops: if att.format.has_stencil_aspect() && !att.stencil_read_only {
                 Some(wgpu::Operations {
                     load: match att.clear_stencil {
                         Some(stencil) => wgpu::LoadOp::Clear(stencil),
                         None => wgpu::LoadOp::Load,
                     },
-                    store: StoreOp::Store,
+                    store: att.store,
                 })
             } else { None },
         }
@@ -101,6 +186,12 @@ pub trait EncoderExtension {
         &'a mut self, attachments: RenderAttachments<'a, S>,
         bundles: impl IntoIterator<Item = &'a wgpu::RenderBundle> + 'a
     );
+
+    // render pass restricted to a region of a target with the given size
+    fn with_region_pass<'a, const S: usize, T>(
+        &'a mut self, attachments: RenderAttachments<'a, S>, region: &Region, target_size: [u32; 2],
+        handler: impl FnOnce(&mut wgpu::RenderPass<'static>) -> T
+    ) -> T;
 }
 
 
@@ -170,4 +261,15 @@ impl EncoderExtension for wgpu::CommandEncoder {
     ) {
         self.render_pass(attachments).execute_bundles(bundles);
     }
+
+
+    fn with_region_pass<'a, const S: usize, T>(
+        &'a mut self, attachments: RenderAttachments<'a, S>, region: &Region, target_size: [u32; 2],
+        handler: impl FnOnce(&mut wgpu::RenderPass<'static>) -> T
+    ) -> T {
+        self.with_render_pass(attachments, |rpass| {
+            rpass.set_region(region, target_size);
+            handler(rpass)
+        })
+    }
 }
\ No newline at end of file
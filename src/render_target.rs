@@ -1,7 +1,8 @@
 
+use arrayvec::ArrayVec;
 use wgpu::{*, PresentMode as Prs};
 use crate::{*, Color};
-use anyhow::{Result as Res};
+use anyhow::{Result as Res, bail};
 
 
 pub trait RenderTarget {
@@ -13,8 +14,18 @@ pub trait RenderTarget {
     fn format(&self) -> TextureFormat;
     fn view_format(&self) -> TextureFormat;
 
+    // changes when pipelines and bundles created for this target have to be rebuilt
+    fn generation(&self) -> u64 { 0 }
+
+    // whole target, use Region::sub for parts of it
+    fn region(&self) -> Region { Region::full(self.size()) }
+
+    // view formats of all color attachments drawn to in one pass, the first is view_format
+    fn color_formats(&self) -> ArrayVec<TextureFormat, 8> { [self.view_format()].into_iter().collect() }
+
     fn render_bundle<'a>(&self, gx: &'a impl WgxDevice, handler: impl FnOnce(&mut wgpu::RenderBundleEncoder<'a>)) -> wgpu::RenderBundle {
-        gx.render_bundle(&[Some(self.view_format())], self.depth_testing(), self.msaa(), handler)
+        let formats: ArrayVec<_, 8> = self.color_formats().into_iter().map(Some).collect();
+        gx.render_bundle(&formats, self.depth_testing(), self.msaa(), handler)
     }
 
     fn render_pipeline(
@@ -29,8 +40,62 @@ pub trait RenderTarget {
             Some((fs_module, fs_entry_point, &[(self.view_format(), blend)])),
         )
     }
+
+    // pipeline writing to all color attachments, with one blend state per attachment
+    fn multi_render_pipeline<const S: usize>(
+        &self, gx: &impl WgxDevice,
+        layout: Option<(&[wgpu::PushConstantRange], &[&wgpu::BindGroupLayout])>,
+        buffers: &[wgpu::VertexBufferLayout],
+        vertex_state: (&wgpu::ShaderModule, &str, Primitive),
+        (fs_module, fs_entry_point, blends): (&wgpu::ShaderModule, &str, [Option<Blend>; S]),
+    ) -> Res<wgpu::RenderPipeline> {
+
+        let formats = self.color_formats();
+
+        if formats.len() != S {
+            bail!("{S} blend states for {} color attachments", formats.len());
+        }
+
+        let targets: [_; S] = std::array::from_fn(|i| (formats[i], blends[i]));
+
+        Ok(gx.render_pipeline(
+            self.msaa(), self.depth_testing(), layout, buffers, vertex_state,
+            Some((fs_module, fs_entry_point, &targets)),
+        ))
+    }
+
+    // like render_pipeline, deduplicated when gx is a WgxCache
+    fn shared_render_pipeline(
+        &self, gx: &impl WgxDevice,
+        layout: Option<(&[wgpu::PushConstantRange], &[&wgpu::BindGroupLayout])>,
+        buffers: &[wgpu::VertexBufferLayout],
+        vertex_state: (&wgpu::ShaderModule, &str, Primitive),
+        (fs_module, fs_entry_point, blend): (&wgpu::ShaderModule, &str, Option<Blend>),
+    ) -> std::sync::Arc<wgpu::RenderPipeline> {
+
+        let layout = layout.map(|(push_constant_ranges, bind_group_layouts)|
+            gx.shared_pipeline_layout(push_constant_ranges, bind_group_layouts)
+        );
+
+        gx.shared_render_pipeline(
+            self.msaa(), self.depth_testing().map(depth_state), layout.as_deref(), buffers, vertex_state,
+            Some((fs_module, fs_entry_point, &[(self.view_format(), blend)])),
+        )
+    }
 }
 
+
+// render target properties the pipelines are built for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct TargetKey { pub format: TextureFormat, pub msaa: u32, pub depth_testing: Option<TextureFormat> }
+
+impl TargetKey {
+    pub(crate) fn of(target: &impl RenderTarget) -> Self {
+        Self { format: target.view_format(), msaa: target.msaa(), depth_testing: target.depth_testing() }
+    }
+}
+
+
 pub trait RenderAttachable: RenderTarget {
 
     // to implement
@@ -38,13 +103,25 @@ pub trait RenderAttachable: RenderTarget {
     fn depth_view(&self) -> Option<(&wgpu::TextureView, wgpu::TextureFormat)>;
 
     // provided
+
+    // descriptions to adjust store ops or read only access before converting into attachments
+    // fails if the clear value doesn't match the view format
+    fn color_attachment_dsc(&self, clear: Option<ClearValue>) -> Res<ColorAttachment> {
+        let (view, msaa) = self.color_views();
+        ColorAttachment::new_checked(view, msaa, clear, self.view_format())
+    }
+
+    fn depth_attachment_dsc(&self, clear_depth: Option<f32>, clear_stencil: Option<u32>) -> Option<DepthAttachment> {
+        self.depth_view().map(|(view, format)| DepthAttachment::new(view, format, clear_depth, clear_stencil))
+    }
+
     fn color_attachment(&self, clear_color: Option<Color>) -> wgpu::RenderPassColorAttachment {
         let (view, msaa) = self.color_views();
-        ColorAttachment { view, msaa, clear: clear_color }.into()
+        ColorAttachment::new(view, msaa, clear_color.map(ClearValue::from)).into()
     }
 
     fn depth_attachment(&self, clear_depth: Option<f32>, clear_stencil: Option<u32>) -> Option<wgpu::RenderPassDepthStencilAttachment> {
-        self.depth_view().map(|(view, format)| DepthAttachment { view, format, clear_depth, clear_stencil }.into())
+        self.depth_attachment_dsc(clear_depth, clear_stencil).map(Into::into)
     }
 
     fn attachments(&self, clear_color: Option<Color>, clear_depth: Option<f32>, clear_stencil: Option<u32>) -> RenderAttachments<1> {
@@ -105,6 +182,27 @@ impl RenderAttachable for TextureLot {
 
 type Surface = wgpu::Surface<'static>;
 
+
+// how SurfaceTarget::with_frame handles errors of get_current_texture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SurfaceRecovery {
+    pub reconfigure: bool, // reconfigure and retry on Outdated and Lost
+    pub max_retries: u32,
+    pub skip_timeout: bool, // skip the frame on Timeout instead of returning an error
+    pub reconfigure_suboptimal: bool, // reconfigure after presenting a suboptimal frame
+}
+
+impl Default for SurfaceRecovery {
+    fn default() -> Self {
+        Self { reconfigure: true, max_retries: 2, skip_timeout: true, reconfigure_suboptimal: true }
+    }
+}
+
+impl SurfaceRecovery {
+    pub const NONE: Self = Self { reconfigure: false, max_retries: 0, skip_timeout: false, reconfigure_suboptimal: false };
+}
+
+
 #[derive(Debug)]
 pub struct SurfaceTarget {
     pub config: SurfaceConfiguration,
@@ -113,6 +211,9 @@ pub struct SurfaceTarget {
     pub msaa: u32,
     pub msaa_opt: Option<TextureLot>,
     pub depth_opt: Option<TextureLot>,
+    pub recovery: SurfaceRecovery,
+    pub capabilities: Option<SurfaceCapabilities>, // used to validate setters, None skips validation
+    pub generation: u64,
 }
 
 impl RenderTarget for SurfaceTarget {
@@ -121,6 +222,7 @@ impl RenderTarget for SurfaceTarget {
     fn depth_testing(&self) -> Option<TextureFormat> { self.depth_opt.as_ref().map(|d| d.view_format()) }
     fn format(&self) -> TextureFormat { self.config.format }
     fn view_format(&self) -> TextureFormat { self.view_format }
+    fn generation(&self) -> u64 { self.generation }
 }
 
 
@@ -136,6 +238,7 @@ impl RenderTarget for SurfaceFrame<'_> {
     fn depth_testing(&self) -> Option<TextureFormat> { self.target.depth_testing() }
     fn format(&self) -> TextureFormat { self.target.format() }
     fn view_format(&self) -> TextureFormat { self.target.view_format() }
+    fn generation(&self) -> u64 { self.target.generation() }
 }
 
 impl RenderAttachable for SurfaceFrame<'_> {
@@ -177,17 +280,95 @@ pub fn configure_surface_defaults(
 }
 
 
+// formats preferred for hdr output, in order
+pub const HDR_SURFACE_FORMATS: [TextureFormat; 2] = [TextureFormat::Rgba16Float, TextureFormat::Rgb10a2Unorm];
+
+// color space the surface presents in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSpace {
+    Srgb, // values are clamped to 0..1, also for Rgb10a2Unorm as wgpu can't request hdr10 presentation
+    ExtendedLinearSrgb, // float surface, 1.0 is sdr white, larger values are brighter on hdr displays
+}
+
+impl ColorSpace {
+    pub fn from_format(format:TextureFormat) -> Self {
+        match format {
+            TextureFormat::Rgba16Float => Self::ExtendedLinearSrgb,
+            _ => Self::Srgb,
+        }
+    }
+    pub fn is_hdr(&self) -> bool { *self == Self::ExtendedLinearSrgb }
+}
+
+// like configure_surface_defaults but prefers HDR_SURFACE_FORMATS on hdr displays, returns true if one was chosen
+// wgpu can't query the display, hdr_display has to come from the caller, e.g. the windowing system or a setting
+pub fn configure_surface_hdr(
+    config: &mut SurfaceConfiguration, capabilites: &SurfaceCapabilities,
+    downlevel_flags: &DownlevelFlags, srgb: bool, hdr_display: bool,
+) -> bool {
+    configure_surface_defaults(config, capabilites, downlevel_flags, srgb);
+
+    if !hdr_display { return false }
+
+    if let Some(format) = HDR_SURFACE_FORMATS.iter().find(|format| capabilites.formats.contains(format)) {
+        config.format = *format;
+        config.view_formats.clear();
+        true
+    }
+    else { false }
+}
+
+
+// nearest sample count supported by all formats, lower on ties
+// adapter specific counts are only usable with Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
+// and need WgxDevice::adapter, otherwise the guaranteed counts are used
+pub fn supported_msaa(gx:&impl WgxDevice, formats:&[TextureFormat], msaa:u32) -> u32 {
+    let mut counts = vec![1, 2, 4, 8, 16];
+    let features = gx.device().features();
+    let adapter = gx.adapter().filter(|_| features.contains(Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES));
+
+    for format in formats {
+        let flags = if let Some(adapter) = adapter {
+            adapter.get_texture_format_features(*format).flags
+        } else {
+            format.guaranteed_format_features(features).flags
+        };
+        counts.retain(|count| *count == 1 || flags.sample_count_supported(*count));
+    }
+
+    counts.into_iter().min_by_key(|count| count.abs_diff(msaa)).unwrap()
+}
+
+
 impl SurfaceTarget {
 
     pub fn new_with_default_config(gx:&Wgx, surface:Surface, size:impl Into<[u32; 2]>, srgb: bool, msaa:u32, depth_testing:Option<TextureFormat>) -> Self
+    {
+        Self::new_with_config_fn(gx, surface, size, srgb, msaa, depth_testing, |config, capabilities, flags| {
+            configure_surface_defaults(config, capabilities, flags, srgb);
+        })
+    }
+
+    // prefers a hdr surface format on hdr displays, falls back to the default config, see color_space
+    pub fn new_with_hdr_config(
+        gx:&Wgx, surface:Surface, size:impl Into<[u32; 2]>, hdr_display:bool, msaa:u32, depth_testing:Option<TextureFormat>,
+    ) -> Self
+    {
+        Self::new_with_config_fn(gx, surface, size, true, msaa, depth_testing, |config, capabilities, flags| {
+            configure_surface_hdr(config, capabilities, flags, true, hdr_display);
+        })
+    }
+
+    fn new_with_config_fn(
+        gx:&Wgx, surface:Surface, size:impl Into<[u32; 2]>, srgb: bool, msaa:u32, depth_testing:Option<TextureFormat>,
+        configure: impl FnOnce(&mut SurfaceConfiguration, &SurfaceCapabilities, &DownlevelFlags),
+    ) -> Self
     {
         let [width, height] = size.into();
         let mut config = surface.get_default_config(&gx.adapter, width, height).unwrap();
+        let capabilities = surface.get_capabilities(&gx.adapter);
 
-        configure_surface_defaults(
-            &mut config, &surface.get_capabilities(&gx.adapter),
-            &gx.adapter.get_downlevel_capabilities().flags, srgb,
-        );
+        configure(&mut config, &capabilities, &gx.adapter.get_downlevel_capabilities().flags);
 
         let format = config.format;
         let view_format = if srgb { format.add_srgb_suffix() } else { format.remove_srgb_suffix() };
@@ -196,24 +377,34 @@ impl SurfaceTarget {
             assert!(config.view_formats.contains(&view_format), "view_formats may not be supported");
         }
 
-        Self::new(gx, surface, config, view_format, msaa, depth_testing)
+        let mut target = Self::new(gx, surface, config, view_format, msaa, depth_testing);
+        target.capabilities = Some(capabilities);
+        target
     }
 
 
     pub fn new(gx:&impl WgxDevice, surface:Surface, config:SurfaceConfiguration, view_format:TextureFormat, msaa:u32, depth_testing:Option<TextureFormat>)
         -> Self
     {
-        let mut target = Self { config, surface, view_format, msaa, msaa_opt: None, depth_opt: None };
+        let mut target = Self {
+            config, surface, view_format, msaa, msaa_opt: None, depth_opt: None,
+            recovery: SurfaceRecovery::default(), capabilities: None, generation: 0,
+        };
         target.configure(gx, depth_testing);
         target
     }
 
 
+    // recreates the msaa and depth attachments, switching the depth format bumps the generation
     pub fn configure(&mut self, gx:&impl WgxDevice, depth_testing:Option<TextureFormat>) {
 
         let [width, height] = self.size();
 
-        self.surface.configure(gx.device(), &self.config);
+        if depth_testing != self.depth_testing() {
+            self.generation += 1;
+        }
+
+        self.configure_surface(gx);
 
         self.msaa_opt = (self.msaa > 1).then(||
             TextureLot::new_2d(gx, [width, height, 1], self.msaa, self.format(), Some(self.view_format), TexUse::RENDER_ATTACHMENT)
@@ -233,11 +424,154 @@ impl SurfaceTarget {
     }
 
 
+    // reconfigure the surface only, keeping msaa and depth attachments
+    pub fn configure_surface(&self, gx:&impl WgxDevice) {
+        self.surface.configure(gx.device(), &self.config);
+    }
+
+    pub fn color_space(&self) -> ColorSpace { ColorSpace::from_format(self.config.format) }
+
+    pub fn query_capabilities(&mut self, adapter:&Adapter) {
+        self.capabilities = Some(self.surface.get_capabilities(adapter));
+    }
+
+
+    // runtime settings
+
+    pub fn set_present_mode(&mut self, gx:&impl WgxDevice, present_mode:PresentMode) -> Res<()> {
+        if let Some(capabilities) = &self.capabilities {
+            if
+                !matches!(present_mode, Prs::AutoVsync | Prs::AutoNoVsync) &&
+                !capabilities.present_modes.contains(&present_mode)
+            {
+                bail!("present mode {present_mode:?} not supported, available: {:?}", capabilities.present_modes);
+            }
+        }
+        self.config.present_mode = present_mode;
+        self.configure_surface(gx);
+        Ok(())
+    }
+
+    // without vsync prefers Mailbox (no tearing) over Immediate
+    pub fn set_vsync(&mut self, gx:&impl WgxDevice, vsync:bool) -> Res<()> {
+        let supports = |mode| self.capabilities.as_ref().is_some_and(|c| c.present_modes.contains(&mode));
+
+        let present_mode =
+            if vsync { Prs::AutoVsync }
+            else if supports(Prs::Mailbox) { Prs::Mailbox }
+            else { Prs::AutoNoVsync }
+        ;
+        self.set_present_mode(gx, present_mode)
+    }
+
+    pub fn set_max_frame_latency(&mut self, gx:&impl WgxDevice, latency:u32) -> Res<()> {
+        if latency == 0 { bail!("maximum frame latency must be at least 1") }
+        self.config.desired_maximum_frame_latency = latency;
+        self.configure_surface(gx);
+        Ok(())
+    }
+
+    pub fn set_alpha_mode(&mut self, gx:&impl WgxDevice, alpha_mode:CompositeAlphaMode) -> Res<()> {
+        if let Some(capabilities) = &self.capabilities {
+            if alpha_mode != CompositeAlphaMode::Auto && !capabilities.alpha_modes.contains(&alpha_mode) {
+                bail!("alpha mode {alpha_mode:?} not supported, available: {:?}", capabilities.alpha_modes);
+            }
+        }
+        self.config.alpha_mode = alpha_mode;
+        self.configure_surface(gx);
+        Ok(())
+    }
+
+    // switches the view format, or the surface format if it can't be viewed as requested
+    // fails for formats without srgb variant like Rgba16Float or Rgb10a2Unorm
+    pub fn set_srgb(&mut self, gx:&impl WgxDevice, srgb:bool) -> Res<()> {
+
+        let view_format = if srgb { self.config.format.add_srgb_suffix() } else { self.config.format.remove_srgb_suffix() };
+
+        if srgb && !view_format.is_srgb() {
+            bail!("surface format {:?} has no srgb variant", self.config.format);
+        }
+
+        if view_format == self.view_format { return Ok(()) }
+
+        if view_format == self.config.format || self.config.view_formats.contains(&view_format) {
+            self.view_format = view_format;
+        }
+        else {
+            let Some(capabilities) = &self.capabilities else {
+                bail!("view format {view_format:?} not available, surface capabilities unknown");
+            };
+            let Some(format) = capabilities.formats.iter().find(|format| format.is_srgb() == srgb) else {
+                bail!("no {} surface format available, available: {:?}", if srgb {"srgb"} else {"linear"}, capabilities.formats);
+            };
+            self.config.format = *format;
+            self.config.view_formats.clear();
+            self.view_format = *format;
+        }
+
+        // msaa attachment has the view format
+        self.configure(gx, self.depth_testing());
+        self.generation += 1;
+        Ok(())
+    }
+
+    // returns the sample count actually set
+    pub fn set_msaa(&mut self, gx:&impl WgxDevice, msaa:u32) -> u32 {
+        let depth_testing = self.depth_testing();
+
+        let formats: Vec<_> = [self.format(), self.view_format].into_iter().chain(depth_testing).collect();
+        let msaa = supported_msaa(gx, &formats, msaa);
+
+        if msaa != self.msaa {
+            self.msaa = msaa;
+            self.configure(gx, depth_testing);
+            self.generation += 1;
+        }
+        msaa
+    }
+
+
+    // get the current texture, recovering from errors according to self.recovery
+    // returns None if the frame should be skipped
+    pub fn current_texture(&mut self, gx:&impl WgxDevice) -> Res<Option<SurfaceTexture>> {
+
+        let mut retries = 0;
+
+        loop {
+            match self.surface.get_current_texture() {
+                Ok(frame) => return Ok(Some(frame)),
+
+                Err(err @ (SurfaceError::Outdated | SurfaceError::Lost))
+                    if self.recovery.reconfigure && retries < self.recovery.max_retries =>
+                {
+                    log::warn!("surface {err}, reconfiguring");
+                    self.configure_surface(gx);
+                    retries += 1;
+                },
+
+                Err(SurfaceError::Timeout) if self.recovery.skip_timeout => {
+                    log::warn!("surface timeout, skipping frame");
+                    return Ok(None);
+                },
+
+                Err(err @ SurfaceError::OutOfMemory) => {
+                    return Err(anyhow::Error::new(err).context("fatal surface error"));
+                },
+
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
+
+    // returns None if the frame was skipped
     pub fn with_frame<T: ImplicitControlFlow>(
-        &mut self, dsc: Option<&wgpu::TextureViewDescriptor>, handler: impl FnOnce(&SurfaceFrame) -> T
-    ) -> Res<T>
+        &mut self, gx:&impl WgxDevice, dsc: Option<&wgpu::TextureViewDescriptor>, handler: impl FnOnce(&SurfaceFrame) -> T
+    ) -> Res<Option<T>>
     {
-        let frame = self.surface.get_current_texture()?;
+        let Some(frame) = self.current_texture(gx)? else { return Ok(None) };
+
+        let suboptimal = frame.suboptimal;
 
         let res = handler(&SurfaceFrame {
             view: if let Some(dsc) = dsc {
@@ -250,9 +584,16 @@ impl SurfaceTarget {
 
         if res.should_continue() {
             frame.present();
+        } else {
+            drop(frame);
         }
 
-        Ok(res)
+        if suboptimal && self.recovery.reconfigure_suboptimal {
+            log::warn!("surface suboptimal, reconfiguring");
+            self.configure_surface(gx);
+        }
+
+        Ok(Some(res))
     }
 }
 
@@ -267,6 +608,8 @@ pub struct TextureTarget {
     pub msaa: u32,
     pub msaa_opt: Option<TextureLot>,
     pub depth_opt: Option<TextureLot>,
+
+    pub generation: u64,
 }
 
 impl RenderTarget for TextureTarget {
@@ -275,6 +618,7 @@ impl RenderTarget for TextureTarget {
     fn depth_testing(&self) -> Option<TextureFormat> { self.depth_opt.as_ref().map(|d| d.view_format()) }
     fn format(&self) -> TextureFormat { self.descriptor.format }
     fn view_format(&self) -> TextureFormat { self.descriptor.view_format }
+    fn generation(&self) -> u64 { self.generation }
 }
 
 impl RenderAttachable for TextureTarget {
@@ -304,6 +648,33 @@ impl TextureTarget {
             depth_opt: depth_testing.map(|depth_format|
                 TextureLot::new_2d(gx, [w, h, 1], msaa, depth_format, None, TexUse::RENDER_ATTACHMENT)
             ),
+
+            generation: 0,
+        }
+    }
+
+    // returns the sample count actually set
+    pub fn set_msaa(&mut self, gx:&impl WgxDevice, msaa:u32) -> u32 {
+        let [w, h] = self.size();
+        let (format, view_format) = (self.format(), self.view_format());
+        let depth_testing = self.depth_testing();
+
+        let formats: Vec<_> = [format, view_format].into_iter().chain(depth_testing).collect();
+        let msaa = supported_msaa(gx, &formats, msaa);
+
+        if msaa != self.msaa {
+            self.msaa = msaa;
+
+            self.msaa_opt = (msaa > 1).then(||
+                TextureLot::new_2d(gx, [w, h, 1], msaa, format, Some(view_format), TexUse::RENDER_ATTACHMENT)
+            );
+
+            self.depth_opt = depth_testing.map(|depth_format|
+                TextureLot::new_2d(gx, [w, h, 1], msaa, depth_format, None, TexUse::RENDER_ATTACHMENT)
+            );
+
+            self.generation += 1;
         }
+        msaa
     }
 }
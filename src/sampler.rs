@@ -0,0 +1,156 @@
+use std::hash::{Hash, Hasher};
+use wgpu::{AddressMode, FilterMode, CompareFunction, SamplerBorderColor, SamplerDescriptor};
+
+
+// our own SamplerDescriptor, hashable for caching
+#[derive(Debug, Clone, Copy)]
+pub struct SamplerDsc {
+    pub label: Option<&'static str>,
+    pub address_mode: [AddressMode; 3],
+    pub mag_filter: FilterMode,
+    pub min_filter: FilterMode,
+    pub mipmap_filter: FilterMode,
+    pub lod_min_clamp: f32,
+    pub lod_max_clamp: f32,
+    pub compare: Option<CompareFunction>,
+    pub anisotropy_clamp: u16,
+    pub border_color: Option<SamplerBorderColor>,
+}
+
+impl Default for SamplerDsc {
+    fn default() -> Self { Self::TRILINEAR }
+}
+
+impl SamplerDsc {
+
+    // presets
+
+    pub const NEAREST: Self = Self {
+        label: None,
+        address_mode: [AddressMode::ClampToEdge; 3],
+        mag_filter: FilterMode::Nearest,
+        min_filter: FilterMode::Nearest,
+        mipmap_filter: FilterMode::Nearest,
+        lod_min_clamp: 0.0,
+        lod_max_clamp: 100.0,
+        compare: None,
+        anisotropy_clamp: 1,
+        border_color: None,
+    };
+
+    pub const LINEAR: Self = Self {
+        mag_filter: FilterMode::Linear,
+        min_filter: FilterMode::Linear,
+        ..Self::NEAREST
+    };
+
+    pub const TRILINEAR: Self = Self {
+        mipmap_filter: FilterMode::Linear,
+        ..Self::LINEAR
+    };
+
+    // depth comparison, sampled with textureSampleCompare
+    pub const SHADOW: Self = Self {
+        compare: Some(CompareFunction::LessEqual),
+        ..Self::LINEAR
+    };
+
+    // anisotropic filtering requires all filters to be linear
+    pub const fn anisotropic(anisotropy_clamp: u16) -> Self {
+        Self { anisotropy_clamp, ..Self::TRILINEAR }
+    }
+
+
+    // builder
+
+    pub const fn label(self, label: &'static str) -> Self { Self { label: Some(label), ..self } }
+
+    pub const fn address(self, mode: AddressMode) -> Self { Self { address_mode: [mode; 3], ..self } }
+    pub const fn address_uvw(self, u: AddressMode, v: AddressMode, w: AddressMode) -> Self { Self { address_mode: [u, v, w], ..self } }
+
+    pub const fn clamp(self) -> Self { self.address(AddressMode::ClampToEdge) }
+    pub const fn repeat(self) -> Self { self.address(AddressMode::Repeat) }
+    pub const fn mirror(self) -> Self { self.address(AddressMode::MirrorRepeat) }
+
+    // requires Features::ADDRESS_MODE_CLAMP_TO_BORDER
+    pub const fn border(self, color: SamplerBorderColor) -> Self {
+        Self { border_color: Some(color), ..self.address(AddressMode::ClampToBorder) }
+    }
+
+    pub const fn filter(self, mag_filter: FilterMode, min_filter: FilterMode, mipmap_filter: FilterMode) -> Self {
+        Self { mag_filter, min_filter, mipmap_filter, ..self }
+    }
+
+    pub const fn lod(self, lod_min_clamp: f32, lod_max_clamp: f32) -> Self {
+        Self { lod_min_clamp, lod_max_clamp, ..self }
+    }
+
+    pub const fn compare(self, compare: CompareFunction) -> Self { Self { compare: Some(compare), ..self } }
+
+    pub const fn anisotropy(self, anisotropy_clamp: u16) -> Self { Self { anisotropy_clamp, ..self } }
+
+
+    // properties
+
+    pub fn is_comparison(&self) -> bool { self.compare.is_some() }
+
+    pub fn is_filtering(&self) -> bool {
+        [self.mag_filter, self.min_filter, self.mipmap_filter].contains(&FilterMode::Linear)
+    }
+
+    pub fn descriptor(&self) -> SamplerDescriptor<'static> {
+        SamplerDescriptor {
+            label: self.label,
+            address_mode_u: self.address_mode[0],
+            address_mode_v: self.address_mode[1],
+            address_mode_w: self.address_mode[2],
+            mag_filter: self.mag_filter,
+            min_filter: self.min_filter,
+            mipmap_filter: self.mipmap_filter,
+            lod_min_clamp: self.lod_min_clamp,
+            lod_max_clamp: self.lod_max_clamp,
+            compare: self.compare,
+            anisotropy_clamp: self.anisotropy_clamp,
+            border_color: self.border_color,
+        }
+    }
+
+    fn key(&self) -> impl Eq + Hash {(
+        self.label, self.address_mode, [self.mag_filter, self.min_filter, self.mipmap_filter],
+        [self.lod_min_clamp.to_bits(), self.lod_max_clamp.to_bits()],
+        self.compare, self.anisotropy_clamp, self.border_color,
+    )}
+}
+
+impl PartialEq for SamplerDsc {
+    fn eq(&self, other: &Self) -> bool { self.key() == other.key() }
+}
+impl Eq for SamplerDsc {}
+
+impl Hash for SamplerDsc {
+    fn hash<H: Hasher>(&self, state: &mut H) { self.key().hash(state) }
+}
+
+
+// to / from SamplerDescriptor
+
+impl From<&SamplerDsc> for SamplerDescriptor<'static> {
+    fn from(dsc: &SamplerDsc) -> Self { dsc.descriptor() }
+}
+
+impl From<&SamplerDescriptor<'_>> for SamplerDsc {
+    fn from(dsc: &SamplerDescriptor) -> Self {
+        Self {
+            label: None,
+            address_mode: [dsc.address_mode_u, dsc.address_mode_v, dsc.address_mode_w],
+            mag_filter: dsc.mag_filter,
+            min_filter: dsc.min_filter,
+            mipmap_filter: dsc.mipmap_filter,
+            lod_min_clamp: dsc.lod_min_clamp,
+            lod_max_clamp: dsc.lod_max_clamp,
+            compare: dsc.compare,
+            anisotropy_clamp: dsc.anisotropy_clamp,
+            border_color: dsc.border_color,
+        }
+    }
+}
@@ -0,0 +1,27 @@
+
+struct Params {
+    src_rect: vec4f, // x, y, width, height in source pixels, negative sizes flip
+}
+
+@group(0) @binding(0) var src: texture_2d<f32>;
+@group(0) @binding(1) var src_sampler: sampler;
+@group(0) @binding(2) var<uniform> params: Params;
+
+
+struct VertexOutput {
+    @builtin(position) position: vec4f,
+    @location(0) uv: vec2f,
+}
+
+// fullscreen triangle, the viewport selects the destination rect
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2f(f32((index << 1u) & 2u), f32(index & 2u));
+    let src_uv = (params.src_rect.xy + uv * params.src_rect.zw) / vec2f(textureDimensions(src));
+    return VertexOutput(vec4f(uv * vec2f(2.0, -2.0) + vec2f(-1.0, 1.0), 0.0, 1.0), src_uv);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    return textureSample(src, src_sampler, in.uv);
+}
@@ -0,0 +1,14 @@
+
+// matches wgx::camera::CameraUniform
+
+struct Camera {
+    view: mat4x4f,
+    projection: mat4x4f,
+    view_projection: mat4x4f,
+    inverse_view: mat4x4f,
+    inverse_projection: mat4x4f,
+    inverse_view_projection: mat4x4f,
+    position: vec3f,
+    near: f32,
+    far: f32, // f32::MAX for infinite projections
+}
@@ -0,0 +1,29 @@
+
+// positions are transformed to clip space on the cpu
+
+@group(0) @binding(0) var t_texture: texture_2d<f32>;
+@group(0) @binding(1) var t_sampler: sampler;
+
+
+struct VertexIn {
+    @location(0) position: vec2f,
+    @location(1) uv: vec2f,
+    @location(2) color: vec4f,
+}
+
+struct VertexOut {
+    @builtin(position) position: vec4f,
+    @location(0) uv: vec2f,
+    @location(1) color: vec4f,
+}
+
+@vertex
+fn vs_main(in: VertexIn) -> VertexOut {
+    return VertexOut(vec4f(in.position, 0.0, 1.0), in.uv, in.color);
+}
+
+
+@fragment
+fn fs_main(in: VertexOut) -> @location(0) vec4f {
+    return textureSample(t_texture, t_sampler, in.uv) * in.color;
+}
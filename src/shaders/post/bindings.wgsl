@@ -0,0 +1,5 @@
+
+// bindings every post effect gets
+@group(0) @binding(0) var src: texture_2d<f32>; // output of the previous effect
+@group(0) @binding(1) var src_sampler: sampler;
+@group(0) @binding(2) var scene: texture_2d<f32>; // input of the chain
@@ -0,0 +1,51 @@
+&include "bindings.wgsl"
+
+struct Params {
+    threshold: f32,
+    knee: f32,
+    intensity: f32,
+    radius: f32, // blur spread in texels
+    direction: vec2f,
+}
+
+@group(0) @binding(3) var<uniform> params: Params;
+
+
+// keeps the bright parts, soft knee around the threshold
+@fragment
+fn fs_extract(@location(0) uv: vec2f) -> @location(0) vec4f {
+    let color = textureSample(src, src_sampler, uv).rgb;
+    let brightness = max(color.r, max(color.g, color.b));
+
+    let soft = clamp(brightness - params.threshold + params.knee, 0.0, 2.0 * params.knee);
+    let soft_weight = soft * soft / (4.0 * params.knee + 1e-5);
+    let weight = max(soft_weight, brightness - params.threshold) / max(brightness, 1e-5);
+
+    return vec4f(color * weight, 1.0);
+}
+
+// separable 9 tap gaussian along params.direction
+@fragment
+fn fs_blur(@location(0) uv: vec2f) -> @location(0) vec4f {
+    let texel = params.direction * params.radius / vec2f(textureDimensions(src));
+
+    var weights = array<f32, 5>(0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+    var color = textureSample(src, src_sampler, uv).rgb * weights[0];
+
+    for (var i = 1; i < 5; i++) {
+        let offset = texel * f32(i);
+        color += textureSample(src, src_sampler, uv + offset).rgb * weights[i];
+        color += textureSample(src, src_sampler, uv - offset).rgb * weights[i];
+    }
+
+    return vec4f(color, 1.0);
+}
+
+// adds the blurred highlights to the scene
+@fragment
+fn fs_composite(@location(0) uv: vec2f) -> @location(0) vec4f {
+    let base = textureSample(scene, src_sampler, uv);
+    let bloom = textureSample(src, src_sampler, uv).rgb;
+    return vec4f(base.rgb + bloom * params.intensity, base.a);
+}
@@ -0,0 +1,6 @@
+&include "bindings.wgsl"
+
+@fragment
+fn fs_main(@location(0) uv: vec2f) -> @location(0) vec4f {
+    return textureSample(src, src_sampler, uv);
+}
@@ -0,0 +1,69 @@
+&include "bindings.wgsl"
+
+struct Params {
+    edge_threshold: f32,
+    edge_threshold_min: f32,
+    subpixel: f32,
+    _pad: f32,
+}
+
+@group(0) @binding(3) var<uniform> params: Params;
+
+
+fn luma(color: vec3f) -> f32 {
+    return dot(color, vec3f(0.299, 0.587, 0.114));
+}
+
+fn sample_luma(uv: vec2f) -> f32 {
+    return luma(textureSampleLevel(src, src_sampler, uv, 0.0).rgb);
+}
+
+// simplified fxaa, expects gamma encoded input
+@fragment
+fn fs_main(@location(0) uv: vec2f) -> @location(0) vec4f {
+    let texel = 1.0 / vec2f(textureDimensions(src));
+    let center = textureSampleLevel(src, src_sampler, uv, 0.0);
+
+    let l_m = luma(center.rgb);
+    let l_n = sample_luma(uv + vec2f(0.0, -texel.y));
+    let l_s = sample_luma(uv + vec2f(0.0, texel.y));
+    let l_w = sample_luma(uv + vec2f(-texel.x, 0.0));
+    let l_e = sample_luma(uv + vec2f(texel.x, 0.0));
+
+    let l_min = min(l_m, min(min(l_n, l_s), min(l_w, l_e)));
+    let l_max = max(l_m, max(max(l_n, l_s), max(l_w, l_e)));
+    let range = l_max - l_min;
+
+    if range < max(params.edge_threshold_min, l_max * params.edge_threshold) {
+        return center;
+    }
+
+    let l_nw = sample_luma(uv + vec2f(-texel.x, -texel.y));
+    let l_ne = sample_luma(uv + vec2f(texel.x, -texel.y));
+    let l_sw = sample_luma(uv + vec2f(-texel.x, texel.y));
+    let l_se = sample_luma(uv + vec2f(texel.x, texel.y));
+
+    // blend direction perpendicular to the edge
+    var dir = vec2f(
+        -((l_nw + l_ne) - (l_sw + l_se)),
+        (l_nw + l_sw) - (l_ne + l_se),
+    );
+
+    let reduce = max((l_nw + l_ne + l_sw + l_se) * 0.25 * 0.125, 1.0 / 128.0);
+    let scale = 1.0 / (min(abs(dir.x), abs(dir.y)) + reduce);
+    dir = clamp(dir * scale, vec2f(-8.0), vec2f(8.0)) * texel;
+
+    let a = 0.5 * (
+        textureSampleLevel(src, src_sampler, uv + dir * (1.0 / 3.0 - 0.5), 0.0).rgb +
+        textureSampleLevel(src, src_sampler, uv + dir * (2.0 / 3.0 - 0.5), 0.0).rgb
+    );
+    let b = a * 0.5 + 0.25 * (
+        textureSampleLevel(src, src_sampler, uv - dir * 0.5, 0.0).rgb +
+        textureSampleLevel(src, src_sampler, uv + dir * 0.5, 0.0).rgb
+    );
+
+    let l_b = luma(b);
+    let color = select(b, a, l_b < l_min || l_b > l_max);
+
+    return vec4f(mix(center.rgb, color, params.subpixel), center.a);
+}
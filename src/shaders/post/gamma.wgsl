@@ -0,0 +1,27 @@
+&include "bindings.wgsl"
+
+struct Params {
+    gamma: f32, // 0 for the exact srgb curve
+    _pad: vec3f,
+}
+
+@group(0) @binding(3) var<uniform> params: Params;
+
+
+fn linear_to_srgb(color: vec3f) -> vec3f {
+    let low = color * 12.92;
+    let high = 1.055 * pow(color, vec3f(1.0 / 2.4)) - 0.055;
+    return select(high, low, color <= vec3f(0.0031308));
+}
+
+// encodes linear values, for output to non srgb formats
+@fragment
+fn fs_main(@location(0) uv: vec2f) -> @location(0) vec4f {
+    let sample = textureSample(src, src_sampler, uv);
+    let color = max(sample.rgb, vec3f(0.0));
+
+    if params.gamma == 0.0 {
+        return vec4f(linear_to_srgb(color), sample.a);
+    }
+    return vec4f(pow(color, vec3f(1.0 / params.gamma)), sample.a);
+}
@@ -0,0 +1,23 @@
+&include "bindings.wgsl"
+
+struct Params {
+    strength: f32,
+    _pad: vec3f,
+}
+
+@group(0) @binding(3) var<uniform> params: Params;
+@group(0) @binding(4) var lut: texture_3d<f32>;
+
+
+// color grading with a 3d lookup table, indexed by the color in 0..1
+@fragment
+fn fs_main(@location(0) uv: vec2f) -> @location(0) vec4f {
+    let color = textureSample(src, src_sampler, uv);
+    let size = f32(textureDimensions(lut).x);
+
+    // sample texel centers
+    let coords = clamp(color.rgb, vec3f(0.0), vec3f(1.0)) * ((size - 1.0) / size) + 0.5 / size;
+    let graded = textureSample(lut, src_sampler, coords).rgb;
+
+    return vec4f(mix(color.rgb, graded, params.strength), color.a);
+}
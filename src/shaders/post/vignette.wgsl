@@ -0,0 +1,21 @@
+&include "bindings.wgsl"
+
+struct Params {
+    color: vec3f,
+    intensity: f32,
+    radius: f32,
+    smoothness: f32,
+}
+
+@group(0) @binding(3) var<uniform> params: Params;
+
+
+@fragment
+fn fs_main(@location(0) uv: vec2f) -> @location(0) vec4f {
+    let color = textureSample(src, src_sampler, uv);
+
+    let distance = length((uv - 0.5) * 2.0);
+    let factor = smoothstep(params.radius, params.radius + params.smoothness, distance) * params.intensity;
+
+    return vec4f(mix(color.rgb, params.color, factor), color.a);
+}
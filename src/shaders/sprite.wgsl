@@ -0,0 +1,45 @@
+
+struct Params {
+    viewport: mat4x4f, // pixels to clip space
+}
+
+@group(0) @binding(0) var t_atlas: texture_2d_array<f32>;
+@group(0) @binding(1) var t_sampler: sampler;
+@group(0) @binding(2) var<uniform> params: Params;
+
+
+struct Instance {
+    @location(0) x_axis: vec2f,
+    @location(1) y_axis: vec2f,
+    @location(2) translation: vec2f,
+    @location(3) uv: vec4f, // x, y, width, height
+    @location(4) color: vec4f,
+    @location(5) layer: u32,
+}
+
+struct VertexOut {
+    @builtin(position) position: vec4f,
+    @location(0) uv: vec2f,
+    @location(1) color: vec4f,
+    @location(2) @interpolate(flat) layer: u32,
+}
+
+// unit quad as triangle strip
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, in: Instance) -> VertexOut {
+    let corner = vec2f(f32(index & 1u), f32(index >> 1u));
+    let position = in.x_axis * corner.x + in.y_axis * corner.y + in.translation;
+
+    return VertexOut(
+        params.viewport * vec4f(position, 0.0, 1.0),
+        in.uv.xy + in.uv.zw * corner,
+        in.color,
+        in.layer,
+    );
+}
+
+
+@fragment
+fn fs_main(in: VertexOut) -> @location(0) vec4f {
+    return textureSample(t_atlas, t_sampler, in.uv, in.layer) * in.color;
+}
@@ -0,0 +1,40 @@
+
+struct Params {
+    sdf: u32, // glyphs are distance fields instead of coverage
+}
+
+@group(0) @binding(0) var t_atlas: texture_2d<f32>;
+@group(0) @binding(1) var t_sampler: sampler;
+@group(0) @binding(2) var<uniform> params: Params;
+
+
+struct VertexIn {
+    @location(0) position: vec4f, // clip space
+    @location(1) uv: vec2f, // atlas pixels
+    @location(2) color: vec4f,
+}
+
+struct VertexOut {
+    @builtin(position) position: vec4f,
+    @location(0) uv: vec2f,
+    @location(1) color: vec4f,
+}
+
+@vertex
+fn vs_main(in: VertexIn) -> VertexOut {
+    return VertexOut(in.position, in.uv / vec2f(textureDimensions(t_atlas)), in.color);
+}
+
+
+@fragment
+fn fs_main(in: VertexOut) -> @location(0) vec4f {
+    let value = textureSample(t_atlas, t_sampler, in.uv).r;
+    let width = max(fwidth(value), 1e-4) * 0.5;
+
+    var alpha = value;
+    if params.sdf != 0u {
+        alpha = smoothstep(0.5 - width, 0.5 + width, value);
+    }
+
+    return vec4f(in.color.rgb, in.color.a * alpha);
+}
@@ -0,0 +1,103 @@
+
+struct Params {
+    exposure: f32,
+    peak: f32, // hdr output: brightness mapped to the operator's white, relative to sdr white
+    tonemap: u32, // 0: clamp, 1: reinhard, 2: aces, 3: agx
+    flags: u32, // 1: hdr output, 2: encode srgb
+}
+
+@group(0) @binding(0) var src: texture_2d<f32>;
+@group(0) @binding(1) var src_sampler: sampler;
+@group(0) @binding(2) var<uniform> params: Params;
+
+
+struct VertexOutput {
+    @builtin(position) position: vec4f,
+    @location(0) uv: vec2f,
+}
+
+// fullscreen triangle
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2f(f32((index << 1u) & 2u), f32(index & 2u));
+    return VertexOutput(vec4f(uv * vec2f(2.0, -2.0) + vec2f(-1.0, 1.0), 0.0, 1.0), uv);
+}
+
+
+// operators
+
+fn luminance(color: vec3f) -> f32 {
+    return dot(color, vec3f(0.2126, 0.7152, 0.0722));
+}
+
+fn reinhard(color: vec3f) -> vec3f {
+    return color / (1.0 + luminance(color));
+}
+
+// Narkowicz fit
+fn aces(color: vec3f) -> vec3f {
+    let x = color * 0.6;
+    return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), vec3f(0.0), vec3f(1.0));
+}
+
+fn agx_contrast(x: vec3f) -> vec3f {
+    let x2 = x * x;
+    let x4 = x2 * x2;
+    return 15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x - 0.00232;
+}
+
+fn agx(color: vec3f) -> vec3f {
+    let inset = mat3x3f(
+        vec3f(0.842479062253094, 0.0423282422610123, 0.0423756549057051),
+        vec3f(0.0784335999999992, 0.878468636469772, 0.0784336),
+        vec3f(0.0792237451477643, 0.0791661274605434, 0.879142973793104),
+    );
+    let outset = mat3x3f(
+        vec3f(1.19687900512017, -0.0528968517574562, -0.0529716355144438),
+        vec3f(-0.0980208811401368, 1.15190312990417, -0.0980434501171241),
+        vec3f(-0.0990297440797205, -0.0989611768448433, 1.15107367264116),
+    );
+    let min_ev = -12.47393;
+    let max_ev = 4.026069;
+
+    var x = clamp(log2(max(inset * color, vec3f(1e-10))), vec3f(min_ev), vec3f(max_ev));
+    x = agx_contrast((x - min_ev) / (max_ev - min_ev));
+
+    // agx output is display encoded
+    return pow(max(outset * x, vec3f(0.0)), vec3f(2.2));
+}
+
+fn tonemap(color: vec3f) -> vec3f {
+    switch params.tonemap {
+        case 1u: { return reinhard(color); }
+        case 2u: { return aces(color); }
+        case 3u: { return agx(color); }
+        default: { return clamp(color, vec3f(0.0), vec3f(1.0)); }
+    }
+}
+
+fn linear_to_srgb(color: vec3f) -> vec3f {
+    let low = color * 12.92;
+    let high = 1.055 * pow(color, vec3f(1.0 / 2.4)) - 0.055;
+    return select(high, low, color <= vec3f(0.0031308));
+}
+
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let sample = textureSample(src, src_sampler, in.uv);
+    var color = max(sample.rgb * params.exposure, vec3f(0.0));
+
+    if (params.flags & 1u) != 0u {
+        // keep the range above sdr white
+        if params.tonemap != 0u { color = tonemap(color / params.peak) * params.peak; }
+    } else {
+        color = tonemap(color);
+    }
+
+    if (params.flags & 2u) != 0u {
+        color = linear_to_srgb(clamp(color, vec3f(0.0), vec3f(1.0)));
+    }
+
+    return vec4f(color, sample.a);
+}
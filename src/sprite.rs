@@ -0,0 +1,291 @@
+
+// instanced sprites from texture atlases, sprites of one atlas within a depth band are one draw call
+
+use std::{ops::Range, mem::size_of, sync::Arc};
+use wgpu::FilterMode;
+use anyhow::{Result as Res};
+use crate::{*, math::*, draw2d::BlendMode, render_target::TargetKey};
+
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SpriteInstance {
+    pub x_axis: [f32; 2], // transform of the unit quad into pixels
+    pub y_axis: [f32; 2],
+    pub translation: [f32; 2],
+    pub uv: [f32; 4], // x, y, width, height
+    pub color: [f32; 4],
+    pub layer: u32,
+}
+
+unsafe impl ReadBytes for SpriteInstance {}
+
+impl SpriteInstance {
+    pub const LAYOUT: wgpu::VertexBufferLayout<'static> = vertex_dsc!(Instance,
+        0 => Float32x2, 1 => Float32x2, 2 => Float32x2, 3 => Float32x4, 4 => Float32x4, 5 => Uint32
+    );
+}
+
+
+// atlas registered with SpriteRenderer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct SheetId(pub usize);
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprite {
+    pub sheet: SheetId,
+    pub position: Vec2, // pixels, origin top left
+    pub size: Vec2,
+    pub origin: Vec2, // pivot for position and rotation, relative to the size
+    pub rotation: f32,
+    pub uv: [f32; 4],
+    pub layer: u32,
+    pub color: Color, // tint
+    pub depth: f32, // sprites with higher depth are drawn first
+}
+
+impl Sprite {
+
+    // sprite with the size of the atlas entry
+    pub fn new(sheet: SheetId, entry: &AtlasEntry, position: impl Into<Vec2>) -> Self {
+        Self {
+            sheet, position: position.into(),
+            size: Vec2::new(entry.rect[2] as f32, entry.rect[3] as f32),
+            origin: Vec2::ZERO, rotation: 0.0,
+            uv: entry.uv, layer: entry.layer,
+            color: Color::WHITE, depth: 0.0,
+        }
+    }
+
+    pub fn size(self, size: impl Into<Vec2>) -> Self { Self { size: size.into(), ..self } }
+    pub fn origin(self, origin: impl Into<Vec2>) -> Self { Self { origin: origin.into(), ..self } }
+    pub fn rotation(self, rotation: f32) -> Self { Self { rotation, ..self } }
+    pub fn color(self, color: Color) -> Self { Self { color, ..self } }
+    pub fn depth(self, depth: f32) -> Self { Self { depth, ..self } }
+
+    pub fn flip_x(self) -> Self {
+        let [x, y, w, h] = self.uv;
+        Self { uv: [x + w, y, -w, h], ..self }
+    }
+
+    pub fn flip_y(self) -> Self {
+        let [x, y, w, h] = self.uv;
+        Self { uv: [x, y + h, w, -h], ..self }
+    }
+
+    pub fn transform(&self) -> Affine2 {
+        Affine2::from_scale_angle_translation(self.size, self.rotation, self.position) * Affine2::from_translation(-self.origin)
+    }
+
+    pub fn instance(&self) -> SpriteInstance {
+        let transform = self.transform();
+        SpriteInstance {
+            x_axis: transform.matrix2.x_axis.into(),
+            y_axis: transform.matrix2.y_axis.into(),
+            translation: transform.translation.into(),
+            uv: self.uv, color: self.color.f32(), layer: self.layer,
+        }
+    }
+}
+
+
+// sprites of one frame, sorted by depth and sheet when prepared
+#[derive(Debug, Clone)]
+pub struct SpriteBatch {
+    pub sprites: Vec<(f32, SheetId, SpriteInstance)>,
+    // 0 sorts strictly by depth, each change of depth and sheet is a draw call
+    // wider bands group the sprites of one sheet within a band into one call,
+    // sprites of different sheets in the same band are then no longer drawn in depth order
+    pub depth_band: f32,
+    viewport: Mat4,
+}
+
+impl SpriteBatch {
+
+    pub fn new(size: [f32; 2]) -> Self {
+        Self { sprites: Vec::new(), depth_band: 0.0, viewport: Mat4::flat_viewport_lh(size[0], size[1], 0.0) }
+    }
+
+    pub fn depth_band(self, depth_band: f32) -> Self { Self { depth_band, ..self } }
+
+    pub fn for_target(target: &impl RenderTarget) -> Self {
+        Self::new(target.region().size())
+    }
+
+    pub fn resize(&mut self, size: [f32; 2]) {
+        self.viewport = Mat4::flat_viewport_lh(size[0], size[1], 0.0);
+    }
+
+    pub fn len(&self) -> usize { self.sprites.len() }
+    pub fn is_empty(&self) -> bool { self.sprites.is_empty() }
+    pub fn clear(&mut self) { self.sprites.clear() }
+
+    pub fn push(&mut self, sprite: &Sprite) {
+        self.sprites.push((sprite.depth, sprite.sheet, sprite.instance()));
+    }
+
+    pub fn push_instance(&mut self, sheet: SheetId, depth: f32, instance: SpriteInstance) {
+        self.sprites.push((depth, sheet, instance));
+    }
+
+    // back to front by depth band, grouped by sheet within a band, then back to front, otherwise in push order
+    pub fn sort(&mut self) {
+        let band = |depth: f32| if self.depth_band > 0.0 { (depth / self.depth_band).floor() } else { depth };
+
+        self.sprites.sort_by(|(depth_a, sheet_a, _), (depth_b, sheet_b, _)| {
+            band(*depth_b).total_cmp(&band(*depth_a)).then(sheet_a.cmp(sheet_b)).then(depth_b.total_cmp(depth_a))
+        });
+    }
+}
+
+
+#[derive(Debug)]
+pub struct SpriteRenderer {
+    pub layout: BindGroupLayoutDsc,
+    pub pipeline: Arc<wgpu::RenderPipeline>,
+    shader: Arc<wgpu::ShaderModule>,
+    blend: BlendMode,
+    target: TargetKey,
+    sampler: (Arc<wgpu::Sampler>, &'static SamplerDsc),
+    params: wgpu::Buffer,
+    sheets: Vec<wgpu::BindGroup>,
+    instances: wgpu::Buffer,
+    capacity: usize,
+    offset: usize, // of the next batch in instances
+    calls: Vec<(SheetId, Range<u32>)>,
+}
+
+fn sprite_pipeline(
+    gx: &impl WgxDevice, shader: &wgpu::ShaderModule, layout: &BindGroupLayoutDsc, blend: BlendMode, target: TargetKey,
+) -> Arc<wgpu::RenderPipeline> {
+
+    // drawn in sort order without depth testing or writing
+    let depth_stencil = target.depth_testing.map(|format| wgpu::DepthStencilState {
+        depth_write_enabled: false, depth_compare: wgpu::CompareFunction::Always, ..depth_state(format)
+    });
+
+    gx.shared_render_pipeline(
+        target.msaa, depth_stencil, Some(&gx.shared_pipeline_layout(&[], &[&layout.layout])), &[SpriteInstance::LAYOUT],
+        (shader, "vs_main", Primitive { topology: Topology::TriangleStrip, ..Primitive::default() }),
+        Some((shader, "fs_main", &[(target.format, blend.blend())])),
+    )
+}
+
+impl SpriteRenderer {
+
+    pub fn new(gx: &impl WgxDevice, target: &impl RenderTarget, blend: BlendMode, filter: FilterMode) -> Self {
+
+        let shader = gx.shared_wgsl(include_str!("shaders/sprite.wgsl"));
+
+        let layout = gx.layout_dsc(&[
+            binding!(0, Stage::FRAGMENT, Texture, D2Array),
+            binding!(1, Stage::FRAGMENT, Sampler),
+            binding!(2, Stage::VERTEX, UniformBuffer, 64),
+        ]);
+
+        let target = TargetKey::of(target);
+        let pipeline = sprite_pipeline(gx, &shader, &layout, blend, target);
+
+        let sampler_dsc = match filter {
+            FilterMode::Nearest => &SamplerDsc::NEAREST,
+            FilterMode::Linear => &SamplerDsc::TRILINEAR,
+        };
+
+        let capacity = 256;
+
+        Self {
+            layout, pipeline, shader, blend, target,
+            sampler: (gx.shared_sampler(sampler_dsc), sampler_dsc),
+            params: gx.buffer(BufUse::UNIFORM | BufUse::COPY_DST, 64, false),
+            sheets: Vec::new(),
+            instances: gx.buffer(BufUse::VERTEX | BufUse::COPY_DST, (capacity * size_of::<SpriteInstance>()) as u64, false),
+            capacity, offset: 0, calls: Vec::new(),
+        }
+    }
+
+    // view has to be a D2Array view like the one of Atlas
+    pub fn add_sheet(&mut self, gx: &impl WgxDevice, view: &wgpu::TextureView) -> Res<SheetId> {
+        let binding = self.layout.bind(gx)
+            .texture(0, view)
+            .sampler_dsc(1, &self.sampler.0, self.sampler.1)
+            .buffer(2, &self.params)
+            .finish()?;
+
+        self.sheets.push(binding);
+        Ok(SheetId(self.sheets.len() - 1))
+    }
+
+    pub fn add_atlas<K>(&mut self, gx: &impl WgxDevice, atlas: &Atlas<K>) -> Res<SheetId> {
+        self.add_sheet(gx, &atlas.lot.view)
+    }
+
+    // follow the render target, e.g. after SurfaceTarget::set_msaa
+    pub fn update(&mut self, gx: &impl WgxDevice, target: &impl RenderTarget) {
+        let target = TargetKey::of(target);
+
+        if target != self.target {
+            self.target = target;
+            self.pipeline = sprite_pipeline(gx, &self.shader, &self.layout, self.blend, target);
+        }
+    }
+
+
+    // sorts and uploads the batch after the ones prepared since the last reset,
+    // the viewport of the last batch prepared before a submit applies to all of its draws
+    pub fn prepare(&mut self, gx: &impl WgxDeviceQueue, batch: &mut SpriteBatch) {
+        batch.sort();
+
+        let instances: Vec<SpriteInstance> = batch.sprites.iter().map(|(_, _, instance)| *instance).collect();
+        let len = instances.len();
+
+        // grows to the instances of the whole frame, draws recorded before keep using the old buffer
+        if self.offset + len > self.capacity {
+            self.capacity = (self.offset + len).next_power_of_two();
+            self.instances = gx.buffer(BufUse::VERTEX | BufUse::COPY_DST, (self.capacity * size_of::<SpriteInstance>()) as u64, false);
+            self.offset = 0;
+        }
+        if len > 0 {
+            gx.write_buffer(&self.instances, (self.offset * size_of::<SpriteInstance>()) as u64, instances.as_slice());
+        }
+        gx.write_buffer(&self.params, 0, batch.viewport);
+
+        // one call per run of the same sheet
+        let base = self.offset as u32;
+        self.calls.clear();
+
+        for (i, (_, sheet, _)) in batch.sprites.iter().enumerate() {
+            let i = base + i as u32;
+            match self.calls.last_mut() {
+                Some((last, range)) if last == sheet => range.end = i + 1,
+                _ => self.calls.push((*sheet, i..i + 1)),
+            }
+        }
+
+        self.offset += len;
+    }
+
+    // call after the queue was submitted, the next batch is written to the start of the buffer again
+    pub fn reset(&mut self) { self.offset = 0 }
+
+    pub fn draw(&self, rpass: &mut wgpu::RenderPass) {
+        if self.calls.is_empty() { return }
+
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_vertex_buffer(0, self.instances.slice(..));
+
+        // sheets of other renderers are skipped
+        for (sheet, instances) in &self.calls {
+            let Some(binding) = self.sheets.get(sheet.0) else { continue };
+            rpass.set_bind_group(0, binding, &[]);
+            rpass.draw(0..4, instances.clone());
+        }
+    }
+
+    // prepares, draws and clears the batch
+    pub fn flush(&mut self, gx: &impl WgxDeviceQueue, rpass: &mut wgpu::RenderPass, batch: &mut SpriteBatch) {
+        self.prepare(gx, batch);
+        self.draw(rpass);
+        batch.clear();
+    }
+}
@@ -0,0 +1,134 @@
+use wgpu::{StencilState, StencilFaceState, StencilOperation as Op, CompareFunction as Cmp, TextureFormat};
+use std::sync::Arc;
+use anyhow::{Result as Res, bail};
+use crate::*;
+
+
+// common stencil states, tested against the reference set with RenderPass::set_stencil_reference
+
+pub const fn stencil_face(compare: Cmp, pass_op: Op) -> StencilFaceState {
+    StencilFaceState { compare, fail_op: Op::Keep, depth_fail_op: Op::Keep, pass_op }
+}
+
+pub const fn stencil_state(face: StencilFaceState, read_mask: u32, write_mask: u32) -> StencilState {
+    StencilState { front: face, back: face, read_mask, write_mask }
+}
+
+// writes the reference where drawn
+pub const STENCIL_WRITE: StencilState = stencil_state(stencil_face(Cmp::Always, Op::Replace), 0xff, 0xff);
+
+// increments where drawn, e.g. for nested portals
+pub const STENCIL_INCREMENT: StencilState = stencil_state(stencil_face(Cmp::Always, Op::IncrementClamp), 0xff, 0xff);
+
+// draws only where the stencil equals the reference
+pub const STENCIL_EQUAL: StencilState = stencil_state(stencil_face(Cmp::Equal, Op::Keep), 0xff, 0x00);
+
+// draws only where the stencil differs from the reference
+pub const STENCIL_NOT_EQUAL: StencilState = stencil_state(stencil_face(Cmp::NotEqual, Op::Keep), 0xff, 0x00);
+
+
+pub fn stencil_depth_state(format: TextureFormat, stencil: StencilState) -> Res<wgpu::DepthStencilState> {
+    if !format.has_stencil_aspect() { bail!("{format:?} has no stencil aspect, use e.g. Depth24PlusStencil8") }
+    Ok(wgpu::DepthStencilState { stencil, ..depth_state(format) })
+}
+
+
+// pipeline with stencil state matching a render target
+pub fn stencil_pipeline(
+    gx: &impl WgxDevice, target: &impl RenderTarget,
+    layout: Option<&wgpu::PipelineLayout>,
+    buffers: &[wgpu::VertexBufferLayout],
+    vertex_state: VertexDsc,
+    (fs_module, fs_entry_point, blend, write_mask): BlendFragmentDsc,
+    depth_stencil: wgpu::DepthStencilState,
+) -> Arc<wgpu::RenderPipeline> {
+    gx.shared_render_pipeline(
+        target.msaa(), Some(depth_stencil), layout, buffers, vertex_state,
+        Some((fs_module, fs_entry_point, &[(target.view_format(), blend, write_mask)])),
+    )
+}
+
+// fragment stage without outputs, used with an empty write mask
+const MASK_WGSL: &str = "@fragment fn fs_main() {}";
+
+
+// two pipelines drawn with the same reference: the first marks the stencil, the second draws masked by it
+#[derive(Debug)]
+pub struct StencilPasses {
+    pub mask: Arc<wgpu::RenderPipeline>,
+    pub masked: Arc<wgpu::RenderPipeline>,
+}
+
+impl StencilPasses {
+
+    // object drawn normally, then an enlarged version (e.g. extruded along normals) only outside of it
+    // the outline ignores depth to stay visible
+    pub fn outline(
+        gx: &impl WgxDevice, target: &impl RenderTarget,
+        layout: Option<(&[wgpu::PushConstantRange], &[&wgpu::BindGroupLayout])>,
+        buffers: &[wgpu::VertexBufferLayout],
+        (object_vertex, object_fragment): (VertexDsc, BlendFragmentDsc),
+        (outline_vertex, outline_fragment): (VertexDsc, BlendFragmentDsc),
+    ) -> Res<Self> {
+        let Some(format) = target.depth_testing() else { bail!("render target has no depth stencil attachment") };
+
+        let layout = layout.map(|(push_constant_ranges, bind_group_layouts)|
+            gx.shared_pipeline_layout(push_constant_ranges, bind_group_layouts)
+        );
+
+        Ok(Self {
+            mask: stencil_pipeline(
+                gx, target, layout.as_deref(), buffers, object_vertex, object_fragment,
+                stencil_depth_state(format, STENCIL_WRITE)?,
+            ),
+            masked: stencil_pipeline(
+                gx, target, layout.as_deref(), buffers, outline_vertex, outline_fragment,
+                wgpu::DepthStencilState {
+                    depth_write_enabled: false, depth_compare: Cmp::Always,
+                    ..stencil_depth_state(format, STENCIL_NOT_EQUAL)?
+                },
+            ),
+        })
+    }
+
+    // portal surface marks the stencil without color or depth writes, the scene behind it is drawn inside only
+    pub fn portal(
+        gx: &impl WgxDevice, target: &impl RenderTarget,
+        layout: Option<(&[wgpu::PushConstantRange], &[&wgpu::BindGroupLayout])>,
+        buffers: &[wgpu::VertexBufferLayout],
+        portal_vertex: VertexDsc,
+        (scene_vertex, scene_fragment): (VertexDsc, BlendFragmentDsc),
+    ) -> Res<Self> {
+        let Some(format) = target.depth_testing() else { bail!("render target has no depth stencil attachment") };
+
+        let layout = layout.map(|(push_constant_ranges, bind_group_layouts)|
+            gx.shared_pipeline_layout(push_constant_ranges, bind_group_layouts)
+        );
+
+        let mask_shader = gx.shared_wgsl(MASK_WGSL);
+
+        Ok(Self {
+            mask: stencil_pipeline(
+                gx, target, layout.as_deref(), buffers, portal_vertex,
+                (&mask_shader, "fs_main", None, wgpu::ColorWrites::empty()),
+                wgpu::DepthStencilState { depth_write_enabled: false, ..stencil_depth_state(format, STENCIL_WRITE)? },
+            ),
+            masked: stencil_pipeline(
+                gx, target, layout.as_deref(), buffers, scene_vertex, scene_fragment,
+                stencil_depth_state(format, STENCIL_EQUAL)?,
+            ),
+        })
+    }
+
+    // sets the reference, draws the mask and then the masked geometry
+    pub fn render(
+        &self, rpass: &mut wgpu::RenderPass, reference: u32,
+        draw_mask: impl FnOnce(&mut wgpu::RenderPass), draw_masked: impl FnOnce(&mut wgpu::RenderPass),
+    ) {
+        rpass.set_stencil_reference(reference);
+        rpass.set_pipeline(&self.mask);
+        draw_mask(rpass);
+        rpass.set_pipeline(&self.masked);
+        draw_masked(rpass);
+    }
+}
@@ -0,0 +1,664 @@
+
+// lightweight text: glyphs of ttf/otf fonts are rasterised on the cpu into a growable atlas,
+// laid out with kerning, line breaking and alignment and drawn in 2d or 3d with one pipeline
+
+use std::{collections::HashMap, ops::Range, mem::size_of, sync::Arc};
+use ab_glyph::{Font as _, FontArc, ScaleFont as _, GlyphId, PxScale};
+use wgpu::TextureFormat;
+use anyhow::{Result as Res, anyhow, bail};
+use crate::{*, math::*, render_target::TargetKey};
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FontId(pub usize);
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GlyphMode {
+    Coverage, // rasterised for every size, sharpest for pixel aligned 2d text
+    Sdf { size: f32, spread: f32 }, // distance fields rasterised once at size, scale well e.g. in 3d
+}
+
+impl GlyphMode {
+    pub const SDF: Self = Self::Sdf { size: 48.0, spread: 6.0 };
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Align {
+    #[default] Left,
+    Center,
+    Right,
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextStyle {
+    pub font: FontId,
+    pub size: f32, // pixels from the fonts ascent to descent
+    pub color: Color,
+    pub align: Align,
+    pub line_height: f32, // relative to the fonts line height
+    pub max_width: Option<f32>, // wraps lines at whitespace or else between characters
+}
+
+impl TextStyle {
+    pub const fn new(font: FontId, size: f32) -> Self {
+        Self { font, size, color: Color::BLACK, align: Align::Left, line_height: 1.0, max_width: None }
+    }
+    pub const fn color(self, color: Color) -> Self { Self { color, ..self } }
+    pub const fn align(self, align: Align) -> Self { Self { align, ..self } }
+    pub const fn line_height(self, line_height: f32) -> Self { Self { line_height, ..self } }
+    pub const fn max_width(self, max_width: Option<f32>) -> Self { Self { max_width, ..self } }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct GlyphKey { font: FontId, id: u16, size: u32 }
+
+// rasterised glyph in the atlas
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlyphEntry {
+    pub rect: [u32; 4], // x, y, width, height in atlas pixels
+    pub offset: Vec2, // top left relative to the pen on the baseline, in raster pixels
+}
+
+
+// glyph of a line before alignment
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Placed { id: GlyphId, x: f32, advance: f32, space: bool }
+
+// wraps at max_width after the last space of the line, or else before the glyph
+fn break_lines(
+    text: &str, max_width: Option<f32>, glyph: impl Fn(char) -> GlyphId,
+    advance: impl Fn(GlyphId) -> f32, kern: impl Fn(GlyphId, GlyphId) -> f32,
+) -> Vec<Vec<Placed>> {
+
+    let mut lines: Vec<Vec<Placed>> = vec![Vec::new()];
+    let mut pen = 0.0;
+    let mut prev = None;
+
+    for c in text.chars() {
+        if c == '\n' {
+            lines.push(Vec::new());
+            (pen, prev) = (0.0, None);
+            continue;
+        }
+        if c.is_control() { continue }
+
+        let id = glyph(c);
+        if let Some(prev) = prev { pen += kern(prev, id) }
+
+        let advance = advance(id);
+        let space = c.is_whitespace();
+
+        if let Some(max_width) = max_width {
+            let line = lines.last_mut().unwrap();
+
+            if !space && pen + advance > max_width && !line.is_empty() {
+                let split = line.iter().rposition(|g| g.space).map_or(line.len(), |i| i + 1);
+                let mut rest: Vec<_> = line.drain(split..).collect();
+                let shift = rest.first().map_or(pen, |g| g.x);
+
+                for g in &mut rest { g.x -= shift }
+                pen -= shift;
+                lines.push(rest);
+            }
+        }
+
+        lines.last_mut().unwrap().push(Placed { id, x: pen, advance, space });
+        pen += advance;
+        prev = Some(id);
+    }
+
+    lines
+}
+
+// trailing whitespace doesn't count for the width
+fn line_width(line: &[Placed]) -> f32 {
+    line.iter().rev().find(|g| !g.space).map_or(0.0, |g| g.x + g.advance)
+}
+
+fn align_offset(align: Align, width: f32, line_width: f32) -> f32 {
+    match align {
+        Align::Left => 0.0,
+        Align::Center => (width - line_width) / 2.0,
+        Align::Right => width - line_width,
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayoutGlyph {
+    pub position: Vec2, // top left in pixels, relative to the top left of the text
+    pub size: Vec2,
+    pub rect: [u32; 4], // in the atlas
+    pub color: Color,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TextLayout {
+    pub glyphs: Vec<LayoutGlyph>,
+    pub size: Vec2, // width of the widest line or max_width, height of all lines
+    pub lines: u32,
+}
+
+
+// single channel atlas, grows in height when full
+// glyph rects stay valid when growing, uvs are normalized in the shader
+#[derive(Debug)]
+pub struct GlyphAtlas {
+    pub lot: TextureLot,
+    pixels: Vec<u8>,
+    packer: Skyline,
+    entries: HashMap<GlyphKey, Option<GlyphEntry>>,
+    dirty: Option<Range<u32>>, // rows
+    recreate: bool,
+    max_height: u32,
+}
+
+const ATLAS_FORMAT: TextureFormat = TextureFormat::R8Unorm;
+
+impl GlyphAtlas {
+
+    pub fn new(gx: &impl WgxDevice, [width, height]: [u32; 2]) -> Self {
+        Self {
+            lot: TextureLot::new_2d(gx, [width, height, 1], 1, ATLAS_FORMAT, None, TexUse::TEXTURE_BINDING | TexUse::COPY_DST),
+            pixels: vec![0; (width * height) as usize],
+            packer: Skyline::new([width, height]),
+            entries: HashMap::new(),
+            dirty: None, recreate: false,
+            max_height: gx.device().limits().max_texture_dimension_2d,
+        }
+    }
+
+    pub fn size(&self) -> [u32; 2] { [self.packer.width, self.packer.height] }
+    pub fn len(&self) -> usize { self.entries.len() }
+    pub fn is_empty(&self) -> bool { self.entries.is_empty() }
+
+    // removes all glyphs, e.g. after many sizes were used, layouts have to be recreated
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.pixels.fill(0);
+        self.packer = Skyline::new(self.size());
+        self.recreate = true;
+    }
+
+    fn insert(&mut self, [w, h]: [u32; 2], data: &[u8]) -> Res<[u32; 2]> {
+        let [x, y] = loop {
+            // one pixel padding against bleeding when sampled linearly
+            if let Some(position) = self.packer.insert([w + 1, h + 1]) { break position }
+
+            let [width, height] = self.size();
+            if height >= self.max_height || w + 1 > width {
+                bail!("glyph atlas is full at {width}x{height}");
+            }
+            self.packer.height = (height * 2).min(self.max_height);
+            self.pixels.resize((width * self.packer.height) as usize, 0);
+            self.recreate = true;
+        };
+
+        let width = self.packer.width;
+        for row in 0..h {
+            let dst = ((y + row) * width + x) as usize;
+            self.pixels[dst..dst + w as usize].copy_from_slice(&data[(row * w) as usize..][..w as usize]);
+        }
+
+        let rows = y..y + h;
+        self.dirty = Some(match self.dirty.take() {
+            Some(dirty) => dirty.start.min(rows.start)..dirty.end.max(rows.end),
+            None => rows,
+        });
+
+        Ok([x, y])
+    }
+
+    fn glyph(&mut self, font_id: FontId, font: &FontArc, id: GlyphId, size: f32, mode: GlyphMode) -> Res<Option<GlyphEntry>> {
+
+        let (size, pad) = match mode {
+            GlyphMode::Coverage => (size, 0),
+            GlyphMode::Sdf { size, spread } => (size, spread.ceil() as u32),
+        };
+
+        let key = GlyphKey { font: font_id, id: id.0, size: size.to_bits() };
+        if let Some(entry) = self.entries.get(&key) { return Ok(*entry) }
+
+        let entry = match font.outline_glyph(id.with_scale(PxScale::from(size))) {
+            None => None,
+            Some(outlined) => {
+                let bounds = outlined.px_bounds();
+                let [w, h] = [bounds.width() as u32 + 2 * pad, bounds.height() as u32 + 2 * pad];
+
+                let mut coverage = vec![0.0f32; (w * h) as usize];
+                outlined.draw(|x, y, c| {
+                    if let Some(value) = coverage.get_mut(((y + pad) * w + x + pad) as usize) { *value = c }
+                });
+
+                let data: Vec<u8> = match mode {
+                    GlyphMode::Coverage => coverage.iter().map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8).collect(),
+                    GlyphMode::Sdf { spread, .. } => distance_field(&coverage, [w, h], spread),
+                };
+
+                let [x, y] = self.insert([w, h], &data)?;
+
+                Some(GlyphEntry {
+                    rect: [x, y, w, h],
+                    offset: Vec2::new(bounds.min.x - pad as f32, bounds.min.y - pad as f32),
+                })
+            },
+        };
+
+        self.entries.insert(key, entry);
+        Ok(entry)
+    }
+
+    // uploads new glyphs, returns true if the texture was recreated
+    pub fn upload(&mut self, gx: &impl WgxDeviceQueue) -> bool {
+        let [width, height] = self.size();
+
+        if self.recreate {
+            self.lot = TextureLot::new_2d_with_data(
+                gx, [width, height, 1], 1, ATLAS_FORMAT, None, TexUse::TEXTURE_BINDING | TexUse::COPY_DST, self.pixels.as_slice(),
+            );
+            self.recreate = false;
+            self.dirty = None;
+            return true;
+        }
+
+        if let Some(rows) = self.dirty.take() {
+            gx.write_texture(
+                (&self.lot.texture, 0, [0, rows.start, 0]),
+                (&self.pixels[(rows.start * width) as usize..(rows.end * width) as usize], (0, (ATLAS_FORMAT, width), None)),
+                [width, rows.len() as u32, 1],
+            );
+        }
+        false
+    }
+}
+
+
+// squared euclidean distance transform of one row or column (Felzenszwalb & Huttenlocher)
+fn distance_transform_1d(f: &[f32], d: &mut [f32], v: &mut [usize], z: &mut [f32]) {
+    let n = f.len();
+    let mut k = 0;
+    v[0] = 0;
+    z[0] = f32::NEG_INFINITY;
+    z[1] = f32::INFINITY;
+
+    for q in 1..n {
+        let mut s;
+        loop {
+            let p = v[k];
+            s = ((f[q] + (q * q) as f32) - (f[p] + (p * p) as f32)) / (2 * q - 2 * p) as f32;
+            if s <= z[k] && k > 0 { k -= 1 } else { break }
+        }
+        if s <= z[k] { v[0] = q; z[0] = f32::NEG_INFINITY; k = 0 }
+        else { k += 1; v[k] = q; z[k] = s; }
+        z[k + 1] = f32::INFINITY;
+    }
+
+    k = 0;
+    for (q, d) in d.iter_mut().enumerate() {
+        while z[k + 1] < q as f32 { k += 1 }
+        let p = v[k];
+        *d = (q as f32 - p as f32).powi(2) + f[p];
+    }
+}
+
+fn distance_transform(mut grid: Vec<f32>, [w, h]: [u32; 2]) -> Vec<f32> {
+    let (w, h) = (w as usize, h as usize);
+    let n = w.max(h);
+    let (mut f, mut d, mut v, mut z) = (vec![0.0; n], vec![0.0; n], vec![0; n], vec![0.0; n + 1]);
+
+    for x in 0..w {
+        for y in 0..h { f[y] = grid[y * w + x] }
+        distance_transform_1d(&f[..h], &mut d[..h], &mut v, &mut z);
+        for y in 0..h { grid[y * w + x] = d[y] }
+    }
+    for row in grid.chunks_exact_mut(w) {
+        f[..w].copy_from_slice(row);
+        distance_transform_1d(&f[..w], &mut d[..w], &mut v, &mut z);
+        row.copy_from_slice(&d[..w]);
+    }
+    grid
+}
+
+// signed distance to the glyph edge mapped to 0..255, 128 on the edge, inside is brighter
+fn distance_field(coverage: &[f32], size: [u32; 2], spread: f32) -> Vec<u8> {
+    const FAR: f32 = 1e10;
+    let inside = |c: f32| c >= 0.5;
+
+    let to_inside = distance_transform(coverage.iter().map(|&c| if inside(c) { 0.0 } else { FAR }).collect(), size);
+    let to_outside = distance_transform(coverage.iter().map(|&c| if inside(c) { FAR } else { 0.0 }).collect(), size);
+
+    to_inside.iter().zip(&to_outside).map(|(&a, &b)| {
+        let distance = b.sqrt() - a.sqrt(); // positive inside
+        ((0.5 + distance / (2.0 * spread)).clamp(0.0, 1.0) * 255.0).round() as u8
+    }).collect()
+}
+
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct TextVertex {
+    pub position: [f32; 4], // clip space
+    pub uv: [f32; 2], // atlas pixels
+    pub color: [f32; 4],
+}
+
+unsafe impl ReadBytes for TextVertex {}
+
+impl TextVertex {
+    pub const LAYOUT: wgpu::VertexBufferLayout<'static> = vertex_dsc!(Vertex, 0 => Float32x4, 1 => Float32x2, 2 => Float32x4);
+}
+
+
+fn text_pipeline(
+    gx: &impl WgxDevice, shader: &wgpu::ShaderModule, layout: &BindGroupLayoutDsc, target: TargetKey,
+) -> Arc<wgpu::RenderPipeline> {
+
+    // tested against the scene in 3d, but never written
+    let depth_stencil = target.depth_testing.map(|format| wgpu::DepthStencilState {
+        depth_write_enabled: false, ..depth_state(format)
+    });
+
+    gx.shared_render_pipeline(
+        target.msaa, depth_stencil, Some(&gx.shared_pipeline_layout(&[], &[&layout.layout])), &[TextVertex::LAYOUT],
+        (shader, "vs_main", Primitive::default()),
+        Some((shader, "fs_main", &[(target.format, Some(Blend::ALPHA_BLENDING))])),
+    )
+}
+
+
+// fonts, glyph atlas and the text drawn until the next flush
+// vertices are appended to the buffer until reset is called after the queue was submitted
+#[derive(Debug)]
+pub struct TextRenderer {
+    pub mode: GlyphMode,
+    pub atlas: GlyphAtlas,
+    pub layout: BindGroupLayoutDsc,
+    pub pipeline: Arc<wgpu::RenderPipeline>,
+    shader: Arc<wgpu::ShaderModule>,
+    target: TargetKey,
+    fonts: Vec<FontArc>,
+    sampler: Arc<wgpu::Sampler>,
+    params: wgpu::Buffer,
+    binding: wgpu::BindGroup,
+    viewport: Mat4,
+    vertices: Vec<TextVertex>,
+    buffer: wgpu::Buffer,
+    capacity: usize,
+    offset: usize, // vertices written since the last reset
+    prepared: Range<u32>,
+}
+
+impl TextRenderer {
+
+    pub fn new(gx: &impl WgxDeviceQueue, target: &impl RenderTarget, mode: GlyphMode) -> Res<Self> {
+
+        let shader = gx.shared_wgsl(include_str!("shaders/text.wgsl"));
+
+        let layout = gx.layout_dsc(&[
+            binding!(0, Stage::VERTEX_FRAGMENT, Texture, D2),
+            binding!(1, Stage::FRAGMENT, Sampler),
+            binding!(2, Stage::FRAGMENT, UniformBuffer, 16),
+        ]);
+
+        let key = TargetKey::of(target);
+        let pipeline = text_pipeline(gx, &shader, &layout, key);
+
+        let atlas = GlyphAtlas::new(gx, [1024, 256]);
+        let sampler = gx.shared_sampler(&SamplerDsc::LINEAR);
+        let params = gx.buffer_from_data(BufUse::UNIFORM, [matches!(mode, GlyphMode::Sdf {..}) as u32, 0, 0, 0]);
+
+        let binding = layout.bind(gx)
+            .texture_lot(0, &atlas.lot)
+            .sampler_dsc(1, &sampler, &SamplerDsc::LINEAR)
+            .buffer(2, &params)
+            .finish()?;
+
+        let capacity = 1024;
+        let [width, height] = target.size();
+
+        Ok(Self {
+            mode, atlas, layout, pipeline, shader, target: key,
+            fonts: Vec::new(), sampler, params, binding,
+            viewport: Mat4::flat_viewport_lh(width as f32, height as f32, 0.0),
+            vertices: Vec::new(),
+            buffer: gx.buffer(BufUse::VERTEX | BufUse::COPY_DST, (capacity * size_of::<TextVertex>()) as u64, false),
+            capacity, offset: 0, prepared: 0..0,
+        })
+    }
+
+    // ttf or otf data
+    pub fn add_font(&mut self, data: impl Into<Vec<u8>>) -> Res<FontId> {
+        let font = FontArc::try_from_vec(data.into()).map_err(|_| anyhow!("invalid font data"))?;
+        self.fonts.push(font);
+        Ok(FontId(self.fonts.len() - 1))
+    }
+
+    pub fn font(&self, id: FontId) -> Option<&FontArc> { self.fonts.get(id.0) }
+
+    // size of the 2d viewport in pixels
+    pub fn resize(&mut self, size: [f32; 2]) {
+        self.viewport = Mat4::flat_viewport_lh(size[0], size[1], 0.0);
+    }
+
+    // follow the render target, e.g. after SurfaceTarget::set_msaa
+    pub fn update(&mut self, gx: &impl WgxDevice, target: &impl RenderTarget) {
+        let target = TargetKey::of(target);
+
+        if target != self.target {
+            self.target = target;
+            self.pipeline = text_pipeline(gx, &self.shader, &self.layout, target);
+        }
+    }
+
+
+    // shapes the text with the fonts kerning, breaks and aligns lines and rasterises missing glyphs
+    pub fn layout(&mut self, text: &str, style: &TextStyle) -> Res<TextLayout> {
+
+        let font = self.font(style.font).ok_or_else(|| anyhow!("unknown font {:?}", style.font))?.clone();
+        let scaled = font.as_scaled(PxScale::from(style.size));
+
+        let lines = break_lines(
+            text, style.max_width, |c| font.glyph_id(c), |id| scaled.h_advance(id), |a, b| scaled.kern(a, b),
+        );
+
+        let widths: Vec<f32> = lines.iter().map(|line| line_width(line)).collect();
+
+        let width = style.max_width.unwrap_or_else(|| widths.iter().copied().fold(0.0, f32::max));
+        let line_height = (scaled.height() + scaled.line_gap()) * style.line_height;
+
+        let (scale, snap) = match self.mode {
+            GlyphMode::Coverage => (1.0, true),
+            GlyphMode::Sdf { size, .. } => (style.size / size, false),
+        };
+
+        let mut glyphs = Vec::with_capacity(text.len());
+
+        for (i, (line, line_width)) in lines.iter().zip(&widths).enumerate() {
+
+            let offset = align_offset(style.align, width, *line_width);
+
+            let baseline = i as f32 * line_height + scaled.ascent();
+
+            for placed in line.iter().filter(|g| !g.space) {
+                let Some(entry) = self.atlas.glyph(style.font, &font, placed.id, style.size, self.mode)? else { continue };
+
+                let mut pen = Vec2::new(offset + placed.x, baseline);
+                if snap { pen = pen.round() }
+
+                glyphs.push(LayoutGlyph {
+                    position: pen + entry.offset * scale,
+                    size: Vec2::new(entry.rect[2] as f32, entry.rect[3] as f32) * scale,
+                    rect: entry.rect,
+                    color: style.color,
+                });
+            }
+        }
+
+        Ok(TextLayout { glyphs, size: Vec2::new(width, lines.len() as f32 * line_height), lines: lines.len() as u32 })
+    }
+
+
+    // drawing, matrix maps the layouts pixels into clip space
+
+    pub fn draw_transformed(&mut self, layout: &TextLayout, matrix: Mat4) {
+        for glyph in &layout.glyphs {
+            let [x, y, w, h] = glyph.rect.map(|v| v as f32);
+            let color = glyph.color.f32();
+
+            let corner = |f: Vec2| TextVertex {
+                position: (matrix * (glyph.position + glyph.size * f).extend(0.0).extend(1.0)).into(),
+                uv: [x + w * f.x, y + h * f.y],
+                color,
+            };
+            let [a, b, c, d] = [Vec2::ZERO, Vec2::X, Vec2::ONE, Vec2::Y].map(corner);
+
+            self.vertices.extend([a, b, c, a, c, d]);
+        }
+    }
+
+    // at the top left position in viewport pixels
+    pub fn draw_2d(&mut self, layout: &TextLayout, position: impl Into<Vec2>) {
+        self.draw_transformed(layout, self.viewport * Mat4::from_translation(position.into().extend(0.0)));
+    }
+
+    // in the xy-plane of the model matrix with y up, pixel_size in world units
+    pub fn draw_3d(&mut self, layout: &TextLayout, view_projection: Mat4, model: Mat4, pixel_size: f32) {
+        self.draw_transformed(layout, view_projection * model * Mat4::from_scale(Vec3::new(pixel_size, -pixel_size, 1.0)));
+    }
+
+    pub fn text_2d(&mut self, text: &str, style: &TextStyle, position: impl Into<Vec2>) -> Res<TextLayout> {
+        let layout = self.layout(text, style)?;
+        self.draw_2d(&layout, position);
+        Ok(layout)
+    }
+
+
+    // uploads glyphs and the vertices behind the ones prepared since the last reset,
+    // the buffer is written when the queue is submitted, so each prepared run keeps its own range
+    pub fn prepare(&mut self, gx: &impl WgxDeviceQueue) -> Res<()> {
+
+        if self.atlas.upload(gx) {
+            self.binding = self.layout.bind(gx)
+                .texture_lot(0, &self.atlas.lot)
+                .sampler_dsc(1, &self.sampler, &SamplerDsc::LINEAR)
+                .buffer(2, &self.params)
+                .finish()?;
+        }
+
+        let len = self.vertices.len();
+
+        // draws recorded before keep using the old buffer
+        if self.offset + len > self.capacity {
+            self.capacity = len.max(self.capacity).next_power_of_two();
+            self.buffer = gx.buffer(BufUse::VERTEX | BufUse::COPY_DST, (self.capacity * size_of::<TextVertex>()) as u64, false);
+            self.offset = 0;
+        }
+        if len > 0 {
+            gx.write_buffer(&self.buffer, (self.offset * size_of::<TextVertex>()) as u64, self.vertices.as_slice());
+        }
+
+        self.prepared = self.offset as u32..(self.offset + len) as u32;
+        self.offset += len;
+        self.vertices.clear();
+        Ok(())
+    }
+
+    // call after the queue was submitted, the next vertices are written to the start of the buffer again
+    pub fn reset(&mut self) { self.offset = 0 }
+
+    pub fn draw(&self, rpass: &mut wgpu::RenderPass) {
+        if self.prepared.is_empty() { return }
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &self.binding, &[]);
+        rpass.set_vertex_buffer(0, self.buffer.slice(..));
+        rpass.draw(self.prepared.clone(), 0..1);
+    }
+
+    pub fn flush(&mut self, gx: &impl WgxDeviceQueue, rpass: &mut wgpu::RenderPass) -> Res<()> {
+        self.prepare(gx)?;
+        self.draw(rpass);
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // monospaced glyphs with the char as id
+    fn lines(text: &str, max_width: Option<f32>, kerning: f32) -> Vec<Vec<Placed>> {
+        break_lines(text, max_width, |c| GlyphId(c as u16), |_| 10.0, |_, _| kerning)
+    }
+
+    fn chars(line: &[Placed]) -> String {
+        line.iter().map(|g| char::from_u32(g.id.0 as u32).unwrap()).collect()
+    }
+
+    fn xs(line: &[Placed]) -> Vec<f32> { line.iter().map(|g| g.x).collect() }
+
+    #[test]
+    fn breaks_at_newlines() {
+        let lines = lines("ab\ncd\n", None, 0.0);
+        assert_eq!(lines.iter().map(|line| chars(line)).collect::<Vec<_>>(), ["ab", "cd", ""]);
+        assert_eq!(xs(&lines[1]), [0.0, 10.0]);
+    }
+
+    #[test]
+    fn wraps_after_last_space() {
+        let lines = lines("ab cd ef", Some(55.0), 0.0);
+        assert_eq!(lines.iter().map(|line| chars(line)).collect::<Vec<_>>(), ["ab cd ", "ef"]);
+        assert_eq!(xs(&lines[1]), [0.0, 10.0]);
+
+        // the trailing space doesn't count
+        assert_eq!(line_width(&lines[0]), 50.0);
+    }
+
+    #[test]
+    fn wraps_long_words_between_chars() {
+        let lines = lines("abcdefg", Some(25.0), 0.0);
+        assert_eq!(lines.iter().map(|line| chars(line)).collect::<Vec<_>>(), ["ab", "cd", "ef", "g"]);
+        assert!(lines.iter().all(|line| line[0].x == 0.0));
+    }
+
+    #[test]
+    fn applies_kerning() {
+        let lines = lines("abc\nd", None, -2.0);
+        assert_eq!(xs(&lines[0]), [0.0, 8.0, 16.0]);
+        assert_eq!(xs(&lines[1]), [0.0]);
+        assert_eq!(line_width(&lines[0]), 26.0);
+    }
+
+    #[test]
+    fn aligns_lines() {
+        assert_eq!(align_offset(Align::Left, 100.0, 40.0), 0.0);
+        assert_eq!(align_offset(Align::Center, 100.0, 40.0), 30.0);
+        assert_eq!(align_offset(Align::Right, 100.0, 40.0), 60.0);
+    }
+
+    #[test]
+    fn distance_field_of_square() {
+        // 3x3 square in the center of 9x9
+        let coverage: Vec<f32> = (0..81).map(|i| if (3..6).contains(&(i % 9)) && (3..6).contains(&(i / 9)) { 1.0 } else { 0.0 }).collect();
+        let field = distance_field(&coverage, [9, 9], 2.0);
+        let at = |x: usize, y: usize| field[y * 9 + x];
+
+        assert_eq!(at(4, 4), 255); // 2 pixels inside
+        assert_eq!(at(3, 4), 191); // on the inner edge
+        assert_eq!(at(2, 4), 64); // next to the edge
+        assert_eq!(at(0, 0), 0);
+
+        // symmetric
+        assert_eq!(at(5, 4), at(3, 4));
+        assert_eq!(at(4, 6), at(4, 2));
+    }
+
+    #[test]
+    fn distance_field_without_glyph() {
+        assert!(distance_field(&[0.0; 16], [4, 4], 2.0).iter().all(|&d| d == 0));
+    }
+}
@@ -8,6 +8,7 @@ use crate::*;
 pub const DEFAULT_SRGB: TextureFormat = TextureFormat::Rgba8UnormSrgb;
 pub const DEFAULT_LINEAR: TextureFormat = TextureFormat::Rgba8Unorm;
 pub const DEFAULT_DEPTH: TextureFormat = TextureFormat::Depth32Float;
+pub const DEFAULT_HDR: TextureFormat = TextureFormat::Rgba16Float;
 
 
 // extend Texture
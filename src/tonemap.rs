@@ -0,0 +1,131 @@
+use std::sync::Arc;
+use anyhow::{Result as Res};
+use crate::{*, render_target::TargetKey};
+
+
+// tonemapping operators
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Tonemap {
+    Clamp,
+    Reinhard,
+    #[default] Aces,
+    AgX,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct Params { exposure: f32, peak: f32, tonemap: u32, flags: u32 }
+
+unsafe impl ReadBytes for Params {}
+
+
+// draws a hdr texture (e.g. a TextureTarget with DEFAULT_HDR format) to a render target
+// Rgb10a2Unorm surfaces get sdr srgb output with 10 bit precision, wgpu can't select hdr10 (pq, bt.2020) presentation
+#[derive(Debug)]
+pub struct TonemapPass {
+    pub tonemap: Tonemap,
+    pub exposure: f32,
+    pub peak: f32, // brightness mapped to the operators white relative to sdr white, 1.0 for sdr output
+    pub color_space: ColorSpace,
+    pub layout: BindGroupLayoutDsc,
+    pub pipeline: Arc<wgpu::RenderPipeline>,
+    pub sampler: Arc<wgpu::Sampler>,
+    pub params: wgpu::Buffer,
+    shader: Arc<wgpu::ShaderModule>,
+    target: TargetKey,
+    encode_srgb: bool,
+}
+
+fn tonemap_pipeline(
+    gx: &impl WgxDevice, shader: &wgpu::ShaderModule, layout: &BindGroupLayoutDsc, target: TargetKey,
+) -> Arc<wgpu::RenderPipeline> {
+    gx.shared_render_pipeline(
+        target.msaa, None, Some(&gx.shared_pipeline_layout(&[], &[&layout.layout])), &[],
+        (shader, "vs_main", Primitive::default()),
+        Some((shader, "fs_main", &[(target.format, None)])),
+    )
+}
+
+// srgb views and float formats take linear values
+fn encode_srgb(color_space: ColorSpace, view_format: TextureFormat) -> bool {
+    color_space == ColorSpace::Srgb && !view_format.is_srgb() && !is_float_format(view_format)
+}
+
+impl TonemapPass {
+
+    pub fn new(gx: &impl WgxDeviceQueue, target: &impl RenderTarget, color_space: ColorSpace, tonemap: Tonemap) -> Self {
+
+        let shader = gx.shared_wgsl(include_str!("shaders/tonemap.wgsl"));
+
+        let layout = gx.layout_dsc(&[
+            binding!(0, Stage::FRAGMENT, Texture, D2),
+            binding!(1, Stage::FRAGMENT, Sampler),
+            binding!(2, Stage::FRAGMENT, UniformBuffer, 16),
+        ]);
+
+        let target = TargetKey::of(target);
+        let pipeline = tonemap_pipeline(gx, &shader, &layout, target);
+
+        let pass = Self {
+            tonemap, exposure: 1.0, peak: if color_space.is_hdr() { 4.0 } else { 1.0 }, color_space,
+            layout, pipeline,
+            sampler: gx.shared_sampler(&SamplerDsc::LINEAR),
+            params: gx.buffer(BufUse::UNIFORM | BufUse::COPY_DST, 16, false),
+            shader, target,
+            encode_srgb: encode_srgb(color_space, target.format),
+        };
+
+        pass.write_params(gx);
+        pass
+    }
+
+    // tonemapping to a surface, using the surface color space
+    pub fn new_for_surface(gx: &impl WgxDeviceQueue, target: &SurfaceTarget, tonemap: Tonemap) -> Self {
+        Self::new(gx, target, target.color_space(), tonemap)
+    }
+
+    // follow the render target, e.g. after SurfaceTarget::set_msaa or set_srgb
+    pub fn update(&mut self, gx: &impl WgxDeviceQueue, target: &impl RenderTarget) {
+        let target = TargetKey::of(target);
+
+        if target != self.target {
+            self.target = target;
+            self.pipeline = tonemap_pipeline(gx, &self.shader, &self.layout, target);
+            self.encode_srgb = encode_srgb(self.color_space, target.format);
+            self.write_params(gx);
+        }
+    }
+
+    fn params(&self) -> Params {
+        Params {
+            exposure: self.exposure, peak: self.peak,
+            tonemap: self.tonemap as u32,
+            flags: self.color_space.is_hdr() as u32 | (self.encode_srgb as u32) << 1,
+        }
+    }
+
+    // upload tonemap, exposure and peak, call after changing them
+    pub fn write_params(&self, gx: &impl WgxQueue) {
+        gx.write_buffer(&self.params, 0, self.params());
+    }
+
+    pub fn bind(&self, gx: &impl WgxDevice, src: &wgpu::TextureView) -> Res<wgpu::BindGroup> {
+        self.layout.bind(gx)
+            .texture(0, src)
+            .sampler(1, &self.sampler)
+            .buffer(2, &self.params)
+            .finish()
+    }
+
+    pub fn render(&self, encoder: &mut wgpu::CommandEncoder, target: &impl RenderAttachable, binding: &wgpu::BindGroup) {
+        let mut rpass = encoder.render_pass(([Some(target.color_attachment(None))], None));
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, binding, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+}
+
+
+fn is_float_format(format: TextureFormat) -> bool {
+    matches!(format, TextureFormat::Rgba16Float | TextureFormat::Rgba32Float | TextureFormat::Rg11b10Float)
+}
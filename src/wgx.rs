@@ -1,7 +1,7 @@
 
 use arrayvec::ArrayVec;
 use wgpu::util::{DeviceExt, TextureDataOrder};
-use std::{ops::{RangeBounds, Bound}, borrow::Cow};
+use std::{ops::{RangeBounds, Bound}, borrow::Cow, sync::Arc};
 use crate::{*};
 use anyhow::{Result as Res, Context, anyhow};
 
@@ -77,11 +77,43 @@ impl Wgx {
 }
 
 
+// color target of a fragment shader, (format, blend) writes all channels
+pub trait ColorTarget {
+    fn color_target_state(&self) -> wgpu::ColorTargetState;
+}
+
+impl ColorTarget for (wgpu::TextureFormat, Option<Blend>) {
+    fn color_target_state(&self) -> wgpu::ColorTargetState {
+        (self.0, self.1, wgpu::ColorWrites::ALL).color_target_state()
+    }
+}
+
+impl ColorTarget for (wgpu::TextureFormat, Option<Blend>, wgpu::ColorWrites) {
+    fn color_target_state(&self) -> wgpu::ColorTargetState {
+        wgpu::ColorTargetState { format: self.0, blend: self.1, write_mask: self.2 }
+    }
+}
+
+impl ColorTarget for wgpu::ColorTargetState {
+    fn color_target_state(&self) -> wgpu::ColorTargetState { self.clone() }
+}
+
+// fragment shader with color target formats and blend states
+pub type FragmentDsc<'a, const S: usize, T = (wgpu::TextureFormat, Option<Blend>)> = (&'a wgpu::ShaderModule, &'a str, &'a [T; S]);
+
+// vertex shader with primitive state, fragment shader with blend state and write mask for a single target
+pub type VertexDsc<'a> = (&'a wgpu::ShaderModule, &'a str, Primitive);
+pub type BlendFragmentDsc<'a> = (&'a wgpu::ShaderModule, &'a str, Option<Blend>, wgpu::ColorWrites);
+
+
 // device methods
 pub trait WgxDevice {
 
     fn device(&self) -> &wgpu::Device;
 
+    // for adapter specific queries like supported_msaa, None falls back to guaranteed features
+    fn adapter(&self) -> Option<&wgpu::Adapter> { None }
+
     // texture, sampler
 
     fn texture(&self, descriptor:&TexDsc) -> wgpu::Texture {
@@ -92,21 +124,12 @@ pub trait WgxDevice {
         self.device().create_sampler(descriptor)
     }
 
+    fn sampler_dsc(&self, descriptor: &SamplerDsc) -> wgpu::Sampler {
+        self.device().create_sampler(&descriptor.descriptor())
+    }
+
     fn default_sampler(&self) -> wgpu::Sampler {
-        self.device().create_sampler(&wgpu::SamplerDescriptor {
-            label: None,
-            border_color: None,
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Linear,
-            mipmap_filter: wgpu::FilterMode::Linear,
-            lod_min_clamp: 0.0,
-            lod_max_clamp: 100.0,
-            compare: None, // Some(wgpu::CompareFunction::LessEqual),
-            anisotropy_clamp: 1,
-        })
+        self.sampler_dsc(&SamplerDsc::default())
     }
 
 
@@ -139,6 +162,10 @@ pub trait WgxDevice {
         })
     }
 
+    fn layout_dsc(&self, entries:&[wgpu::BindGroupLayoutEntry]) -> BindGroupLayoutDsc {
+        BindGroupLayoutDsc { layout: self.shared_layout(entries), entries: entries.to_vec() }
+    }
+
     fn bind(&self, layout:&wgpu::BindGroupLayout, entries:&[wgpu::BindGroupEntry]) -> wgpu::BindGroup {
         self.device().create_bind_group(&wgpu::BindGroupDescriptor {
             layout, entries, label: None
@@ -152,19 +179,33 @@ pub trait WgxDevice {
 
 
     // render bundle
+    // the stencil is read only, bundles with stencil writing pipelines are created with render_bundle_with_state
 
     fn render_bundle<'a>(&'a self,
         formats: &[Option<wgpu::TextureFormat>], depth_testing:Option<wgpu::TextureFormat>, msaa:u32,
         handler: impl FnOnce(&mut wgpu::RenderBundleEncoder<'a>),
     )
         -> wgpu::RenderBundle
+    {
+        self.render_bundle_with_state(
+            formats,
+            depth_testing.map(|format| wgpu::RenderBundleDepthStencil {
+                format, depth_read_only: false, stencil_read_only: true,
+            }),
+            msaa, handler,
+        )
+    }
+
+    fn render_bundle_with_state<'a>(&'a self,
+        formats: &[Option<wgpu::TextureFormat>], depth_stencil:Option<wgpu::RenderBundleDepthStencil>, msaa:u32,
+        handler: impl FnOnce(&mut wgpu::RenderBundleEncoder<'a>),
+    )
+        -> wgpu::RenderBundle
     {
         let mut encoder = self.device().create_render_bundle_encoder(&wgpu::RenderBundleEncoderDescriptor {
             label: None,
             color_formats: formats,
-            depth_stencil: depth_testing.map(|format| wgpu::RenderBundleDepthStencil {
-                format, depth_read_only: false, stencil_read_only: true,
-            }),
+            depth_stencil,
             sample_count: msaa,
             multiview: None,
         });
@@ -175,23 +216,38 @@ pub trait WgxDevice {
     }
 
 
+    // pipeline layout
+
+    fn pipeline_layout(
+        &self, push_constant_ranges: &[wgpu::PushConstantRange], bind_group_layouts: &[&wgpu::BindGroupLayout],
+    ) -> wgpu::PipelineLayout {
+        self.device().create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None, push_constant_ranges, bind_group_layouts,
+        })
+    }
+
+
     // compute pipeline
 
     fn compute_pipeline(
         &self,
         layout:Option<(&[wgpu::PushConstantRange], &[&wgpu::BindGroupLayout])>,
-        (module, entry_point):(&wgpu::ShaderModule, &str),
+        module_entry:(&wgpu::ShaderModule, &str),
     ) -> wgpu::ComputePipeline {
 
         let layout = layout.map(|(push_constant_ranges, bind_group_layouts)|
-            self.device().create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: None, push_constant_ranges, bind_group_layouts,
-            })
+            self.shared_pipeline_layout(push_constant_ranges, bind_group_layouts)
         );
 
+        self.compute_pipeline_with_layout(layout.as_deref(), module_entry)
+    }
+
+    fn compute_pipeline_with_layout(
+        &self, layout:Option<&wgpu::PipelineLayout>, (module, entry_point):(&wgpu::ShaderModule, &str),
+    ) -> wgpu::ComputePipeline {
         self.device().create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
             label: None,
-            layout: layout.as_ref(),
+            layout,
             module, entry_point,
             compilation_options: wgpu::PipelineCompilationOptions::default(),
             cache: None,
@@ -201,17 +257,44 @@ pub trait WgxDevice {
 
     // render pipeline
 
-    fn render_pipeline<const S: usize>(
+    fn render_pipeline<const S: usize, T: ColorTarget>(
         &self,
         msaa: u32, depth_testing: Option<wgpu::TextureFormat>,
         layout: Option<(&[wgpu::PushConstantRange], &[&wgpu::BindGroupLayout])>,
         buffers: &[wgpu::VertexBufferLayout],
+        vertex: (&wgpu::ShaderModule, &str, wgpu::PrimitiveState),
+        fragment: Option<FragmentDsc<S, T>>,
+    ) -> wgpu::RenderPipeline {
+
+        let layout = layout.map(|(push_constant_ranges, bind_group_layouts)|
+            self.shared_pipeline_layout(push_constant_ranges, bind_group_layouts)
+        );
+
+        self.render_pipeline_with_layout(msaa, depth_testing, layout.as_deref(), buffers, vertex, fragment)
+    }
+
+    fn render_pipeline_with_layout<const S: usize, T: ColorTarget>(
+        &self,
+        msaa: u32, depth_testing: Option<wgpu::TextureFormat>,
+        layout: Option<&wgpu::PipelineLayout>,
+        buffers: &[wgpu::VertexBufferLayout],
+        vertex: (&wgpu::ShaderModule, &str, wgpu::PrimitiveState),
+        fragment: Option<FragmentDsc<S, T>>,
+    ) -> wgpu::RenderPipeline {
+        self.render_pipeline_with_state(msaa, depth_testing.map(depth_state), layout, buffers, vertex, fragment)
+    }
+
+    // full control over depth, stencil and bias
+    fn render_pipeline_with_state<const S: usize, T: ColorTarget>(
+        &self,
+        msaa: u32, depth_stencil: Option<wgpu::DepthStencilState>,
+        layout: Option<&wgpu::PipelineLayout>,
+        buffers: &[wgpu::VertexBufferLayout],
         (module, entry_point, primitive): (&wgpu::ShaderModule, &str, wgpu::PrimitiveState),
-        fragment: Option<(&wgpu::ShaderModule, &str, &[(wgpu::TextureFormat, Option<Blend>); S])>,
+        fragment: Option<FragmentDsc<S, T>>,
     ) -> wgpu::RenderPipeline {
 
         // cache temporar values
-        let pipeline_layout;
         let targets: ArrayVec<_, S>;
 
 
@@ -220,15 +303,7 @@ pub trait WgxDevice {
             label: None,
             cache: None,
 
-            layout: if let Some((push_constant_ranges, bind_group_layouts)) = layout {
-
-                pipeline_layout = self.device().create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                    label: None, push_constant_ranges, bind_group_layouts,
-                });
-
-                Some(&pipeline_layout)
-            }
-            else { None },
+            layout,
 
             vertex: wgpu::VertexState {
                 module, entry_point, buffers,
@@ -239,11 +314,7 @@ pub trait WgxDevice {
 
             fragment: if let Some((module, entry_point, formats)) = fragment {
 
-                targets = formats.iter().map(|(format, blend)| Some(wgpu::ColorTargetState {
-                    format: *format,
-                    blend: *blend,
-                    write_mask: wgpu::ColorWrites::ALL,
-                })).collect();
+                targets = formats.iter().map(|target| Some(target.color_target_state())).collect();
 
                 Some(wgpu::FragmentState {
                     module, entry_point, targets: &targets,
@@ -252,13 +323,7 @@ pub trait WgxDevice {
             }
             else {None},
 
-            depth_stencil: depth_testing.map(|format| wgpu::DepthStencilState {
-                format,
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::LessEqual,
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState::default(),
-            }),
+            depth_stencil,
 
             multisample: wgpu::MultisampleState {
                 count: msaa, mask: !0, alpha_to_coverage_enabled: false,
@@ -268,6 +333,57 @@ pub trait WgxDevice {
 
         })
     }
+
+
+    // shared objects, created anew here and deduplicated by WgxCache
+    // the methods above return owned objects, which can't be shared, and always create new ones
+
+    fn shared_layout(&self, entries:&[wgpu::BindGroupLayoutEntry]) -> Arc<wgpu::BindGroupLayout> {
+        Arc::new(self.layout(entries))
+    }
+
+    fn shared_pipeline_layout(
+        &self, push_constant_ranges: &[wgpu::PushConstantRange], bind_group_layouts: &[&wgpu::BindGroupLayout],
+    ) -> Arc<wgpu::PipelineLayout> {
+        Arc::new(self.pipeline_layout(push_constant_ranges, bind_group_layouts))
+    }
+
+    fn shared_sampler(&self, descriptor: &SamplerDsc) -> Arc<wgpu::Sampler> {
+        Arc::new(self.sampler_dsc(descriptor))
+    }
+
+    fn shared_wgsl<'a>(&self, code: impl Into<Cow<'a, str>>) -> Arc<wgpu::ShaderModule> {
+        Arc::new(self.load_wgsl(code))
+    }
+
+    fn shared_compute_pipeline(
+        &self, layout:Option<&wgpu::PipelineLayout>, module_entry:(&wgpu::ShaderModule, &str),
+    ) -> Arc<wgpu::ComputePipeline> {
+        Arc::new(self.compute_pipeline_with_layout(layout, module_entry))
+    }
+
+    fn shared_render_pipeline<const S: usize, T: ColorTarget>(
+        &self,
+        msaa: u32, depth_stencil: Option<wgpu::DepthStencilState>,
+        layout: Option<&wgpu::PipelineLayout>,
+        buffers: &[wgpu::VertexBufferLayout],
+        vertex: (&wgpu::ShaderModule, &str, wgpu::PrimitiveState),
+        fragment: Option<FragmentDsc<S, T>>,
+    ) -> Arc<wgpu::RenderPipeline> {
+        Arc::new(self.render_pipeline_with_state(msaa, depth_stencil, layout, buffers, vertex, fragment))
+    }
+}
+
+
+// default depth testing state
+pub fn depth_state(format: wgpu::TextureFormat) -> wgpu::DepthStencilState {
+    wgpu::DepthStencilState {
+        format,
+        depth_write_enabled: true,
+        depth_compare: wgpu::CompareFunction::LessEqual,
+        stencil: wgpu::StencilState::default(),
+        bias: wgpu::DepthBiasState::default(),
+    }
 }
 
 
@@ -333,7 +449,10 @@ pub trait WgxDeviceQueue: WgxDevice + WgxQueue {
 
 impl<T: WgxDevice + WgxQueue> WgxDeviceQueue for T {}
 
-impl WgxDevice for Wgx { fn device(&self) -> &wgpu::Device { &self.device } }
+impl WgxDevice for Wgx {
+    fn device(&self) -> &wgpu::Device { &self.device }
+    fn adapter(&self) -> Option<&wgpu::Adapter> { Some(&self.adapter) }
+}
 impl WgxQueue for Wgx { fn queue(&self) -> &wgpu::Queue { &self.queue } }
 
 impl WgxDevice for wgpu::Device { fn device(&self) -> &wgpu::Device { self } }
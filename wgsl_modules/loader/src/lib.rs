@@ -9,6 +9,9 @@ use naga::{FastHashMap, FastHashSet};
 use naga::{front::wgsl, valid::{ValidationFlags, Validator, Capabilities}};
 use anyhow::{Result as Res, Context, anyhow, bail};
 
+mod packages;
+pub use packages::*;
+
 
 #[derive(Debug)]
 struct Include { path: Box<Path>, source_range: Range<usize> }
@@ -112,10 +115,17 @@ fn normpath(path: &Path) -> Box<Path> {
 // modules
 
 #[derive(Default)]
-pub struct ModuleCache { map: FastHashMap<Box<Path>, Module> }
+pub struct ModuleCache { map: FastHashMap<Box<Path>, Module>, packages: Packages }
 
 impl ModuleCache {
 
+    fn include_path(&self, dir_path: &Path, include_path: &Path) -> Box<Path> {
+        match self.packages.resolve(include_path) {
+            Some(package_path) => normpath(&package_path),
+            None => normpath(&dir_path.join(include_path)),
+        }
+    }
+
     fn insert_and_get(&mut self, key: Box<Path>, module: Module) -> &Module {
         match self.map.entry(key) {
             Entry::Occupied(mut occupied) => {
@@ -161,7 +171,7 @@ impl Module {
 
         for include in self.includes.iter().rev() {
 
-            let include_path = normpath(&dir_path.join(&include.path));
+            let include_path = cache.include_path(dir_path, &include.path);
             let include_dir_path = parent_path(&include_path)?;
 
             let module = cache.resolve_module(module_trace, &include_path)?;
@@ -241,6 +251,23 @@ impl ModuleCache {
 
     pub fn new() -> Self { Self::default() }
 
+    pub fn with_packages(packages: Packages) -> Self {
+        Self { packages, ..Self::default() }
+    }
+
+    // packages
+
+    pub fn packages(&self) -> &Packages { &self.packages }
+    pub fn packages_mut(&mut self) -> &mut Packages { &mut self.packages }
+    pub fn set_packages(&mut self, packages: Packages) { self.packages = packages }
+
+    pub fn register_package(&mut self, name: impl AsRef<str>, root: impl AsRef<Path>) -> Res<()> {
+        self.packages.insert(name, root)?;
+        Ok(())
+    }
+
+    // modules
+
     pub fn module(&self, path: impl AsRef<Path>) -> Option<&Module> {
         self.map.get(path.as_ref())
     }
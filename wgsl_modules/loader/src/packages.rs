@@ -0,0 +1,178 @@
+use std::{
+    path::{Path, PathBuf}, fs::{read_to_string, write}, env,
+};
+use naga::FastHashMap;
+use anyhow::{Result as Res, Context, bail};
+
+
+// env variable pointing to the packages manifest of the crate being compiled
+pub const PACKAGES_ENV: &str = "WGSL_MODULES_PACKAGES";
+
+// cargo metadata key, passed to dependents as DEP_<LINKS>_WGSL_PACKAGE_<NAME>
+const METADATA_KEY: &str = "wgsl_package";
+
+const MANIFEST_FILE: &str = "wgsl_packages.manifest";
+
+
+fn is_package_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_') &&
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn parse_entry(entry: &str) -> Res<(&str, &Path)> {
+    let (name, root) = entry.split_once('=').with_context(|| format!("invalid package entry '{entry}'"))?;
+    let (name, root) = (name.trim(), root.trim());
+    if !is_package_name(name) { bail!("invalid package name '{name}'") }
+    if root.is_empty() { bail!("missing root path for package '{name}'") }
+    Ok((name, root.as_ref()))
+}
+
+
+// named shader packages, included with `&include "package_name::path"`
+
+#[derive(Debug, Default, Clone)]
+pub struct Packages { map: FastHashMap<Box<str>, Box<Path>> }
+
+impl Packages {
+
+    pub fn new() -> Self { Self::default() }
+
+    pub fn insert(&mut self, name: impl AsRef<str>, root: impl AsRef<Path>) -> Res<Option<Box<Path>>> {
+        let name = name.as_ref();
+        if !is_package_name(name) { bail!("invalid package name '{name}'") }
+        Ok(self.map.insert(name.into(), root.as_ref().into()))
+    }
+
+    pub fn remove(&mut self, name: impl AsRef<str>) -> Option<Box<Path>> {
+        self.map.remove(name.as_ref())
+    }
+
+    pub fn root(&self, name: impl AsRef<str>) -> Option<&Path> {
+        self.map.get(name.as_ref()).map(|root| root.as_ref())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=(&str, &Path)> {
+        self.map.iter().map(|(name, root)| (name.as_ref(), root.as_ref()))
+    }
+
+    pub fn is_empty(&self) -> bool { self.map.is_empty() }
+
+    pub fn extend(&mut self, other: Packages) { self.map.extend(other.map) }
+
+
+    // resolve "package_name::path", returns None if the prefix is not a registered package
+    pub fn resolve(&self, include_path: &Path) -> Option<PathBuf> {
+        let (name, path) = include_path.to_str()?.split_once("::")?;
+        let root = self.map.get(name)?;
+        Some(root.join(path.trim_start_matches('/')))
+    }
+
+
+    // manifest, one `name=root_path` per line
+
+    pub fn parse_manifest(manifest: &str) -> Res<Self> {
+        let mut packages = Self::new();
+
+        for line in manifest.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let (name, root) = parse_entry(line)?;
+            packages.insert(name, root)?;
+        }
+
+        Ok(packages)
+    }
+
+    pub fn load_manifest(path: impl AsRef<Path>) -> Res<Self> {
+        let path = path.as_ref();
+        let manifest = read_to_string(path).with_context(
+            || format!("failed loading packages manifest from path '{}'", path.display())
+        )?;
+        Self::parse_manifest(&manifest)
+    }
+
+    pub fn manifest(&self) -> String {
+        let mut entries: Vec<_> = self.iter().collect();
+        entries.sort_unstable_by_key(|(name, _)| *name);
+        entries.iter().map(|(name, root)| format!("{name}={}\n", root.display())).collect()
+    }
+
+    // manifest path set by a build script with `write_manifest` or `import_packages`
+    pub fn manifest_path_from_env() -> Option<PathBuf> {
+        env::var_os(PACKAGES_ENV).map(PathBuf::from)
+    }
+
+    pub fn from_env() -> Res<Self> {
+        match Self::manifest_path_from_env() {
+            Some(path) => Self::load_manifest(path),
+            None => Ok(Self::new()),
+        }
+    }
+
+    // packages exported by direct dependencies, only available inside build scripts
+    pub fn from_dependencies() -> Res<Self> {
+        let mut packages = Self::new();
+
+        for (key, value) in env::vars() {
+            if key.starts_with("DEP_") && key.contains("_WGSL_PACKAGE_") {
+                let (name, root) = parse_entry(&value).with_context(|| format!("in '{key}'"))?;
+                packages.insert(name, root)?;
+            }
+        }
+
+        Ok(packages)
+    }
+}
+
+
+// build script helpers
+pub mod build {
+
+    use super::*;
+
+    fn out_dir() -> Res<PathBuf> {
+        env::var_os("OUT_DIR").map(PathBuf::from).context("OUT_DIR not set, not called from a build script?")
+    }
+
+    fn manifest_dir() -> Res<PathBuf> {
+        env::var_os("CARGO_MANIFEST_DIR").map(PathBuf::from).context("CARGO_MANIFEST_DIR not set, not called from a build script?")
+    }
+
+    // export a shader directory of this crate as package to direct dependents,
+    // requires the `links` key to be set in the package manifest
+    pub fn export_package(name: &str, dir: impl AsRef<Path>) -> Res<PathBuf> {
+
+        if !is_package_name(name) { bail!("invalid package name '{name}'") }
+
+        if env::var_os("CARGO_MANIFEST_LINKS").is_none() {
+            bail!("exporting wgsl package '{name}' requires the `links` key in Cargo.toml")
+        }
+
+        let root = manifest_dir()?.join(dir);
+
+        if !root.is_dir() { bail!("package root '{}' is not a directory", root.display()) }
+
+        println!("cargo:{METADATA_KEY}_{name}={name}={}", root.display());
+
+        Ok(root)
+    }
+
+    // write the manifest to OUT_DIR and make it available to the include!/inline! macros
+    pub fn write_manifest(packages: &Packages) -> Res<PathBuf> {
+        let path = out_dir()?.join(MANIFEST_FILE);
+
+        write(&path, packages.manifest()).with_context(
+            || format!("failed writing packages manifest to '{}'", path.display())
+        )?;
+
+        println!("cargo:rustc-env={PACKAGES_ENV}={}", path.display());
+
+        Ok(path)
+    }
+
+    // import all packages exported by direct dependencies
+    pub fn import_packages() -> Res<Packages> {
+        let packages = Packages::from_dependencies()?;
+        write_manifest(&packages)?;
+        Ok(packages)
+    }
+}
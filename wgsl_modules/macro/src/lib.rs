@@ -1,9 +1,9 @@
-#![feature(proc_macro_span, track_path)]
+#![cfg_attr(feature = "nightly", feature(track_path))]
 
-use std::{cell::RefCell, path::Path};
-use wgsl_modules_loader::{Module, ModuleCache};
+use std::{cell::RefCell, path::{Path, PathBuf}};
+use wgsl_modules_loader::{Module, ModuleCache, Packages};
 
-use proc_macro::{TokenStream, TokenTree, Literal, Span, tracked_path};
+use proc_macro::{TokenStream, TokenTree};
 use syn::{parse_macro_input, LitStr};
 use quote::quote;
 
@@ -13,27 +13,100 @@ use anyhow::{Result as Res};
 thread_local!(static CACHE: RefCell<ModuleCache> = ModuleCache::new().into());
 
 
+// caller directory and file tracking
+
+mod caller {
+    use super::*;
+    use proc_macro::Span;
+
+    // paths are relative to the calling source file like with include_str!, the manifest directory of
+    // the calling crate is only used when the compiler can't name the file (e.g. generated code)
+    // Span::local_file needs rust 1.88
+    pub fn dir_path() -> PathBuf {
+        match Span::call_site().local_file() {
+            Some(file_path) => {
+                // rustc reports paths relative to its working directory
+                let file_path = std::env::current_dir().map(|dir| dir.join(&file_path)).unwrap_or(file_path);
+                file_path.parent().map(Path::to_owned).unwrap_or_default()
+            },
+            None => std::env::var_os("CARGO_MANIFEST_DIR").map(PathBuf::from).unwrap_or_default(),
+        }
+    }
+
+    // files can only be tracked by utf-8 paths
+    fn tracked_paths(tracked: &[PathBuf]) -> Result<Vec<&str>, TokenStream> {
+        tracked.iter().map(|path| path.to_str().ok_or_else(|| {
+            let err = format!("can't track the non utf-8 path {}", path.display());
+            quote!(compile_error!(#err)).into()
+        })).collect()
+    }
+
+    #[cfg(feature = "nightly")]
+    pub fn output(code: &str, tracked: &[PathBuf]) -> TokenStream {
+        use proc_macro::{Literal, tracked_path};
+
+        let tracked = match tracked_paths(tracked) {
+            Ok(tracked) => tracked,
+            Err(err) => return err,
+        };
+        for path in tracked {
+            tracked_path::path(path);
+        }
+        TokenTree::from(Literal::string(code)).into()
+    }
+
+    // include_str! every tracked file so cargo rebuilds when it changes
+    // the result is a block expression, not a literal, so it can't be used in macros like concat!
+    #[cfg(not(feature = "nightly"))]
+    pub fn output(code: &str, tracked: &[PathBuf]) -> TokenStream {
+        match tracked_paths(tracked) {
+            Ok(tracked) => quote!({ #(const _: &str = include_str!(#tracked);)* #code }).into(),
+            Err(err) => err,
+        }
+    }
+}
+
+
 // helper
-fn handle_result(res: Res<&Module>, path: &Path) -> TokenStream {
+fn with_cache(
+    dir_path: &Path, path: &str, resolve_package: bool,
+    load: impl for<'c> FnOnce(&'c mut ModuleCache, &Path) -> Res<&'c Module>,
+) -> TokenStream {
+    CACHE.with_borrow_mut(|cache| {
+
+        // packages of the crate being compiled
+        if let Err(err) = Packages::from_env().map(|packages| cache.set_packages(packages)) {
+            let err = format!("{err:?}");
+            return quote!(compile_error!(#err)).into()
+        }
+
+        let manifest_path = Packages::manifest_path_from_env();
+
+        let path = match resolve_package.then(|| cache.packages().resolve(path.as_ref())).flatten() {
+            Some(package_path) => package_path,
+            None => dir_path.join(path),
+        };
+
+        handle_result(load(cache, &path), &path, manifest_path)
+    })
+}
+
+fn handle_result(res: Res<&Module>, path: &Path, manifest_path: Option<PathBuf>) -> TokenStream {
     match res.and_then(|module| {
         // validate naga_module
         module.naga_module(true)?;
         Ok(module)
     }) {
         Ok(module) => {
-            // track source code files
-            if path.exists() {
-                tracked_path::path(path.to_str().unwrap());
-            }
-
-            for file_path in module.dependencies() {
-                // may be put in cache manually
-                if file_path.exists() {
-                    tracked_path::path(file_path.to_str().unwrap());
-                }
-            }
-
-            TokenTree::from(Literal::string(module.code())).into()
+            // track source code files, dependencies may be put in cache manually
+            let tracked: Vec<PathBuf> = [path].into_iter()
+                .chain(module.dependencies())
+                .map(Path::to_owned)
+                .chain(manifest_path)
+                .filter(|file_path| file_path.exists())
+                .collect();
+
+            caller::output(module.code(), &tracked)
         },
         Err(err) => {
             let err = format!("{err:?}");
@@ -47,19 +120,17 @@ fn handle_result(res: Res<&Module>, path: &Path) -> TokenStream {
 #[proc_macro]
 pub fn include(input: TokenStream) -> TokenStream {
 
-    let dir_path = Span::call_site().source_file().path().parent().unwrap().to_owned();
-    let path = dir_path.join(parse_macro_input!(input as LitStr).value());
+    let dir_path = caller::dir_path();
+    let path = parse_macro_input!(input as LitStr).value();
 
-    CACHE.with_borrow_mut(|cache| {
-        handle_result(cache.load_from_path(&path), &path)
-    })
+    with_cache(&dir_path, &path, true, |cache, path| cache.load_from_path(path))
 }
 
 
 
 use quote::quote_spanned;
 use syn::token::Le;
-use proc_macro::{Delimiter};
+use proc_macro::{Delimiter, Span};
 
 
 // get the next token or return error
@@ -80,14 +151,14 @@ macro_rules! next {
 #[proc_macro]
 pub fn inline(input: TokenStream) -> TokenStream {
 
-    let dir_path = Span::call_site().source_file().path().parent().unwrap().to_owned();
+    let dir_path = caller::dir_path();
 
     let mut input = input.into_iter();
     let mut span = Span::call_site();
 
     // parse path
     let path_token = next!(span, input).into();
-    let path = dir_path.join(parse_macro_input!(path_token as LitStr).value());
+    let path = parse_macro_input!(path_token as LitStr).value();
 
     // parse le
     let le_token = TokenStream::from_iter([next!(span, input), next!(span, input)]);
@@ -106,7 +177,5 @@ pub fn inline(input: TokenStream) -> TokenStream {
         },
     };
 
-    CACHE.with_borrow_mut(|cache| {
-        handle_result(cache.load(&path, source), &path)
-    })
+    with_cache(&dir_path, &path, false, |cache, path| cache.load(path, source))
 }
\ No newline at end of file
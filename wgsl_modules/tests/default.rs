@@ -1,7 +1,4 @@
-#![feature(assert_matches)]
-
-use std::assert_matches::assert_matches;
-use wgsl_modules::{Module, ModuleCache, inline};
+use wgsl_modules::{Module, ModuleCache, Packages, inline};
 use proc_macro2::TokenStream;
 use std::str::FromStr;
 
@@ -42,7 +39,7 @@ fn circular_includes() {
 
     let res = Module::load_from_path("../wgsl_modules/shaders/circular.wgsl");
 
-    assert_matches!(res, Err(err) if err.to_string().starts_with("circular dependency"));
+    assert!(matches!(res, Err(err) if err.to_string().starts_with("circular dependency")));
 }
 
 
@@ -51,7 +48,7 @@ fn nonexistent_includes() {
 
     let res = Module::load_from_path("shaders/nonexistent.wgsl");
 
-    assert_matches!(res, Err(err) if err.to_string().starts_with("failed loading module from path"));
+    assert!(matches!(res, Err(err) if err.to_string().starts_with("failed loading module from path")));
 }
 
 
@@ -60,7 +57,7 @@ fn invalid_path() {
 
     let res = Module::load_from_path("");
 
-    assert_matches!(res, Err(err) if err.to_string().starts_with("invalid path"));
+    assert!(matches!(res, Err(err) if err.to_string().starts_with("invalid path")));
 }
 
 
@@ -83,6 +80,40 @@ fn inline_loading_into_cache() {
 }
 
 
+#[test]
+fn package_including() {
+
+    let mut modules = ModuleCache::new();
+
+    modules.register_package("shaders", "../wgsl_modules/shaders").unwrap();
+
+    let module = modules.load("$module", stringify!{
+        &include "shaders::util.wgsl";
+    }).unwrap();
+
+    tokens_eq!(module.code(), include_str!("../shaders/util.wgsl"));
+}
+
+
+#[test]
+fn packages_manifest() {
+
+    let packages = Packages::parse_manifest("
+        # comment
+        shaders = ../wgsl_modules/shaders
+        other_lib=/some/path
+    ").unwrap();
+
+    assert_eq!(packages.root("shaders").unwrap().to_str(), Some("../wgsl_modules/shaders"));
+    assert_eq!(packages.root("other_lib").unwrap().to_str(), Some("/some/path"));
+
+    let reparsed = Packages::parse_manifest(&packages.manifest()).unwrap();
+    assert_eq!(reparsed.manifest(), packages.manifest());
+
+    assert!(matches!(Packages::parse_manifest("0invalid=path"), Err(err) if err.to_string().starts_with("invalid package name")));
+}
+
+
 #[test]
 fn inline_registering() {
 
@@ -138,7 +169,7 @@ fn naga_parsing_failing() {
         module.naga_module(false)
     });
 
-    assert_matches!(res, Err(err) if err.to_string().starts_with("error: expected global item"));
+    assert!(matches!(res, Err(err) if err.to_string().starts_with("error: expected global item")));
 }
 
 
@@ -150,5 +181,5 @@ fn naga_validation_failing() {
         module.naga_module(true)
     });
 
-    assert_matches!(res, Err(err) if err.to_string().starts_with("error: Entry point vs_main at Vertex is invalid"));
+    assert!(matches!(res, Err(err) if err.to_string().starts_with("error: Entry point vs_main at Vertex is invalid")));
 }
\ No newline at end of file